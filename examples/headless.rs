@@ -0,0 +1,51 @@
+//! No window at all, a plugin that spawns some entities on a grid and quits after a few frames.
+//!
+//! ```sh
+//! cargo run --example headless
+//! ```
+
+use std::process::ExitCode;
+
+use gristmill::{ecs::spatial::spatial_partial, logging::setup_logging, prelude::*};
+
+fn wander(world: &World) -> Result<()> {
+    let frame = world.get_resource::<Time>().frame_count();
+
+    for (entity, mut position) in world.query_mut::<GridPosition>() {
+        position.x += 1;
+        debug!(
+            "{} is at {}, {}",
+            world.label(entity),
+            position.x,
+            position.y
+        );
+    }
+
+    if frame >= 10 {
+        let index = world.get_resource::<SpatialIndex>();
+        info!(
+            "{} entities within 5 tiles of the origin",
+            index.in_radius(GridPosition::new(0, 0), 5.0).len()
+        );
+        world.exit(AppExit::Success);
+    }
+    Ok(())
+}
+
+fn bugs_partial() -> PartialManager {
+    PartialManager::new()
+        .add_bundle((Name::new("Bug"), GridPosition::new(0, 0)))
+        .add_bundle((Name::new("Beetle"), GridPosition::new(-8, 2)))
+        .add_systems((wander as System,).order_up())
+}
+
+fn main() -> Result<ExitCode> {
+    setup_logging();
+
+    let exit = Manager::new()?
+        .integrate(spatial_partial::<GridPosition>())?
+        .integrate(bugs_partial())?
+        .run_headless()?;
+
+    Ok(exit.into())
+}
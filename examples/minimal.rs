@@ -0,0 +1,33 @@
+//! The smallest windowed app, the renderer and a system counting fixed ticks.
+//!
+//! ```sh
+//! cargo run --example minimal
+//! ```
+
+use std::process::ExitCode;
+
+use gristmill::{engine::engine_partial, logging::setup_logging, prelude::*};
+
+#[derive(Default)]
+struct Ticks(u64);
+
+fn count_ticks(world: &World) -> Result<()> {
+    let mut ticks = world.get_resource_mut::<Ticks>();
+    ticks.0 += 1;
+    if ticks.0.is_multiple_of(60) {
+        info!("{} fixed ticks", ticks.0);
+    }
+    Ok(())
+}
+
+fn main() -> Result<ExitCode> {
+    setup_logging();
+
+    let exit = Manager::new()?
+        .integrate(engine_partial())?
+        .add_resource(Ticks::default())
+        .add_fixed_systems((count_ticks as System,).order_up())
+        .run()?;
+
+    Ok(exit.into())
+}
@@ -0,0 +1,91 @@
+//! Derive macros for gristmill, these live in their own crate because proc macros have to.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Fields, Index, parse_macro_input, spanned::Spanned};
+
+/// Implements `Bundle` for a struct, every field becomes a component.
+///
+/// Fields marked with `#[bundle]` are bundles themselves and get flattened into this one.
+///
+/// # Example
+/// ```rs
+/// #[derive(Bundle)]
+/// struct BugBundle {
+///     position: GridPosition,
+///     health: Health,
+///     #[bundle]
+///     sprite: SpriteBundle,
+/// }
+/// ```
+#[proc_macro_derive(Bundle, attributes(bundle))]
+pub fn derive_bundle(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match bundle_impl(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn bundle_impl(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(
+            input.span(),
+            "Bundle can only be derived for structs",
+        ));
+    };
+
+    let fields: Vec<_> = match &data.fields {
+        Fields::Named(fields) => fields.named.iter().collect(),
+        Fields::Unnamed(fields) => fields.unnamed.iter().collect(),
+        Fields::Unit => {
+            return Err(Error::new(
+                input.span(),
+                "Bundle can't be derived for unit structs, they have no components",
+            ));
+        }
+    };
+
+    let mut pushes = vec![];
+    for (i, field) in fields.iter().enumerate() {
+        let member = match &field.ident {
+            Some(ident) => quote!(#ident),
+            None => {
+                let index = Index::from(i);
+                quote!(#index)
+            }
+        };
+        let ty = &field.ty;
+
+        let nested = field.attrs.iter().any(|a| a.path().is_ident("bundle"));
+        pushes.push(if nested {
+            quote! {
+                components.extend(::gristmill::ecs::bundle::Bundle::into_components(self.#member));
+            }
+        } else {
+            quote! {
+                components.push((
+                    ::std::any::TypeId::of::<#ty>(),
+                    ::std::boxed::Box::new(self.#member) as ::std::boxed::Box<dyn ::std::any::Any>,
+                ));
+            }
+        });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::gristmill::ecs::bundle::Bundle for #name #ty_generics #where_clause {
+            fn into_components(
+                self,
+            ) -> ::std::vec::Vec<(::std::any::TypeId, ::std::boxed::Box<dyn ::std::any::Any>)> {
+                let mut components = ::std::vec::Vec::new();
+                #(#pushes)*
+                components
+            }
+        }
+    })
+}
@@ -0,0 +1,41 @@
+//! Bare bones command line parsing, just enough for a few debug flags.
+
+use std::{env, fmt::Display, str::FromStr};
+
+use log::*;
+
+/// The value of `--name <value>` or `--name=<value>`, the first one wins.
+pub fn value(name: &str) -> Option<String> {
+    let flag = format!("--{name}");
+    let prefix = format!("--{name}=");
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if let Some(value) = arg.strip_prefix(&prefix) {
+            return Some(value.to_string());
+        }
+        if arg == flag {
+            return args.next();
+        }
+    }
+
+    None
+}
+
+/// Whether `--name` was passed.
+pub fn flag(name: &str) -> bool {
+    let flag = format!("--{name}");
+    env::args().skip(1).any(|arg| arg == flag)
+}
+
+/// The value of `--name` parsed into a `T`. Values that don't parse are warned about and ignored.
+pub fn parse<T: FromStr>(name: &str) -> Option<T>
+where
+    T::Err: Display,
+{
+    let value = value(name)?;
+    value
+        .parse()
+        .inspect_err(|err| warn!("Ignoring --{name} {value}: {err}"))
+        .ok()
+}
@@ -0,0 +1,521 @@
+//! Settings for the window and renderer, from a config file with command line flags on top.
+//!
+//! The file is `engine.toml` or `engine.ron` in `$XDG_CONFIG_HOME/gristmill` (or
+//! `~/.config/gristmill`), or whatever `--config <path>` points at. Anything left out of it keeps
+//! its default. Then the flags:
+//!
+//! ```sh
+//! gristmill --width 1920 --height 1080 --window-mode exclusive --monitor DP-2 \
+//!     --present-mode low-latency --fps-cap 144 --gpu "RTX 3070" --log-level debug --seed 42
+//! ```
+//!
+//! Where the window was and how big it was is saved to `window.toml` next to it on exit and used
+//! over the config file next time, unless `remember_window` is turned off. Flags still win.
+//!
+//! # Example `engine.toml`
+//! ```toml
+//! width = 1600
+//! height = 900
+//! present_mode = "uncapped"
+//! # 0 is no cap
+//! fps_cap = 240
+//!
+//! [camera]
+//! pan_speed = 12.0
+//! edge_scroll_margin = 0.0
+//! ```
+
+use std::{
+    env,
+    fmt::{self, Display},
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow};
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cli,
+    ecs::{
+        camera_controller::CameraControls,
+        reflect::{Reflect, ReflectValue, impl_reflect},
+    },
+    logging,
+};
+
+/// Name of the directory in the config dir everything of ours goes in.
+pub const CONFIG_DIR_NAME: &str = "gristmill";
+
+/// The engine's settings, a resource once the game is running.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineConfig {
+    pub title: String,
+    /// Window size in logical pixels, when windowed.
+    pub width: u32,
+    pub height: u32,
+    /// Where the top left corner of the window goes when windowed, the OS picks if unset.
+    pub position: Option<[i32; 2]>,
+    /// Alt+Enter switches between this and windowed. Can be changed while running.
+    pub window_mode: WindowMode,
+    /// Go fullscreen on the first monitor with this in its name, or with this index. The primary
+    /// monitor if unset or not found.
+    pub monitor: Option<String>,
+    /// Save the window's position, size, mode and monitor on exit and start with them next time.
+    pub remember_window: bool,
+    /// How finished frames wait for the display. Can be changed while running, the swapchain is
+    /// remade on the next frame.
+    pub present_mode: PresentMode,
+    /// Most frames a second to draw, `0` for no cap. Works with any present mode.
+    pub fps_cap: u32,
+    /// Use the first GPU with this in its name, or with this index. Picked automatically if
+    /// unset or not found.
+    pub gpu: Option<String>,
+    /// `error`, `warn`, `info`, `debug`, `trace` or `off`. `RUST_LOG` is used if unset.
+    pub log_level: Option<String>,
+    /// What the [`GameRng`](crate::ecs::rng::GameRng) starts from, a new seed every run if unset.
+    pub seed: Option<u64>,
+    /// How the camera pans and zooms, the `[camera]` table. The game copies it into the
+    /// [`CameraControls`] resource at startup and only uses that copy from then on, so changing
+    /// this while running does nothing.
+    pub camera: CameraControls,
+}
+
+impl_reflect!(EngineConfig {
+    title,
+    width,
+    height,
+    position,
+    window_mode,
+    monitor,
+    remember_window,
+    present_mode,
+    fps_cap,
+    gpu,
+    log_level,
+    seed,
+    camera,
+});
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            title: "Factory Game".to_string(),
+            width: 1024,
+            height: 768,
+            position: None,
+            window_mode: WindowMode::Windowed,
+            monitor: None,
+            remember_window: true,
+            present_mode: PresentMode::Vsync,
+            fps_cap: 0,
+            gpu: None,
+            log_level: None,
+            seed: None,
+            camera: CameraControls::default(),
+        }
+    }
+}
+
+impl EngineConfig {
+    /// Read the config file and the saved window state, apply the command line flags and set the
+    /// log level.
+    ///
+    /// No config file is fine, a broken one is an error. A broken window state is only a warning,
+    /// it's ours and gets overwritten on exit anyway.
+    pub fn load() -> Result<Self> {
+        let path = cli::value("config")
+            .map(PathBuf::from)
+            .or_else(Self::default_path);
+
+        let mut config = match path {
+            Some(path) => Self::load_file(&path)?,
+            None => {
+                debug!("No engine config file, using the defaults");
+                Self::default()
+            }
+        };
+        if config.remember_window
+            && let Some(state) = WindowState::load()
+        {
+            state.apply(&mut config);
+        }
+        config.apply_args();
+
+        if let Some(level) = &config.log_level {
+            logging::set_level(level);
+        }
+
+        Ok(config)
+    }
+
+    /// `engine.toml` or `engine.ron` in the config dir, whichever exists.
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = config_dir()?;
+        ["engine.toml", "engine.ron"]
+            .into_iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.exists())
+    }
+
+    /// Load a TOML or RON file, which one is decided by the extension.
+    pub fn load_file(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| anyhow!("Couldn't read {}: {err}", path.display()))?;
+
+        let config = Self::parse(&text, path.extension().and_then(|e| e.to_str()))
+            .map_err(|err| anyhow!("Couldn't parse {}: {err}", path.display()))?;
+
+        info!("Loaded engine config from {}", path.display());
+        Ok(config)
+    }
+
+    /// Parse a config as RON if `extension` is `ron`, TOML otherwise.
+    fn parse(text: &str, extension: Option<&str>) -> Result<Self> {
+        match extension {
+            Some("ron") => ron::from_str(text).map_err(|err| anyhow!("{err}")),
+            _ => toml::from_str(text).map_err(|err| anyhow!("{err}")),
+        }
+    }
+
+    /// Override whatever was passed on the command line.
+    pub fn apply_args(&mut self) {
+        if let Some(title) = cli::value("title") {
+            self.title = title;
+        }
+        if let Some(width) = cli::parse("width") {
+            self.width = width;
+        }
+        if let Some(height) = cli::parse("height") {
+            self.height = height;
+        }
+        if cli::flag("fullscreen") {
+            self.window_mode = WindowMode::Borderless;
+        }
+        if cli::flag("windowed") {
+            self.window_mode = WindowMode::Windowed;
+        }
+        if let Some(window_mode) = cli::parse("window-mode") {
+            self.window_mode = window_mode;
+        }
+        if let Some(monitor) = cli::value("monitor") {
+            self.monitor = Some(monitor);
+        }
+        if cli::flag("vsync") {
+            self.present_mode = PresentMode::Vsync;
+        }
+        if cli::flag("no-vsync") {
+            self.present_mode = PresentMode::Uncapped;
+        }
+        if let Some(present_mode) = cli::parse("present-mode") {
+            self.present_mode = present_mode;
+        }
+        if let Some(fps_cap) = cli::parse("fps-cap") {
+            self.fps_cap = fps_cap;
+        }
+        if let Some(gpu) = cli::value("gpu") {
+            self.gpu = Some(gpu);
+        }
+        if let Some(level) = cli::value("log-level") {
+            self.log_level = Some(level);
+        }
+        if let Some(seed) = cli::parse("seed") {
+            self.seed = Some(seed);
+        }
+    }
+
+    /// Shortest time a frame can take with the FPS cap, `None` if there's no cap.
+    pub fn min_frame_time(&self) -> Option<Duration> {
+        (self.fps_cap > 0).then(|| Duration::from_secs(1) / self.fps_cap)
+    }
+}
+
+/// How frames are handed to the display. Falls back to [`PresentMode::Vsync`] when the GPU doesn't
+/// support the one asked for, since every GPU supports that.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PresentMode {
+    /// Wait for the display to refresh, no tearing but up to a frame of extra latency (FIFO).
+    #[default]
+    Vsync,
+    /// Replace the waiting frame with newer ones, no tearing and less latency (MAILBOX).
+    LowLatency,
+    /// Show frames as soon as they're done and tear (IMMEDIATE).
+    Uncapped,
+}
+
+impl Display for PresentMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PresentMode::Vsync => "vsync",
+            PresentMode::LowLatency => "low-latency",
+            PresentMode::Uncapped => "uncapped",
+        })
+    }
+}
+
+impl FromStr for PresentMode {
+    type Err = anyhow::Error;
+
+    /// Also takes the Vulkan names.
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "vsync" | "fifo" => Ok(PresentMode::Vsync),
+            "low-latency" | "mailbox" => Ok(PresentMode::LowLatency),
+            "uncapped" | "immediate" => Ok(PresentMode::Uncapped),
+            _ => Err(anyhow!(
+                "Unknown present mode \"{s}\", expected vsync, low-latency or uncapped"
+            )),
+        }
+    }
+}
+
+/// Whether the window is a window, or which kind of fullscreen it is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    /// A window without decorations covering the whole monitor. Switches instantly.
+    Borderless,
+    /// Takes over the monitor at its native resolution, slower to switch in and out of.
+    Exclusive,
+}
+
+impl WindowMode {
+    pub fn is_fullscreen(&self) -> bool {
+        *self != WindowMode::Windowed
+    }
+}
+
+impl Display for WindowMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            WindowMode::Windowed => "windowed",
+            WindowMode::Borderless => "borderless",
+            WindowMode::Exclusive => "exclusive",
+        })
+    }
+}
+
+impl FromStr for WindowMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "windowed" => Ok(WindowMode::Windowed),
+            "borderless" | "fullscreen" => Ok(WindowMode::Borderless),
+            "exclusive" => Ok(WindowMode::Exclusive),
+            _ => Err(anyhow!(
+                "Unknown window mode \"{s}\", expected windowed, borderless or exclusive"
+            )),
+        }
+    }
+}
+
+/// Reflects enums as their name so they can be switched from the inspector or with
+/// `world.set_resource_path("engine_config", "present_mode", "uncapped")`.
+macro_rules! impl_reflect_by_name {
+    ($($ty:ty),*) => {
+        $(
+            impl Reflect for $ty {
+                fn value(&self) -> ReflectValue {
+                    ReflectValue::String(self.to_string())
+                }
+
+                fn set_value(&mut self, value: ReflectValue) -> Result<()> {
+                    match value {
+                        ReflectValue::String(s) => *self = s.parse()?,
+                        _ => return Err(anyhow!("Expected a name, got {value}")),
+                    }
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_reflect_by_name!(PresentMode, WindowMode);
+
+/// How the window was left last time, saved to `window.toml` in the config dir.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowState {
+    pub width: u32,
+    pub height: u32,
+    pub position: Option<[i32; 2]>,
+    pub window_mode: WindowMode,
+    pub monitor: Option<String>,
+}
+
+impl WindowState {
+    pub fn path() -> Option<PathBuf> {
+        Some(config_dir()?.join("window.toml"))
+    }
+
+    /// The saved state, `None` if there isn't one or it can't be read.
+    pub fn load() -> Option<Self> {
+        let path = Self::path().filter(|path| path.exists())?;
+        let state = fs::read_to_string(&path)
+            .map_err(|err| anyhow!("{err}"))
+            .and_then(|text| toml::from_str(&text).map_err(|err| anyhow!("{err}")))
+            .inspect_err(|err| {
+                warn!(
+                    "Ignoring the saved window state in {}: {err}",
+                    path.display()
+                )
+            })
+            .ok()?;
+        debug!("Loaded the window state from {}", path.display());
+        Some(state)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path =
+            Self::path().ok_or_else(|| anyhow!("No config dir to save the window state in"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, toml::to_string(self)?)?;
+        debug!("Saved the window state to {}", path.display());
+        Ok(())
+    }
+
+    /// Put the saved state over `config`, a size of zero means it wasn't known.
+    pub fn apply(&self, config: &mut EngineConfig) {
+        if self.width > 0 && self.height > 0 {
+            config.width = self.width;
+            config.height = self.height;
+        }
+        config.position = self.position;
+        config.window_mode = self.window_mode;
+        config.monitor = self.monitor.clone();
+    }
+}
+
+/// `$XDG_CONFIG_HOME/gristmill`, or `~/.config/gristmill` if that isn't set.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join(CONFIG_DIR_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_toml_and_leaves_out_keys_at_their_default() {
+        let config = EngineConfig::parse(
+            r#"
+            width = 1600
+            height = 900
+            window_mode = "borderless"
+            present_mode = "low-latency"
+            seed = 42
+            "#,
+            Some("toml"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config,
+            EngineConfig {
+                width: 1600,
+                height: 900,
+                window_mode: WindowMode::Borderless,
+                present_mode: PresentMode::LowLatency,
+                seed: Some(42),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn parses_ron_by_extension() {
+        let ron = r#"(title: "Mill", fps_cap: 144, monitor: Some("DP-2"))"#;
+
+        let config = EngineConfig::parse(ron, Some("ron")).unwrap();
+
+        assert_eq!(config.title, "Mill");
+        assert_eq!(config.fps_cap, 144);
+        assert_eq!(config.monitor.as_deref(), Some("DP-2"));
+        assert_eq!(config.width, EngineConfig::default().width);
+        // Anything but `.ron` is read as TOML
+        assert!(EngineConfig::parse(ron, Some("conf")).is_err());
+    }
+
+    #[test]
+    fn saved_window_state_goes_over_the_config() {
+        let mut config = EngineConfig {
+            monitor: Some("HDMI-1".to_string()),
+            ..Default::default()
+        };
+        let state = WindowState {
+            width: 1280,
+            height: 720,
+            position: Some([10, 20]),
+            window_mode: WindowMode::Exclusive,
+            monitor: None,
+        };
+
+        state.apply(&mut config);
+
+        assert_eq!((config.width, config.height), (1280, 720));
+        assert_eq!(config.position, Some([10, 20]));
+        assert_eq!(config.window_mode, WindowMode::Exclusive);
+        assert_eq!(config.monitor, None);
+    }
+
+    #[test]
+    fn saved_window_state_ignores_a_zero_size() {
+        let mut config = EngineConfig::default();
+
+        WindowState {
+            width: 0,
+            height: 720,
+            ..Default::default()
+        }
+        .apply(&mut config);
+
+        assert_eq!((config.width, config.height), (1024, 768));
+    }
+
+    #[test]
+    fn modes_parse_from_their_names() {
+        assert_eq!("vsync".parse::<PresentMode>().unwrap(), PresentMode::Vsync);
+        assert_eq!(
+            " Mailbox ".parse::<PresentMode>().unwrap(),
+            PresentMode::LowLatency
+        );
+        assert_eq!(
+            "immediate".parse::<PresentMode>().unwrap(),
+            PresentMode::Uncapped
+        );
+        assert!("tearing".parse::<PresentMode>().is_err());
+
+        assert_eq!(
+            "fullscreen".parse::<WindowMode>().unwrap(),
+            WindowMode::Borderless
+        );
+        assert_eq!(
+            "EXCLUSIVE".parse::<WindowMode>().unwrap(),
+            WindowMode::Exclusive
+        );
+        assert!("maximized".parse::<WindowMode>().is_err());
+
+        for mode in [
+            PresentMode::Vsync,
+            PresentMode::LowLatency,
+            PresentMode::Uncapped,
+        ] {
+            assert_eq!(mode.to_string().parse::<PresentMode>().unwrap(), mode);
+        }
+    }
+}
@@ -1,96 +1,162 @@
 //! Hell where Entities and Components and Systems live
 
 use anyhow::{Result, anyhow};
+use log::*;
+use serde::{Serialize, de::DeserializeOwned};
+#[cfg(all(feature = "render", not(feature = "headless")))]
+use std::cell::Cell;
 use std::{
     any::{Any, TypeId, type_name},
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap},
     mem::transmute,
+    path::{Path, PathBuf},
     rc::Rc,
     sync::{
         MappedRwLockReadGuard, MappedRwLockWriteGuard, RwLock, RwLockReadGuard, RwLockWriteGuard,
     },
+    thread,
+    time::{Duration, Instant},
 };
-use winit::event_loop::{EventLoop, EventLoopWindowTarget};
+// Re-exported so plugins name the same types whether or not there's a window
+#[cfg(feature = "render")]
+pub use winit::{
+    event::Event,
+    event_loop::{EventLoop, EventLoopWindowTarget},
+};
+
+#[cfg(not(feature = "render"))]
+pub use crate::ecs::windowless::{Event, EventLoop, EventLoopWindowTarget};
 
-type WinitEvent = winit::event::Event<()>;
+type WinitEvent = Event<()>;
 
-use crate::{
-    ecs::{
-        events::{EventDataWrapper, EventWrapper, LemgineEvent, LemgineEventData},
-        ordering::SystemOrder,
-        partial_manager::PartialManager,
+use crate::ecs::{
+    bundle::Bundle,
+    changes::ChangeTracker,
+    entity::{Entity, MapEntities},
+    events::{
+        EventDataWrapper, EventHandler, EventHandlers, EventWrapper, LemgineEvent,
+        LemgineEventData, TypedEventSystem,
     },
-    engine::Engine,
+    exit::{AppExit, ExitRequest},
+    filter::QueryFilter,
+    hierarchy::{Children, Parent},
+    input::{InputCapture, InputEvent},
+    name::Name,
+    ordering::SystemOrder,
+    partial_manager::PartialManager,
+    reflect::Reflect,
+    registry::TypeRegistry,
+    replay::{InputMode, Recording},
+    rng::GameRng,
+    spatial::GridPosition,
+    time::{Time, update_time},
+    timings::{Stage, SystemTimings},
+    worlds::{Inbox, MAIN_WORLD, Outbox, SubWorld, UndeliveredReason},
 };
 
+pub mod actions;
+pub mod bundle;
+pub mod camera;
+pub mod camera_controller;
+pub mod changes;
+pub mod entity;
 pub mod events;
+pub mod exit;
+pub mod filter;
+pub mod hierarchy;
+pub mod input;
+pub mod input_state;
+pub mod name;
 pub mod order_up;
 pub mod ordering;
 pub mod partial_manager;
+pub mod picking;
+pub mod prefab;
+pub mod reflect;
+pub mod registry;
+pub mod replay;
+pub mod rng;
+pub mod snapshot;
+pub mod spatial;
+pub mod tasks;
+pub mod test_app;
+pub mod time;
+pub mod timings;
+#[cfg(not(feature = "render"))]
+pub mod windowless;
+pub mod worlds;
 
 pub type StartupSystem = fn(&mut World, &EventLoop<()>) -> Result<()>;
 pub type System = fn(&World) -> Result<()>;
 pub type EventSystem = fn(&World, LemgineEventData) -> Result<()>;
 pub type WinitEventSystem = fn(&World, WinitEvent, &EventLoopWindowTarget<()>) -> Result<()>;
+/// Gets player input, recorded input when replaying. See [`input`].
+pub type InputSystem = fn(&World, &InputEvent) -> Result<()>;
+/// Runs once when the game is quitting, nothing else is running by then.
+pub type ShutdownSystem = fn(&mut World) -> Result<()>;
+/// Gets the world being extracted from first and the world being extracted into second.
+pub type ExtractSystem = fn(&World, &World) -> Result<()>;
 
 /// Should manage everything related to the ECS
 pub struct Manager {
     pub world: World,
     pub startup_systems: SystemOrder<StartupSystem>,
     pub systems: SystemOrder<System>,
+    pub fixed_systems: SystemOrder<System>,
     pub winit_event_systems: SystemOrder<WinitEventSystem>,
-    pub event_systems: HashMap<LemgineEvent, SystemOrder<EventSystem>>,
+    pub input_systems: SystemOrder<InputSystem>,
+    pub event_systems: EventHandlers,
+    /// One group per plugin in the order they were added, run back to front on exit.
+    pub shutdown_systems: Vec<SystemOrder<ShutdownSystem>>,
+    /// Worlds other than the main one, updated in the order they were added.
+    pub worlds: Vec<SubWorld>,
+    pub extract_systems: Vec<(String, String, ExtractSystem)>,
+    pub input_mode: InputMode,
 }
 
 impl Manager {
     pub fn new() -> Result<Self> {
-        let world = World::new();
+        let mut world = World::with_defaults();
+        // Replaced by `with_seed` in the game, tests get a fresh seed every run
+        world.add_resource(GameRng::new(rand::random()));
 
         Ok(Self {
             world,
             startup_systems: SystemOrder::empty(),
             systems: SystemOrder::empty(),
+            fixed_systems: SystemOrder::empty(),
             winit_event_systems: SystemOrder::empty(),
-            event_systems: HashMap::new(),
+            input_systems: SystemOrder::empty(),
+            event_systems: EventHandlers::new(),
+            shutdown_systems: vec![],
+            worlds: vec![],
+            extract_systems: vec![],
+            input_mode: InputMode::Live,
         })
     }
 
     pub fn integrate(mut self, partial: PartialManager) -> Result<Self> {
         self.startup_systems.extend_mut_ref(partial.startup_systems);
         self.systems.extend_mut_ref(partial.systems);
+        self.fixed_systems.extend_mut_ref(partial.fixed_systems);
 
         self.winit_event_systems
             .extend_mut_ref(partial.winit_event_systems);
+        self.input_systems.extend_mut_ref(partial.input_systems);
 
-        for (event, system) in partial.event_systems {
-            // self.event_systems.
-            if let Some(value) = self.event_systems.get_mut(&event) {
-                value.extend_mut_ref(system);
-            } else {
-                self.event_systems.insert(event, system);
-            }
-        }
+        self.event_systems.extend(partial.event_systems);
 
-        for (id, value) in partial.resources {
-            match self.world.resources.contains_key(&id) {
-                true => {
-                    return Err(anyhow!(
-                        "Resource from PartialManager's world exists in world already."
-                    ));
-                }
-                false => {
-                    self.world.resources.insert(id, value);
-                }
-            }
-        }
-        for (id, value) in partial.components {
-            if let Some(entry) = self.world.components.get_mut(&id) {
-                entry.extend_from_slice(&value);
-            } else {
-                self.world.components.insert(id, value);
-            }
+        if !partial.shutdown_systems.order.is_empty() {
+            self.shutdown_systems.push(partial.shutdown_systems);
         }
 
+        self.world.absorb_partial(
+            partial.resources,
+            partial.components,
+            partial.bundles,
+            partial.type_registry,
+        )?;
+
         Ok(self)
     }
 
@@ -107,108 +173,718 @@ impl Manager {
         self
     }
 
+    pub fn add_input_systems<S: Into<SystemOrder<InputSystem>>>(mut self, systems: S) -> Self {
+        self.input_systems = systems.into();
+        self
+    }
+
     pub fn add_systems<S: Into<SystemOrder<System>>>(mut self, systems: S) -> Self {
         self.systems = systems.into();
         self
     }
 
+    pub fn add_fixed_systems<S: Into<SystemOrder<System>>>(mut self, systems: S) -> Self {
+        self.fixed_systems = systems.into();
+        self
+    }
+
+    /// Shutdown systems added here run before the ones of everything integrated so far.
+    pub fn add_shutdown_systems<S: Into<SystemOrder<ShutdownSystem>>>(
+        mut self,
+        systems: S,
+    ) -> Self {
+        self.shutdown_systems.push(systems.into());
+        self
+    }
+
     pub fn add_event_handler<E: EventWrapper + 'static, S: Into<SystemOrder<EventSystem>>>(
         mut self,
         event: E,
         system: S,
     ) -> Self {
         self.event_systems
-            .entry(Box::new(event))
-            .or_insert(system.into());
+            .add_value_handler(Box::new(event), system.into());
+        self
+    }
+
+    pub fn add_typed_event_handler<E: EventWrapper + 'static>(
+        mut self,
+        system: TypedEventSystem<E>,
+    ) -> Self {
+        self.event_systems
+            .add_type_handler(EventHandler::new(system));
+        self
+    }
+
+    pub fn add_typed_event_handler_with(mut self, handler: EventHandler) -> Self {
+        self.event_systems.add_type_handler(handler);
         self
     }
 
     pub fn raise_event(&self, event: LemgineEvent, data: LemgineEventData) -> Result<()> {
-        if let Some(systems) = self.event_systems.get(&event) {
-            for system in systems.clone().order {
-                match system(&self.world, data.clone()) {
-                    Ok(_) => {}
-                    Err(err) => return Err(anyhow!(err)),
-                };
+        raise_event(&self.world, &self.event_systems, event, data)
+    }
+
+    pub fn check_events(&mut self) -> Result<()> {
+        check_events(&self.world, &self.event_systems)
+    }
+
+    pub fn add_resource<T: Any>(mut self, resource: T) -> Self {
+        self.world.add_resource(resource);
+        self
+    }
+
+    pub fn add_component<T: Any>(mut self, component: T) -> Self {
+        self.world.add_component(component);
+        self
+    }
+
+    /// Spawn an entity with every component in `bundle`.
+    pub fn add_bundle<B: Bundle>(mut self, bundle: B) -> Self {
+        self.world.spawn_bundle(bundle);
+        self
+    }
+
+    pub fn register_type<T: Any + Serialize + DeserializeOwned>(
+        mut self,
+        name: &'static str,
+    ) -> Self {
+        self.world.register_type::<T>(name);
+        self
+    }
+
+    pub fn register_mapped_type<T: Any + Serialize + DeserializeOwned + MapEntities>(
+        mut self,
+        name: &'static str,
+    ) -> Self {
+        self.world.register_mapped_type::<T>(name);
+        self
+    }
+
+    pub fn register_reflect_type<T: Reflect>(mut self, name: &'static str) -> Self {
+        self.world.register_reflect_type::<T>(name);
+        self
+    }
+
+    /// Runs the fixed systems once for every fixed step that fits in the [`Time`] accumulator.
+    pub fn run_fixed_systems(&mut self) -> Result<()> {
+        run_fixed_systems(&mut self.world, &self.fixed_systems, &self.event_systems)
+    }
+
+    /// Add another world with its own schedule, made from everything in `partial`.
+    ///
+    /// Worlds don't share anything, data only crosses between them through extract systems
+    /// (see [`Manager::add_extract_system`]) and messages (see [`World::send_to`]).
+    /// Winit events only go to the main world.
+    ///
+    /// # Example
+    /// ```rs
+    /// Manager::new()?
+    ///     .integrate(engine_partial())?
+    ///     .add_world("sim", factory_partial())?
+    ///     .add_extract_system("sim", MAIN_WORLD, extract_machines);
+    /// ```
+    pub fn add_world(mut self, name: &str, partial: PartialManager) -> Result<Self> {
+        if name == MAIN_WORLD || self.worlds.iter().any(|w| w.name == name) {
+            return Err(anyhow!("A world named {name} already exists."));
+        }
+
+        let mut sub_world = SubWorld::new(name, partial)?;
+        let seed = self.world.get_resource::<GameRng>().seed();
+        sub_world
+            .world
+            .add_resource(GameRng::new(GameRng::world_seed(seed, name)));
+
+        self.worlds.push(sub_world);
+        Ok(self)
+    }
+
+    /// Run `system` every frame after the world `from` has updated, with `from` and `to`.
+    ///
+    /// The sub worlds update before the main world, so extracting out of a sub world lands in time
+    /// for the main world's update that frame. Extracting out of the main world happens after its
+    /// update, so a sub world sees it on the next frame.
+    ///
+    /// This is the place to copy whatever `to` needs out of `from`, e.g. pulling sprite positions
+    /// out of the simulation for the renderer.
+    pub fn add_extract_system(mut self, from: &str, to: &str, system: ExtractSystem) -> Self {
+        self.extract_systems
+            .push((from.to_string(), to.to_string(), system));
+        self
+    }
+
+    /// Start the [`GameRng`] of every world from `seed`, or from a random seed without one.
+    pub fn with_seed(self, seed: Option<u64>) -> Self {
+        let seed = GameRng::from_seed(seed).seed();
+        rng::reseed_worlds(&self.world, &self.worlds, seed);
+        self
+    }
+
+    /// Write every frame's input to `path` when the game quits, to be replayed with
+    /// [`Manager::replay_input`]. See [`replay`].
+    pub fn record_input<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.input_mode = InputMode::Recording {
+            path: path.into(),
+            recording: None,
+        };
+        self
+    }
+
+    /// Play back the input recorded in `path` instead of listening to the window, then quit.
+    /// See [`replay`].
+    pub fn replay_input<P: AsRef<Path>>(mut self, path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let recording = Recording::load_from_file(path)
+            .map_err(|err| anyhow!("Couldn't load recording {}: {err}", path.display()))?;
+
+        self.input_mode = InputMode::Replaying {
+            recording,
+            next_frame: 0,
+        };
+        Ok(self)
+    }
+
+    /// Run the input systems for every input in order.
+    pub fn dispatch_input(&self, inputs: &[InputEvent]) -> Result<()> {
+        for input in inputs {
+            for system in self.input_systems.order.iter() {
+                let start = Instant::now();
+                system(&self.world, input)?;
+                self.world
+                    .record_timing(Stage::Input, *system as usize, start.elapsed());
             }
         }
 
         Ok(())
     }
 
-    pub fn check_events(&mut self) -> Result<()> {
-        let events = self.world.new_events.read().unwrap();
-
-        // Check if any events have been raised
-        if events.is_empty() {
+    /// Everything in a frame that comes after the winit systems: input, the other worlds and
+    /// extraction out of them, then the main world's update and extraction out of it.
+    pub fn run_frame(&mut self, live_inputs: Vec<InputEvent>) -> Result<()> {
+        let Some((inputs, delta)) = self.input_mode.next_frame(&self.world, live_inputs) else {
+            if self.world.exit_requested().is_none() {
+                info!("Replay finished");
+                self.world.exit(AppExit::Success);
+                self.check_events()?;
+            }
             return Ok(());
+        };
+        let tick = self.world.get_resource::<Time>().fixed_tick_count();
+
+        self.step(&inputs, delta)?;
+
+        self.input_mode.end_frame(&self.world, tick, inputs);
+
+        Ok(())
+    }
+
+    /// Run a single frame with `inputs`, without looking at the [`InputMode`].
+    ///
+    /// Time moves forward by `delta`, or by however long it's been since the last frame if it's
+    /// `None`.
+    pub fn step(&mut self, inputs: &[InputEvent], delta: Option<Duration>) -> Result<()> {
+        self.dispatch_input(inputs)?;
+        self.check_events()?;
+        self.world.apply_commands()?;
+
+        for sub_world in self.worlds.iter_mut() {
+            sub_world.update(delta)?;
+        }
+
+        self.exchange_between_worlds()?;
+
+        run_update(
+            &mut self.world,
+            &self.fixed_systems,
+            &self.systems,
+            &self.event_systems,
+            delta,
+        )?;
+
+        self.run_extract_systems(true)?;
+        self.apply_commands_everywhere()?;
+
+        Ok(())
+    }
+
+    /// Look up a world by name, [`MAIN_WORLD`] is [`Manager::world`].
+    pub fn get_world(&self, name: &str) -> Option<&World> {
+        if name == MAIN_WORLD {
+            return Some(&self.world);
+        }
+        self.worlds
+            .iter()
+            .find(|w| w.name == name)
+            .map(|w| &w.world)
+    }
+
+    pub fn get_world_mut(&mut self, name: &str) -> Option<&mut World> {
+        if name == MAIN_WORLD {
+            return Some(&mut self.world);
         }
+        self.worlds
+            .iter_mut()
+            .find(|w| w.name == name)
+            .map(|w| &mut w.world)
+    }
 
-        drop(events);
+    /// Run the extract systems out of the sub worlds and hand every sent message to the world it's
+    /// for. Extracting out of the main world waits until it has updated, see
+    /// [`Manager::add_extract_system`].
+    pub fn exchange_between_worlds(&mut self) -> Result<()> {
+        self.run_extract_systems(false)?;
 
-        let mut events = self.world.new_events.write().unwrap();
+        let mut messages = vec![(MAIN_WORLD, self.world.take_outgoing_messages())];
+        for sub_world in self.worlds.iter() {
+            messages.push((&sub_world.name, sub_world.world.take_outgoing_messages()));
+        }
 
-        for (event, data) in events.clone().into_iter() {
-            self.raise_event(event, data)?;
+        for (sender, outgoing) in messages {
+            let sender = self.get_world(sender).unwrap();
+            for message in outgoing {
+                let Some(world) = self.get_world(&message.target) else {
+                    sender.report_undelivered(message, UndeliveredReason::NoSuchWorld);
+                    continue;
+                };
+                if let Err(message) = world.receive_message(message) {
+                    sender.report_undelivered(message, UndeliveredReason::NoInbox);
+                }
+            }
         }
 
-        events.clear();
+        self.apply_commands_everywhere()
+    }
+
+    /// Run the extract systems out of the main world if `from_main`, otherwise the ones out of
+    /// the sub worlds.
+    fn run_extract_systems(&mut self, from_main: bool) -> Result<()> {
+        for (from, to, system) in self.extract_systems.clone() {
+            if (from == MAIN_WORLD) != from_main {
+                continue;
+            }
+
+            let (Some(from_world), Some(to_world)) = (self.get_world(&from), self.get_world(&to))
+            else {
+                return Err(anyhow!(
+                    "Extract system between {from} and {to} which don't exist."
+                ));
+            };
+
+            let start = Instant::now();
+            system(from_world, to_world)?;
+            self.world
+                .record_timing(Stage::Extract, system as usize, start.elapsed());
+        }
 
         Ok(())
     }
 
-    pub fn add_resource<T: Any>(mut self, resource: T) -> Self {
-        self.world.add_resource(resource);
-        self
+    fn apply_commands_everywhere(&mut self) -> Result<()> {
+        for sub_world in self.worlds.iter_mut() {
+            sub_world.world.apply_commands()?;
+        }
+        self.world.apply_commands()
     }
 
-    pub fn add_component<T: Any>(mut self, component: T) -> Self {
-        self.world.add_component(component);
-        self
+    /// The exit asked for by any world, the main world wins if there's more than one.
+    pub fn exit_requested(&self) -> Option<AppExit> {
+        self.world
+            .exit_requested()
+            .or_else(|| self.worlds.iter().find_map(|w| w.world.exit_requested()))
+    }
+
+    /// Run every shutdown system, the sub worlds first and then the main world, each in the
+    /// reverse order of their plugins.
+    ///
+    /// A failing shutdown system doesn't stop the rest from running, otherwise a broken save
+    /// would leave the renderer alive. Returns the exit code to quit with, which is turned into
+    /// an error if anything failed.
+    pub fn shutdown(&mut self, exit: AppExit) -> AppExit {
+        info!("Shutting down ({exit:?})");
+
+        let mut failed = false;
+        if let Err(err) = self.input_mode.finish() {
+            error!("Couldn't save the input recording: {err:?}");
+            failed = true;
+        }
+
+        for sub_world in self.worlds.iter_mut().rev() {
+            failed |= run_shutdown_systems(
+                &mut sub_world.world,
+                std::slice::from_ref(&sub_world.shutdown_systems),
+            );
+        }
+        failed |= run_shutdown_systems(&mut self.world, &self.shutdown_systems);
+
+        match failed && exit.is_success() {
+            true => AppExit::error(1),
+            false => exit,
+        }
     }
 
-    pub fn run(mut self) -> Result<()> {
+    /// Run the game until something raises [`AppExit`] or the window is closed.
+    /// Returns the exit code the process should quit with.
+    ///
+    /// Without the `render` feature, or with `headless`, this is [`Manager::run_headless`].
+    #[cfg(all(feature = "render", not(feature = "headless")))]
+    pub fn run(mut self) -> Result<AppExit> {
         let event_loop = EventLoop::new()?;
 
         for system in self.startup_systems.order.iter() {
+            let start = Instant::now();
             system(&mut self.world, &event_loop)?;
+            self.world
+                .record_timing(Stage::Startup, *system as usize, start.elapsed());
         }
 
+        self.world.apply_commands()?;
+
+        for sub_world in self.worlds.iter_mut() {
+            sub_world.startup(&event_loop)?;
+        }
+
+        self.input_mode.start(&self.world, &self.worlds);
+
+        let exit = Rc::new(Cell::new(None));
+        let exit_code = exit.clone();
+
+        // Input waits here until the frame it's part of, so "this frame" means a frame and not
+        // a winit event
+        let mut live_inputs = vec![];
         event_loop.run(move |event, elwt| {
-            for system in self.winit_event_systems.clone().order.iter() {
-                system(&self.world, event.clone(), elwt).unwrap();
+            // Already shut down, just waiting for winit to stop
+            if exit.get().is_some() {
+                return;
+            }
+            let loop_exiting = matches!(event, WinitEvent::LoopExiting);
+            // Winit is done with this batch of events, the redraw for it comes next
+            let frame_done = matches!(event, WinitEvent::AboutToWait);
+            live_inputs.extend(self.handle_winit_event(event, elwt).unwrap());
+            if frame_done {
+                self.run_frame(std::mem::take(&mut live_inputs)).unwrap();
+            }
+
+            // Winit can stop on its own, e.g. when the OS ends the session
+            let request = self
+                .exit_requested()
+                .or(loop_exiting.then_some(AppExit::Success));
+            if let Some(request) = request {
+                exit.set(Some(self.shutdown(request)));
+                elwt.exit();
             }
+        })?;
 
-            self.check_events().unwrap();
+        Ok(exit_code.get().unwrap_or(AppExit::Success))
+    }
 
-            for system in self.systems.clone().order.iter() {
-                system(&self.world).unwrap();
+    /// Run the winit systems for `event`, returns the input in it that the gui didn't take.
+    pub fn handle_winit_event(
+        &mut self,
+        event: WinitEvent,
+        elwt: &EventLoopWindowTarget<()>,
+    ) -> Result<Vec<InputEvent>> {
+        let inputs = inputs_in(&event);
+
+        // Without a window `event` can't exist, so there's nothing to run
+        #[cfg(not(feature = "render"))]
+        let _ = elwt;
+        #[cfg(feature = "render")]
+        for system in self.winit_event_systems.clone().order.iter() {
+            let start = Instant::now();
+            system(&self.world, event.clone(), elwt)?;
+            self.world
+                .record_timing(Stage::WinitEvent, *system as usize, start.elapsed());
+        }
+
+        self.check_events()?;
+        self.world.apply_commands()?;
+
+        Ok(self.uncaptured(inputs))
+    }
+
+    /// `inputs` without whatever the gui took, see [`InputCapture`].
+    pub fn uncaptured(&self, mut inputs: Vec<InputEvent>) -> Vec<InputEvent> {
+        if let Some(capture) = self.world.try_get_resource::<InputCapture>() {
+            inputs.retain(|input| {
+                let captured = capture.captures(input);
+                if captured {
+                    trace!("The gui took {input:?}");
+                }
+                !captured
+            });
+        }
+        inputs
+    }
+
+    #[cfg(any(not(feature = "render"), feature = "headless"))]
+    pub fn run(self) -> Result<AppExit> {
+        self.run_headless()
+    }
+
+    /// Run without a window or event loop until something raises [`AppExit`], meant for
+    /// replaying recordings on machines without a display.
+    ///
+    /// A replay runs as fast as it can and stops once it's out of frames, otherwise frames are
+    /// spaced a fixed timestep apart so the game doesn't spin a core.
+    ///
+    /// Startup and winit systems need the event loop so they don't run at all, anything they
+    /// would've set up won't be there.
+    pub fn run_headless(mut self) -> Result<AppExit> {
+        let startup_systems = self.startup_systems.order.len()
+            + self
+                .worlds
+                .iter()
+                .map(|w| w.startup_systems.order.len())
+                .sum::<usize>();
+        if startup_systems > 0 {
+            warn!("Running headless, skipping {startup_systems} startup systems");
+        }
+
+        self.world.apply_commands()?;
+        self.input_mode.start(&self.world, &self.worlds);
+
+        let replaying = self.input_mode.is_replaying();
+        loop {
+            let start = Instant::now();
+            self.run_frame(vec![])?;
+
+            if let Some(request) = self.exit_requested() {
+                return Ok(self.shutdown(request));
+            }
+            if self.input_mode.is_finished() {
+                info!("Replay finished");
+                return Ok(self.shutdown(AppExit::Success));
             }
 
-            self.check_events().unwrap();
-        })?;
+            if !replaying {
+                let frame_time = self.world.get_resource::<Time>().fixed_timestep();
+                if let Some(sleep) = frame_time.checked_sub(start.elapsed()) {
+                    thread::sleep(sleep);
+                }
+            }
+        }
+    }
+}
 
-        Ok(())
+/// The input in a winit event.
+#[cfg(feature = "render")]
+fn inputs_in(event: &WinitEvent) -> Vec<InputEvent> {
+    match event {
+        WinitEvent::WindowEvent { event, .. } => {
+            InputEvent::from_window_event(event).into_iter().collect()
+        }
+        _ => vec![],
+    }
+}
+
+#[cfg(not(feature = "render"))]
+fn inputs_in(_: &WinitEvent) -> Vec<InputEvent> {
+    vec![]
+}
+
+/// Run the handlers for a single event.
+pub(crate) fn raise_event(
+    world: &World,
+    event_systems: &EventHandlers,
+    event: LemgineEvent,
+    data: LemgineEventData,
+) -> Result<()> {
+    event_systems.raise(world, &event, data)
+}
+
+/// Run the handlers for every event raised in `world` since the last check.
+pub(crate) fn check_events(world: &World, event_systems: &EventHandlers) -> Result<()> {
+    let events = world.new_events.read().unwrap();
+
+    // Check if any events have been raised
+    if events.is_empty() {
+        return Ok(());
+    }
+
+    drop(events);
+
+    let events = std::mem::take(&mut *world.new_events.write().unwrap());
+
+    for (event, data) in events.into_iter() {
+        world.note_exit(&*event);
+        raise_event(world, event_systems, event, data)?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn run_fixed_systems(
+    world: &mut World,
+    fixed_systems: &SystemOrder<System>,
+    event_systems: &EventHandlers,
+) -> Result<()> {
+    while world.get_resource_mut::<Time>().expend_fixed_step() {
+        for system in fixed_systems.order.iter() {
+            let start = Instant::now();
+            system(world)?;
+            world.record_timing(Stage::Fixed, *system as usize, start.elapsed());
+        }
+
+        check_events(world, event_systems)?;
+        world.apply_commands()?;
     }
+
+    Ok(())
+}
+
+/// Run the shutdown systems of every group, last group first.
+/// Returns `true` if any of them failed.
+pub(crate) fn run_shutdown_systems(
+    world: &mut World,
+    shutdown_systems: &[SystemOrder<ShutdownSystem>],
+) -> bool {
+    let mut failed = false;
+    for group in shutdown_systems.iter().rev() {
+        for system in group.order.iter() {
+            let start = Instant::now();
+            if let Err(err) = system(world) {
+                error!("Shutdown system failed: {err:?}");
+                failed = true;
+            }
+            world.record_timing(Stage::Shutdown, *system as usize, start.elapsed());
+        }
+
+        if let Err(err) = world.apply_commands() {
+            error!("Command queued during shutdown failed: {err:?}");
+            failed = true;
+        }
+    }
+
+    failed
+}
+
+/// Everything a world does each frame after the winit events: advance time, run the fixed
+/// systems, run the systems and then handle whatever events and commands they left behind.
+///
+/// Time is advanced by `delta` instead of the real clock if there is one, for replays.
+pub(crate) fn run_update(
+    world: &mut World,
+    fixed_systems: &SystemOrder<System>,
+    systems: &SystemOrder<System>,
+    event_systems: &EventHandlers,
+    delta: Option<Duration>,
+) -> Result<()> {
+    match delta {
+        Some(delta) => world.get_resource_mut::<Time>().advance_by(delta),
+        None => update_time(world)?,
+    }
+
+    run_fixed_systems(world, fixed_systems, event_systems)?;
+
+    for system in systems.order.iter() {
+        let start = Instant::now();
+        system(world)?;
+        world.record_timing(Stage::Update, *system as usize, start.elapsed());
+    }
+
+    check_events(world, event_systems)?;
+    world.apply_commands()?;
+
+    Ok(())
 }
 
 pub type Resource = Rc<RwLock<Box<dyn Any>>>;
 pub type Component = Rc<RwLock<Box<dyn Any>>>;
 
+/// A change to the [`World`] that has to wait until no system is borrowing it.
+pub type Command = Box<dyn FnOnce(&mut World) -> Result<()>>;
+
 /// A whole new world!
 #[derive(Clone)]
 pub struct World {
     resources: HashMap<TypeId, Resource>,
-    components: HashMap<TypeId, Vec<Component>>,
+    components: HashMap<TypeId, BTreeMap<Entity, Component>>,
+    entities: BTreeSet<Entity>,
+    next_entity: u64,
+    type_registry: Rc<RwLock<TypeRegistry>>,
     new_events: Rc<RwLock<Vec<(LemgineEvent, LemgineEventData)>>>,
+    commands: Rc<RwLock<Vec<Command>>>,
+    changes: Rc<RwLock<ChangeTracker>>,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl World {
+    /// A world with the resources every world run by [`Manager`] has, like [`Time`].
+    pub fn with_defaults() -> Self {
+        let mut world = World::new();
+        world.add_resource(Time::new());
+        world.add_resource(SystemTimings::new());
+        world.add_resource(Outbox::default());
+        world.add_resource(Inbox::default());
+        world.add_resource(ExitRequest::default());
+        world.register_reflect_type::<Time>("time");
+        world.register_type::<GameRng>("rng");
+        world.register_mapped_type::<Parent>("parent");
+        world.register_reflect_type::<Parent>("parent");
+        world.register_mapped_type::<Children>("children");
+        world.register_reflect_type::<Children>("children");
+        world.register_type::<Name>("name");
+        world.register_reflect_type::<Name>("name");
+        world.register_type::<GridPosition>("grid_position");
+        world.register_reflect_type::<GridPosition>("grid_position");
+        world
+    }
+
+    /// Take the resources, components and registered types out of a [`PartialManager`].
+    pub(crate) fn absorb_partial(
+        &mut self,
+        resources: HashMap<TypeId, Resource>,
+        components: HashMap<TypeId, Vec<Component>>,
+        bundles: Vec<Vec<(TypeId, Box<dyn Any>)>>,
+        type_registry: TypeRegistry,
+    ) -> Result<()> {
+        for (id, value) in resources {
+            match self.resources.contains_key(&id) {
+                true => {
+                    return Err(anyhow!(
+                        "Resource from PartialManager's world exists in world already."
+                    ));
+                }
+                false => {
+                    self.resources.insert(id, value);
+                }
+            }
+        }
+        for (id, value) in components {
+            for component in value {
+                let entity = self.spawn();
+                self.components
+                    .entry(id)
+                    .or_default()
+                    .insert(entity, component);
+                self.mark_changed(id, entity);
+            }
+        }
+        for components in bundles {
+            let entity = self.spawn();
+            self.insert_components(entity, components);
+        }
+
+        self.type_registry.write().unwrap().extend(type_registry);
+
+        Ok(())
+    }
+
     pub fn new() -> Self {
         Self {
             resources: HashMap::new(),
             components: HashMap::new(),
+            entities: BTreeSet::new(),
+            next_entity: 0,
+            type_registry: Rc::new(RwLock::new(TypeRegistry::new())),
             new_events: Rc::new(RwLock::new(vec![])),
+            commands: Rc::new(RwLock::new(vec![])),
+            changes: Rc::new(RwLock::new(ChangeTracker::default())),
         }
     }
 
@@ -239,33 +915,320 @@ impl World {
         Some(RwLockWriteGuard::map(reading, |r| unsafe { transmute(r) }))
     }
 
-    pub fn add_component<T: Any>(&mut self, component: T) {
-        if let Some(value) = self.components.get_mut(&TypeId::of::<T>()) {
-            value.push(Rc::new(RwLock::new(Box::new(component))));
-        } else {
-            self.components.insert(
-                TypeId::of::<T>(),
-                vec![Rc::new(RwLock::new(Box::new(component)))],
+    /// Make a new entity with no components.
+    pub fn spawn(&mut self) -> Entity {
+        let entity = Entity(self.next_entity);
+        self.next_entity += 1;
+        self.entities.insert(entity);
+        entity
+    }
+
+    /// Remove an entity and all of its components.
+    /// Returns `false` if the entity didn't exist.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.entities.remove(&entity) {
+            return false;
+        }
+
+        for (id, components) in self.components.iter_mut() {
+            if components.remove(&entity).is_some() {
+                self.changes.write().unwrap().mark_removed(*id, entity);
+            }
+        }
+
+        true
+    }
+
+    pub fn contains_entity(&self, entity: Entity) -> bool {
+        self.entities.contains(&entity)
+    }
+
+    /// Every entity, oldest first.
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.iter().copied()
+    }
+
+    /// Give `entity` a component, replacing the old one if it already had a `T`.
+    ///
+    /// # Panics
+    /// Panics if the entity doesn't exist.
+    pub fn insert<T: Any>(&mut self, entity: Entity, component: T) {
+        self.insert_boxed(entity, TypeId::of::<T>(), Box::new(component));
+    }
+
+    pub(crate) fn insert_boxed(&mut self, entity: Entity, id: TypeId, component: Box<dyn Any>) {
+        assert!(
+            self.entities.contains(&entity),
+            "Tried to insert a component on {} which doesn't exist.",
+            self.label(entity)
+        );
+
+        self.components
+            .entry(id)
+            .or_default()
+            .insert(entity, Rc::new(RwLock::new(component)));
+        self.mark_changed(id, entity);
+    }
+
+    /// Spawn a new entity with every component in `bundle`.
+    ///
+    /// # Panics
+    /// Panics if the bundle has the same component type twice.
+    pub fn spawn_bundle<B: Bundle>(&mut self, bundle: B) -> Entity {
+        let entity = self.spawn();
+        self.insert_bundle(entity, bundle);
+        entity
+    }
+
+    /// Give `entity` every component in `bundle`, replacing any it already had.
+    ///
+    /// # Panics
+    /// Panics if the entity doesn't exist or the bundle has the same component type twice.
+    pub fn insert_bundle<B: Bundle>(&mut self, entity: Entity, bundle: B) {
+        self.insert_components(entity, bundle.into_components());
+    }
+
+    /// Checks everything first so the entity never ends up with only part of the components.
+    pub(crate) fn insert_components(
+        &mut self,
+        entity: Entity,
+        components: Vec<(TypeId, Box<dyn Any>)>,
+    ) {
+        assert!(
+            self.entities.contains(&entity),
+            "Tried to insert a bundle on {} which doesn't exist.",
+            self.label(entity)
+        );
+        for (i, (id, _)) in components.iter().enumerate() {
+            assert!(
+                !components[..i].iter().any(|(other, _)| other == id),
+                "Bundle has the same component twice."
             );
         }
+
+        for (id, component) in components {
+            self.insert_boxed(entity, id, component);
+        }
+    }
+
+    /// Take a component off of an entity. Returns `false` if it didn't have one.
+    pub fn remove<T: Any>(&mut self, entity: Entity) -> bool {
+        let removed = self
+            .components
+            .get_mut(&TypeId::of::<T>())
+            .is_some_and(|components| components.remove(&entity).is_some());
+        if removed {
+            self.mark_removed(TypeId::of::<T>(), entity);
+        }
+        removed
+    }
+
+    pub fn has_component<T: Any>(&self, entity: Entity) -> bool {
+        self.components
+            .get(&TypeId::of::<T>())
+            .is_some_and(|components| components.contains_key(&entity))
+    }
+
+    /// Spawn a new entity with a single component.
+    pub fn add_component<T: Any>(&mut self, component: T) -> Entity {
+        let entity = self.spawn();
+        self.insert(entity, component);
+        entity
+    }
+
+    pub fn get_component<T: Any>(
+        &self,
+        entity: Entity,
+    ) -> Option<MappedRwLockReadGuard<'_, Box<T>>> {
+        let reading = self
+            .components
+            .get(&TypeId::of::<T>())?
+            .get(&entity)?
+            .read()
+            .ok()?;
+        Some(RwLockReadGuard::map(reading, |r| unsafe { transmute(r) }))
+    }
+
+    pub fn get_component_mut<T: Any>(
+        &self,
+        entity: Entity,
+    ) -> Option<MappedRwLockWriteGuard<'_, Box<T>>> {
+        let writing = self
+            .components
+            .get(&TypeId::of::<T>())?
+            .get(&entity)?
+            .write()
+            .ok()?;
+        self.mark_changed(TypeId::of::<T>(), entity);
+        Some(RwLockWriteGuard::map(writing, |r| unsafe { transmute(r) }))
     }
 
     pub fn get_components<T: Any>(&self) -> Vec<MappedRwLockReadGuard<'_, Box<T>>> {
-        let reading = self.components.get(&TypeId::of::<T>()).unwrap();
+        self.query::<T>().into_iter().map(|(_, v)| v).collect()
+    }
+
+    pub fn get_components_mut<T: Any>(&self) -> Vec<MappedRwLockWriteGuard<'_, Box<T>>> {
+        self.query_mut::<T>().into_iter().map(|(_, v)| v).collect()
+    }
+
+    /// Every `T` along with the entity it belongs to.
+    pub fn query<T: Any>(&self) -> Vec<(Entity, MappedRwLockReadGuard<'_, Box<T>>)> {
+        let Some(reading) = self.components.get(&TypeId::of::<T>()) else {
+            return vec![];
+        };
         reading
             .iter()
-            .map(|v| RwLockReadGuard::map(v.read().unwrap(), |r| unsafe { transmute(r) }))
+            .map(|(entity, v)| {
+                (
+                    *entity,
+                    RwLockReadGuard::map(v.read().unwrap(), |r| unsafe { transmute(r) }),
+                )
+            })
             .collect()
     }
 
-    pub fn get_components_mut<T: Any>(&self) -> Vec<MappedRwLockWriteGuard<'_, Box<T>>> {
-        let reading = self.components.get(&TypeId::of::<T>()).unwrap();
+    /// Every `T` along with the entity it belongs to, mutably.
+    pub fn query_mut<T: Any>(&self) -> Vec<(Entity, MappedRwLockWriteGuard<'_, Box<T>>)> {
+        let Some(reading) = self.components.get(&TypeId::of::<T>()) else {
+            return vec![];
+        };
         reading
             .iter()
-            .map(|v| RwLockWriteGuard::map(v.write().unwrap(), |r| unsafe { transmute(r) }))
+            .map(|(entity, v)| {
+                self.mark_changed(TypeId::of::<T>(), *entity);
+                (
+                    *entity,
+                    RwLockWriteGuard::map(v.write().unwrap(), |r| unsafe { transmute(r) }),
+                )
+            })
             .collect()
     }
 
+    /// Every `T` on an entity that matches `F`, see [`filter`](crate::ecs::filter).
+    ///
+    /// # Example
+    /// ```rs
+    /// let selected = world.query_filtered::<GridPosition, With<Selected>>();
+    /// ```
+    pub fn query_filtered<T: Any, F: QueryFilter>(
+        &self,
+    ) -> Vec<(Entity, MappedRwLockReadGuard<'_, Box<T>>)> {
+        let Some(reading) = self.components.get(&TypeId::of::<T>()) else {
+            return vec![];
+        };
+        reading
+            .iter()
+            .filter(|(entity, _)| F::matches(self, **entity))
+            .map(|(entity, v)| {
+                (
+                    *entity,
+                    RwLockReadGuard::map(v.read().unwrap(), |r| unsafe { transmute(r) }),
+                )
+            })
+            .collect()
+    }
+
+    /// Every `T` on an entity that matches `F`, mutably.
+    pub fn query_filtered_mut<T: Any, F: QueryFilter>(
+        &self,
+    ) -> Vec<(Entity, MappedRwLockWriteGuard<'_, Box<T>>)> {
+        let Some(reading) = self.components.get(&TypeId::of::<T>()) else {
+            return vec![];
+        };
+        reading
+            .iter()
+            .filter(|(entity, _)| F::matches(self, **entity))
+            .map(|(entity, v)| {
+                self.mark_changed(TypeId::of::<T>(), *entity);
+                (
+                    *entity,
+                    RwLockWriteGuard::map(v.write().unwrap(), |r| unsafe { transmute(r) }),
+                )
+            })
+            .collect()
+    }
+
+    /// Every entity that matches `F`, oldest first. Handy for tags, which have nothing to read.
+    ///
+    /// # Example
+    /// ```rs
+    /// for bug in world.filter_entities::<(With<Bug>, Without<Dead>)>() {
+    ///     world.queue_command(move |world| Ok(world.insert(bug, Dead)));
+    /// }
+    /// ```
+    pub fn filter_entities<F: QueryFilter>(&self) -> Vec<Entity> {
+        self.entities
+            .iter()
+            .copied()
+            .filter(|entity| F::matches(self, *entity))
+            .collect()
+    }
+
+    /// Register a type so it's included in snapshots. See [`TypeRegistry::register`].
+    pub fn register_type<T: Any + Serialize + DeserializeOwned>(&mut self, name: &'static str) {
+        self.type_registry.write().unwrap().register::<T>(name);
+    }
+
+    /// Register a type holding entities so it's included in snapshots.
+    /// See [`TypeRegistry::register_mapped`].
+    pub fn register_mapped_type<T: Any + Serialize + DeserializeOwned + MapEntities>(
+        &mut self,
+        name: &'static str,
+    ) {
+        self.type_registry
+            .write()
+            .unwrap()
+            .register_mapped::<T>(name);
+    }
+
+    /// Register a type so the inspector and console can look inside of it.
+    /// See [`TypeRegistry::register_reflect`].
+    pub fn register_reflect_type<T: Reflect>(&mut self, name: &'static str) {
+        self.type_registry
+            .write()
+            .unwrap()
+            .register_reflect::<T>(name);
+    }
+
+    pub fn type_registry(&self) -> RwLockReadGuard<'_, TypeRegistry> {
+        self.type_registry.read().unwrap()
+    }
+
+    /// Queue up a change that needs `&mut World`, like spawning or despawning.
+    ///
+    /// Systems only get a `&World`, so [`Manager`] runs these in order after the systems are done.
+    ///
+    /// # Example
+    /// ```rs
+    /// world.queue_command(move |world| {
+    ///     world.despawn(entity);
+    ///     Ok(())
+    /// });
+    /// ```
+    pub fn queue_command<F: FnOnce(&mut World) -> Result<()> + 'static>(&self, command: F) {
+        self.commands.write().unwrap().push(Box::new(command));
+    }
+
+    /// Run every queued command. Commands queued by commands also get run.
+    pub fn apply_commands(&mut self) -> Result<()> {
+        loop {
+            let commands = std::mem::take(&mut *self.commands.write().unwrap());
+            if commands.is_empty() {
+                return Ok(());
+            }
+
+            for command in commands {
+                command(self)?;
+            }
+        }
+    }
+
+    pub(crate) fn record_timing(&self, stage: Stage, address: usize, elapsed: Duration) {
+        if let Some(mut timings) = self.try_get_resource_mut::<SystemTimings>() {
+            timings.record(stage, address, elapsed);
+        }
+    }
+
     pub fn raise_event<E: EventWrapper + 'static, D: EventDataWrapper + 'static>(
         &self,
         event: E,
@@ -0,0 +1,711 @@
+//! Named actions instead of hard-coded keys.
+//!
+//! The game lists what the player can do as an [`Action`] enum and gives every action its default
+//! bindings in an [`InputMap`]. Systems then only ever ask the [`ActionState`] about actions, so
+//! the keys can change without them knowing. Bindings the player changed are saved to `input.toml`
+//! in the config dir, the game opts into that with [`InputMap::load_user_bindings`]. A map without
+//! it never touches the config dir, which is what tests want.
+//!
+//! Recordings store the keys that were pressed, not the actions, so replay them with the same
+//! bindings they were recorded with.
+//!
+//! # Example
+//! ```rs
+//! #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+//! enum GameAction { MoveUp, MoveDown, Save }
+//!
+//! impl Action for GameAction {
+//!     const ALL: &'static [Self] = &[GameAction::MoveUp, GameAction::MoveDown, GameAction::Save];
+//! }
+//!
+//! let bindings = InputMap::new()
+//!     .bind(GameAction::MoveUp, KeyCode::KeyW)
+//!     .bind(GameAction::MoveUp, KeyCode::ArrowUp)
+//!     .bind(GameAction::MoveDown, KeyCode::KeyS)
+//!     .bind(GameAction::Save, Binding::new([InputButton::Ctrl, KeyCode::KeyS.into()]))
+//!     .load_user_bindings();
+//! manager.integrate(input_partial())?.integrate(actions_partial(bindings))?;
+//!
+//! let actions = world.get_resource::<ActionState<GameAction>>();
+//! let speed = actions.axis(GameAction::MoveDown, GameAction::MoveUp);
+//! if actions.just_pressed(GameAction::Save) { .. }
+//! ```
+//!
+//! # Example `input.toml`
+//! ```toml
+//! [bindings]
+//! MoveUp = ["KeyW", "ArrowUp"]
+//! Save = ["Ctrl+KeyS"]
+//! PlaceBuilding = ["MouseLeft"]
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::{self, Debug, Display},
+    fs,
+    hash::Hash,
+    path::PathBuf,
+    str::FromStr,
+};
+
+use anyhow::{Result, anyhow};
+use log::*;
+use serde::{Deserialize, Serialize, de::IntoDeserializer};
+
+use crate::{
+    config::config_dir,
+    ecs::{
+        System, World,
+        input::{KeyCode, MouseButton},
+        input_state::ButtonInput,
+        order_up::OrderUp,
+        partial_manager::PartialManager,
+    },
+};
+
+/// Something the player can do. The `Debug` name is what shows up in `input.toml` and the
+/// controls settings.
+pub trait Action: Copy + Eq + Hash + Debug + 'static {
+    /// Every action, in the order the controls settings list them.
+    const ALL: &'static [Self];
+
+    fn name(&self) -> String {
+        format!("{self:?}")
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name() == name)
+    }
+}
+
+/// A single key or mouse button. The modifiers stand for both the left and right key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InputButton {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Ctrl,
+    Shift,
+    Alt,
+    Super,
+}
+
+impl InputButton {
+    pub const MODIFIERS: [InputButton; 4] = [
+        InputButton::Ctrl,
+        InputButton::Shift,
+        InputButton::Alt,
+        InputButton::Super,
+    ];
+
+    /// Whether `pressed`, an actual key or mouse button, counts as this.
+    pub fn matches(&self, pressed: InputButton) -> bool {
+        use KeyCode::*;
+        match self {
+            InputButton::Ctrl => pressed.is_key(&[ControlLeft, ControlRight]),
+            InputButton::Shift => pressed.is_key(&[ShiftLeft, ShiftRight]),
+            InputButton::Alt => pressed.is_key(&[AltLeft, AltRight]),
+            InputButton::Super => pressed.is_key(&[SuperLeft, SuperRight]),
+            _ => *self == pressed,
+        }
+    }
+
+    pub fn is_modifier(&self) -> bool {
+        Self::MODIFIERS.iter().any(|m| m.matches(*self))
+    }
+
+    fn is_key(&self, codes: &[KeyCode]) -> bool {
+        matches!(self, InputButton::Key(code) if codes.contains(code))
+    }
+}
+
+impl From<KeyCode> for InputButton {
+    fn from(code: KeyCode) -> Self {
+        InputButton::Key(code)
+    }
+}
+
+impl From<MouseButton> for InputButton {
+    fn from(button: MouseButton) -> Self {
+        InputButton::Mouse(button)
+    }
+}
+
+impl Display for InputButton {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputButton::Key(code) => write!(f, "{code:?}"),
+            InputButton::Mouse(MouseButton::Other(n)) => write!(f, "Mouse{n}"),
+            InputButton::Mouse(button) => write!(f, "Mouse{button:?}"),
+            modifier => write!(f, "{modifier:?}"),
+        }
+    }
+}
+
+impl FromStr for InputButton {
+    type Err = anyhow::Error;
+
+    /// `KeyW`, `MouseLeft`, `Mouse4` or `Ctrl`, the key names are winit's.
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        Ok(match s {
+            "Ctrl" | "Control" => InputButton::Ctrl,
+            "Shift" => InputButton::Shift,
+            "Alt" => InputButton::Alt,
+            "Super" => InputButton::Super,
+            "MouseLeft" => InputButton::Mouse(MouseButton::Left),
+            "MouseRight" => InputButton::Mouse(MouseButton::Right),
+            "MouseMiddle" => InputButton::Mouse(MouseButton::Middle),
+            "MouseBack" => InputButton::Mouse(MouseButton::Back),
+            "MouseForward" => InputButton::Mouse(MouseButton::Forward),
+            _ => match s.strip_prefix("Mouse").map(str::parse) {
+                Some(Ok(n)) => InputButton::Mouse(MouseButton::Other(n)),
+                _ => {
+                    let deserializer: serde::de::value::StrDeserializer<serde::de::value::Error> =
+                        s.into_deserializer();
+                    InputButton::Key(
+                        KeyCode::deserialize(deserializer)
+                            .map_err(|_| anyhow!("Unknown key or button \"{s}\""))?,
+                    )
+                }
+            },
+        })
+    }
+}
+
+/// Buttons that all have to be down for an action, one for a plain key or more for a chord like
+/// Ctrl+S. Anything else held at the same time doesn't matter, unless it makes another binding
+/// down too, then only the longest chord counts so Ctrl+S doesn't also press S.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Binding(pub Vec<InputButton>);
+
+impl Binding {
+    pub fn new(buttons: impl IntoIterator<Item = InputButton>) -> Self {
+        Self(buttons.into_iter().collect())
+    }
+
+    /// Whether every button is down, `down` says if a single one is.
+    pub fn is_down(&self, down: impl Fn(InputButton) -> bool) -> bool {
+        !self.0.is_empty() && self.0.iter().all(|button| down(*button))
+    }
+
+    /// Whether `other` has every button this one has and more, like Ctrl+S is to S.
+    pub fn is_part_of(&self, other: &Binding) -> bool {
+        self.0.len() < other.0.len() && self.0.iter().all(|button| other.0.contains(button))
+    }
+}
+
+impl<T: Into<InputButton>> From<T> for Binding {
+    fn from(button: T) -> Self {
+        Self(vec![button.into()])
+    }
+}
+
+impl Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, button) in self.0.iter().enumerate() {
+            if i != 0 {
+                write!(f, "+")?;
+            }
+            write!(f, "{button}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Binding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let buttons = s.split('+').map(str::parse).collect::<Result<Vec<_>>>()?;
+        Ok(Self(buttons))
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<Binding> for String {
+    fn from(binding: Binding) -> Self {
+        binding.to_string()
+    }
+}
+
+/// What `input.toml` looks like, actions by name so unknown ones can be skipped.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct BindingsFile {
+    bindings: BTreeMap<String, Vec<Binding>>,
+}
+
+/// Which bindings trigger which action.
+pub struct InputMap<A: Action> {
+    bindings: HashMap<A, Vec<Binding>>,
+    defaults: HashMap<A, Vec<Binding>>,
+    /// Waiting for the next key or mouse button to bind to the action, replacing the binding at
+    /// the index or adding one if there's none.
+    capturing: Option<(A, Option<usize>)>,
+    /// Came from [`InputMap::load_user_bindings`], so changes get saved back to `input.toml`.
+    persist: bool,
+}
+
+impl<A: Action> Default for InputMap<A> {
+    fn default() -> Self {
+        Self {
+            bindings: HashMap::new(),
+            defaults: HashMap::new(),
+            capturing: None,
+            persist: false,
+        }
+    }
+}
+
+impl<A: Action> InputMap<A> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a default binding for `action`.
+    pub fn bind(mut self, action: A, binding: impl Into<Binding>) -> Self {
+        let binding = binding.into();
+        self.defaults
+            .entry(action)
+            .or_default()
+            .push(binding.clone());
+        self.bindings.entry(action).or_default().push(binding);
+        self
+    }
+
+    pub fn bindings(&self, action: A) -> &[Binding] {
+        self.bindings
+            .get(&action)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn set_bindings(&mut self, action: A, bindings: Vec<Binding>) {
+        self.bindings.insert(action, bindings);
+    }
+
+    /// Remove the binding at `index`.
+    pub fn unbind(&mut self, action: A, index: usize) {
+        if let Some(bindings) = self.bindings.get_mut(&action)
+            && index < bindings.len()
+        {
+            bindings.remove(index);
+        }
+    }
+
+    /// Put every action back to the bindings the game came with.
+    pub fn reset(&mut self) {
+        self.bindings = self.defaults.clone();
+    }
+
+    /// The bindings the game came with for `action`.
+    pub fn default_bindings(&self, action: A) -> &[Binding] {
+        self.defaults
+            .get(&action)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Whether every action has its default bindings. No bindings is the same as never having
+    /// had any.
+    pub fn is_default(&self) -> bool {
+        A::ALL
+            .iter()
+            .all(|a| self.bindings(*a) == self.default_bindings(*a))
+    }
+
+    /// Bind the next key or mouse button pressed to `action`, replacing the binding at `index` or
+    /// adding a new one if it's `None`. Escape cancels.
+    pub fn start_capture(&mut self, action: A, index: Option<usize>) {
+        self.capturing = Some((action, index));
+    }
+
+    pub fn cancel_capture(&mut self) {
+        self.capturing = None;
+    }
+
+    /// The action and binding waiting for a key, if any.
+    pub fn capturing(&self) -> Option<(A, Option<usize>)> {
+        self.capturing
+    }
+
+    /// Finish capturing with `pressed` and whichever modifiers are held. Returns whether it was
+    /// used, if so nothing else should see it.
+    fn capture(&mut self, pressed: InputButton, keys: &ButtonInput<KeyCode>) -> bool {
+        let Some((action, index)) = self.capturing else {
+            return false;
+        };
+        // Modifiers are only ever part of a chord, wait for the key they go with
+        if pressed.is_modifier() {
+            return false;
+        }
+        self.capturing = None;
+        if pressed == InputButton::Key(KeyCode::Escape) {
+            return true;
+        }
+
+        let modifiers = InputButton::MODIFIERS
+            .into_iter()
+            .filter(|m| keys.get_pressed().any(|k| m.matches(InputButton::Key(k))));
+        let binding = Binding::new(modifiers.chain([pressed]));
+        info!("Bound {binding} to {}", action.name());
+
+        let bindings = self.bindings.entry(action).or_default();
+        match index.filter(|i| *i < bindings.len()) {
+            Some(i) => bindings[i] = binding,
+            None => bindings.push(binding),
+        }
+        if let Err(err) = self.save() {
+            warn!("Couldn't save the controls: {err}");
+        }
+        true
+    }
+
+    /// `input.toml` in the config dir.
+    pub fn path() -> Option<PathBuf> {
+        Some(config_dir()?.join("input.toml"))
+    }
+
+    /// Replace the defaults with whatever the player changed and save any changes made from now
+    /// on. Unknown actions and a broken file are only warned about, the defaults still work.
+    pub fn load_user_bindings(mut self) -> Self {
+        self.persist = true;
+        self.load();
+        self
+    }
+
+    fn load(&mut self) {
+        let Some(path) = Self::path().filter(|path| path.exists()) else {
+            return;
+        };
+        let file: BindingsFile = match fs::read_to_string(&path)
+            .map_err(|err| anyhow!("{err}"))
+            .and_then(|text| toml::from_str(&text).map_err(|err| anyhow!("{err}")))
+        {
+            Ok(file) => file,
+            Err(err) => {
+                warn!("Ignoring the controls in {}: {err}", path.display());
+                return;
+            }
+        };
+
+        for (name, bindings) in file.bindings {
+            match A::from_name(&name) {
+                Some(action) => self.set_bindings(action, bindings),
+                None => warn!("Unknown action {name:?} in {}", path.display()),
+            }
+        }
+        info!("Loaded the controls from {}", path.display());
+    }
+
+    /// Save the bindings that aren't the default anymore. Does nothing unless they came from
+    /// [`InputMap::load_user_bindings`].
+    pub fn save(&self) -> Result<()> {
+        if !self.persist {
+            return Ok(());
+        }
+        let path = Self::path().ok_or_else(|| anyhow!("No config dir to save the controls in"))?;
+        let bindings = A::ALL
+            .iter()
+            .filter(|a| self.bindings(**a) != self.default_bindings(**a))
+            .map(|a| (a.name(), self.bindings(*a).to_vec()))
+            .collect();
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, toml::to_string(&BindingsFile { bindings })?)?;
+        debug!("Saved the controls to {}", path.display());
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct ButtonState {
+    pressed: bool,
+    just_pressed: bool,
+    just_released: bool,
+}
+
+/// What every action is doing this frame.
+pub struct ActionState<A: Action> {
+    actions: HashMap<A, ButtonState>,
+    /// Presses used up by binding them to something, ignored until they're let go of.
+    consumed: HashSet<InputButton>,
+}
+
+impl<A: Action> Default for ActionState<A> {
+    fn default() -> Self {
+        Self {
+            actions: HashMap::new(),
+            consumed: HashSet::new(),
+        }
+    }
+}
+
+impl<A: Action> ActionState<A> {
+    fn state(&self, action: A) -> ButtonState {
+        self.actions.get(&action).copied().unwrap_or_default()
+    }
+
+    /// Any of the action's bindings is down.
+    pub fn pressed(&self, action: A) -> bool {
+        self.state(action).pressed
+    }
+
+    /// Started this frame.
+    pub fn just_pressed(&self, action: A) -> bool {
+        self.state(action).just_pressed
+    }
+
+    /// Stopped this frame.
+    pub fn just_released(&self, action: A) -> bool {
+        self.state(action).just_released
+    }
+
+    /// `-1.0` when only `negative` is pressed, `1.0` when only `positive` is, `0.0` otherwise.
+    pub fn axis(&self, negative: A, positive: A) -> f32 {
+        self.pressed(positive) as i8 as f32 - self.pressed(negative) as i8 as f32
+    }
+
+    /// Work out every action from the frame's keys and mouse buttons. Something pressed and let
+    /// go of within the frame still counts as down for it.
+    pub fn update(
+        &mut self,
+        map: &InputMap<A>,
+        keys: &ButtonInput<KeyCode>,
+        mouse: &ButtonInput<MouseButton>,
+    ) {
+        let down: Vec<InputButton> = keys
+            .get_pressed()
+            .chain(keys.get_just_pressed())
+            .map(InputButton::Key)
+            .chain(
+                mouse
+                    .get_pressed()
+                    .chain(mouse.get_just_pressed())
+                    .map(InputButton::Mouse),
+            )
+            .filter(|b| !self.consumed.contains(b))
+            .collect();
+        self.consumed.retain(|b| match b {
+            InputButton::Key(code) => keys.pressed(*code),
+            InputButton::Mouse(button) => mouse.pressed(*button),
+            _ => false,
+        });
+
+        let is_down =
+            |binding: &Binding| binding.is_down(|button| down.iter().any(|b| button.matches(*b)));
+        let chords: Vec<&Binding> = A::ALL
+            .iter()
+            .flat_map(|a| map.bindings(*a))
+            .filter(|binding| binding.0.len() > 1 && is_down(binding))
+            .collect();
+
+        for &action in A::ALL {
+            let down = map.bindings(action).iter().any(|binding| {
+                is_down(binding) && !chords.iter().any(|chord| binding.is_part_of(chord))
+            });
+            let state = self.actions.entry(action).or_default();
+            state.just_pressed = down && !state.pressed;
+            state.just_released = !down && state.pressed;
+            state.pressed = down;
+        }
+    }
+}
+
+/// Hands the frame's presses to the [`InputMap`] while it's capturing a new binding, the one it
+/// takes doesn't trigger anything.
+///
+/// Runs on the frame's [`ButtonInput`]s rather than as the input comes in, so a modifier pressed
+/// in the same frame as the key still ends up in the chord.
+pub fn capture_binding<A: Action>(world: &World) -> Result<()> {
+    let mut map = world.get_resource_mut::<InputMap<A>>();
+    if map.capturing().is_none() {
+        return Ok(());
+    }
+    let keys = world.get_resource::<ButtonInput<KeyCode>>();
+    let mouse = world.get_resource::<ButtonInput<MouseButton>>();
+
+    let pressed = keys
+        .get_just_pressed()
+        .map(InputButton::Key)
+        .chain(mouse.get_just_pressed().map(InputButton::Mouse));
+    for button in pressed {
+        if map.capture(button, &keys) {
+            world
+                .get_resource_mut::<ActionState<A>>()
+                .consumed
+                .insert(button);
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Works out the [`ActionState`] for the frame.
+pub fn update_actions<A: Action>(world: &World) -> Result<()> {
+    let map = world.get_resource::<InputMap<A>>();
+    let keys = world.get_resource::<ButtonInput<KeyCode>>();
+    let mouse = world.get_resource::<ButtonInput<MouseButton>>();
+    world
+        .get_resource_mut::<ActionState<A>>()
+        .update(&map, &keys, &mouse);
+    Ok(())
+}
+
+/// An [`ActionState`] for `A` with `bindings`, call [`InputMap::load_user_bindings`] on them
+/// first for the player's own.
+/// Goes after [`input_partial`](crate::ecs::input_state::input_partial), which it reads the keys
+/// from, and before the plugins that read it so it's up to date for them.
+pub fn actions_partial<A: Action>(bindings: InputMap<A>) -> PartialManager {
+    PartialManager::new()
+        .add_resource(bindings)
+        .add_resource(ActionState::<A>::default())
+        .add_systems(
+            (
+                capture_binding::<A> as System,
+                update_actions::<A> as System,
+            )
+                .order_up(),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{input_state::input_partial, test_app::TestApp};
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    enum TestAction {
+        MoveDown,
+        Save,
+    }
+
+    impl Action for TestAction {
+        const ALL: &'static [Self] = &[TestAction::MoveDown, TestAction::Save];
+    }
+
+    fn actions_app() -> TestApp {
+        let bindings = InputMap::new()
+            .bind(TestAction::MoveDown, KeyCode::KeyS)
+            .bind(
+                TestAction::Save,
+                Binding::new([InputButton::Ctrl, KeyCode::KeyS.into()]),
+            );
+        TestApp::new()
+            .unwrap()
+            .integrate(input_partial())
+            .unwrap()
+            .integrate(actions_partial(bindings))
+            .unwrap()
+    }
+
+    fn pressed(app: &TestApp, action: TestAction) -> bool {
+        app.world()
+            .get_resource::<ActionState<TestAction>>()
+            .pressed(action)
+    }
+
+    #[test]
+    fn a_chord_doesnt_press_the_keys_in_it() {
+        let mut app = actions_app();
+
+        app.press_key(KeyCode::ControlLeft);
+        app.press_key(KeyCode::KeyS);
+        app.update().unwrap();
+
+        assert!(pressed(&app, TestAction::Save));
+        assert!(!pressed(&app, TestAction::MoveDown));
+    }
+
+    #[test]
+    fn a_key_on_its_own_still_works() {
+        let mut app = actions_app();
+
+        app.press_key(KeyCode::KeyS);
+        app.update().unwrap();
+
+        assert!(pressed(&app, TestAction::MoveDown));
+        assert!(!pressed(&app, TestAction::Save));
+    }
+
+    #[test]
+    fn capturing_binds_the_next_key_and_swallows_it() {
+        let mut app = actions_app();
+        app.world()
+            .get_resource_mut::<InputMap<TestAction>>()
+            .start_capture(TestAction::MoveDown, Some(0));
+
+        app.press_key(KeyCode::KeyJ);
+        app.update().unwrap();
+
+        let map = app.world().get_resource::<InputMap<TestAction>>();
+        assert_eq!(map.bindings(TestAction::MoveDown), [KeyCode::KeyJ.into()]);
+        assert!(map.capturing().is_none());
+        drop(map);
+        assert!(!pressed(&app, TestAction::MoveDown));
+    }
+
+    #[test]
+    fn capturing_keeps_a_modifier_pressed_in_the_same_frame() {
+        let mut app = actions_app();
+        app.world()
+            .get_resource_mut::<InputMap<TestAction>>()
+            .start_capture(TestAction::Save, None);
+
+        app.press_key(KeyCode::KeyO);
+        app.press_key(KeyCode::ControlLeft);
+        app.update().unwrap();
+
+        let map = app.world().get_resource::<InputMap<TestAction>>();
+        assert_eq!(
+            map.bindings(TestAction::Save)[1],
+            Binding::new([InputButton::Ctrl, KeyCode::KeyO.into()])
+        );
+        assert!(map.capturing().is_none());
+    }
+
+    #[test]
+    fn escape_cancels_capturing() {
+        let mut app = actions_app();
+        app.world()
+            .get_resource_mut::<InputMap<TestAction>>()
+            .start_capture(TestAction::MoveDown, Some(0));
+
+        app.press_key(KeyCode::Escape);
+        app.update().unwrap();
+
+        let map = app.world().get_resource::<InputMap<TestAction>>();
+        assert_eq!(map.bindings(TestAction::MoveDown), [KeyCode::KeyS.into()]);
+        assert!(map.capturing().is_none());
+    }
+
+    #[test]
+    fn no_bindings_is_the_default_for_an_action_without_defaults() {
+        let mut map = InputMap::new().bind(TestAction::MoveDown, KeyCode::KeyS);
+        assert!(map.is_default());
+
+        map.set_bindings(TestAction::Save, vec![KeyCode::KeyP.into()]);
+        assert!(!map.is_default());
+        map.unbind(TestAction::Save, 0);
+        assert!(map.is_default());
+
+        map.unbind(TestAction::MoveDown, 0);
+        assert!(!map.is_default());
+        map.reset();
+        assert!(map.is_default());
+    }
+}
@@ -0,0 +1,108 @@
+use std::any::{Any, TypeId};
+
+use seq_macro::seq;
+
+pub use gristmill_derive::Bundle;
+
+/// A group of components that get put on an entity together.
+///
+/// Tuples of up to 16 components are bundles already, for anything bigger or anything used in
+/// more than one place derive it:
+/// ```rs
+/// #[derive(Bundle)]
+/// struct BugBundle {
+///     position: GridPosition,
+///     health: Health,
+///     #[bundle]
+///     sprite: SpriteBundle,
+/// }
+///
+/// let bug = world.spawn_bundle(BugBundle { .. });
+/// let other_bug = world.spawn_bundle((GridPosition::new(2, 3), Health(10)));
+/// ```
+pub trait Bundle: 'static {
+    /// Split the bundle up into its components, nested bundles included.
+    fn into_components(self) -> Vec<(TypeId, Box<dyn Any>)>;
+}
+
+/// Generates the [`Bundle`] impls for tuples, the same way `gen_order_up_impl!` does for `OrderUp`.
+///
+/// `gen_bundle_impl!()` generates tuples with 1 to 16 components, `gen_bundle_impl!(5)` only the
+/// one with 6.
+macro_rules! gen_bundle_impl {
+    () => {
+        seq!(N in 0..16 {
+            gen_bundle_impl!(N);
+        });
+    };
+    ($n:literal) => {
+        seq!(T in 0..=$n {
+            impl<#(C~T: Any,)*> Bundle for (#(C~T,)*) {
+                fn into_components(self) -> Vec<(TypeId, Box<dyn Any>)> {
+                    vec![#((TypeId::of::<C~T>(), Box::new(self.T) as Box<dyn Any>),)*]
+                }
+            }
+        });
+    };
+}
+
+gen_bundle_impl!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{World, name::Name, spatial::GridPosition};
+
+    #[derive(Debug, PartialEq)]
+    struct Health(u32);
+
+    #[derive(Debug, PartialEq)]
+    struct Sprite(&'static str);
+
+    #[derive(Debug, PartialEq)]
+    struct Layer(i32);
+
+    #[derive(Bundle)]
+    struct SpriteBundle(Sprite, Layer);
+
+    #[derive(Bundle)]
+    struct BugBundle<T: Any> {
+        position: GridPosition,
+        health: Health,
+        #[bundle]
+        sprite: SpriteBundle,
+        extra: T,
+    }
+
+    #[test]
+    fn derived_bundles_spawn_every_component() {
+        let mut world = World::new();
+
+        let bug = world.spawn_bundle(BugBundle {
+            position: GridPosition::new(2, 3),
+            health: Health(10),
+            sprite: SpriteBundle(Sprite("bug"), Layer(1)),
+            extra: Name("Bug".to_string()),
+        });
+
+        assert_eq!(
+            **world.get_component::<GridPosition>(bug).unwrap(),
+            GridPosition::new(2, 3)
+        );
+        assert_eq!(**world.get_component::<Health>(bug).unwrap(), Health(10));
+        assert_eq!(**world.get_component::<Sprite>(bug).unwrap(), Sprite("bug"));
+        assert_eq!(**world.get_component::<Layer>(bug).unwrap(), Layer(1));
+        assert_eq!(
+            **world.get_component::<Name>(bug).unwrap(),
+            Name("Bug".to_string())
+        );
+        // The nested bundle is flattened, not stored as a component of its own
+        assert!(!world.has_component::<SpriteBundle>(bug));
+    }
+
+    #[test]
+    #[should_panic(expected = "same component twice")]
+    fn bundles_cant_repeat_a_component() {
+        World::new().spawn_bundle((Health(1), Health(2)));
+    }
+}
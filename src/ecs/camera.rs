@@ -0,0 +1,409 @@
+//! The 2D camera the renderer looks through.
+//!
+//! A [`Camera2D`] is a component, the renderer draws from the first active one. Gameplay moves its
+//! target around and [`update_cameras`] eases the camera there, so nothing jumps unless damping is
+//! turned off. Scrolling zooms toward whatever is under the cursor.
+//!
+//! # Example
+//! ```rs
+//! let manager = Manager::new()?.integrate(camera_partial(
+//!     Camera2D::new()
+//!         .with_zoom_range(0.5, 8.0)
+//!         .with_bounds([-64.0, -64.0], [64.0, 64.0]),
+//! ))?;
+//!
+//! for (_, mut camera) in world.query_mut::<Camera2D>() {
+//!     camera.move_by([0.0, 1.0]);
+//! }
+//! ```
+
+use anyhow::Result;
+
+use crate::ecs::{
+    System, World,
+    input_state::{CursorPosition, MouseScroll},
+    name::Name,
+    order_up::OrderUp,
+    partial_manager::PartialManager,
+    reflect::impl_reflect,
+    time::Time,
+};
+
+/// Close enough to the target to stop easing.
+const EPSILON: f32 = 1e-4;
+/// The furthest out any camera can zoom, whatever its range says. Zoom 0 would show infinitely
+/// much of the world.
+const MIN_ZOOM: f32 = 1e-3;
+
+/// An orthographic camera looking down at the ground.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Camera2D {
+    /// The world position in the middle of the screen.
+    pub position: [f32; 2],
+    /// 2 shows half as much of the world as 1.
+    pub zoom: f32,
+    /// Where [`Camera2D::position`] is easing to.
+    pub target_position: [f32; 2],
+    /// What [`Camera2D::zoom`] is easing to.
+    pub target_zoom: f32,
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+    /// World units from the top of the screen to the bottom at zoom 1.
+    pub view_height: f32,
+    /// The smallest and biggest corner of the part of the world the camera can show.
+    pub bounds: Option<[[f32; 2]; 2]>,
+    /// Roughly how many seconds it takes to catch up with the target, 0 snaps to it.
+    pub damping: f32,
+    /// How much one line of scrolling zooms in.
+    pub zoom_per_line: f32,
+    /// Whether the renderer can draw from this one, it takes the first active camera.
+    pub active: bool,
+    /// The size of what's being drawn to in pixels, kept up to date by the engine.
+    pub viewport: [f32; 2],
+    /// The world point that stays put on screen while zooming.
+    zoom_anchor: Option<[f32; 2]>,
+}
+
+impl_reflect!(Camera2D {
+    position,
+    zoom,
+    target_position,
+    target_zoom,
+    min_zoom,
+    max_zoom,
+    view_height,
+    bounds,
+    damping,
+    zoom_per_line,
+    active,
+    viewport,
+});
+
+impl Default for Camera2D {
+    fn default() -> Self {
+        Self {
+            position: [0.0; 2],
+            zoom: 1.0,
+            target_position: [0.0; 2],
+            target_zoom: 1.0,
+            min_zoom: 0.25,
+            max_zoom: 4.0,
+            view_height: 6.0,
+            bounds: None,
+            damping: 0.1,
+            zoom_per_line: 1.15,
+            active: true,
+            viewport: [1280.0, 720.0],
+            zoom_anchor: None,
+        }
+    }
+}
+
+impl Camera2D {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_position(mut self, position: [f32; 2]) -> Self {
+        self.position = position;
+        self.target_position = position;
+        self
+    }
+
+    pub fn with_zoom(mut self, zoom: f32) -> Self {
+        self.zoom = zoom;
+        self.target_zoom = zoom;
+        self
+    }
+
+    /// # Panics
+    /// Panics unless `0 < min <= max`.
+    pub fn with_zoom_range(mut self, min: f32, max: f32) -> Self {
+        assert!(
+            0.0 < min && min <= max,
+            "Zoom range has to be positive and go from small to big, got {min} to {max}"
+        );
+        self.min_zoom = min;
+        self.max_zoom = max;
+        self
+    }
+
+    pub fn with_bounds(mut self, min: [f32; 2], max: [f32; 2]) -> Self {
+        self.bounds = Some([min, max]);
+        self
+    }
+
+    pub fn with_damping(mut self, damping: f32) -> Self {
+        self.damping = damping;
+        self
+    }
+
+    /// How much of the world fits on screen right now, in world units.
+    pub fn view_size(&self) -> [f32; 2] {
+        let height = self.view_height / self.zoom;
+        let aspect = match self.viewport[1] > 0.0 {
+            true => self.viewport[0] / self.viewport[1],
+            false => 1.0,
+        };
+        [height * aspect, height]
+    }
+
+    /// The smallest and biggest corner of what's on screen right now.
+    pub fn visible_rect(&self) -> [[f32; 2]; 2] {
+        let [width, height] = self.view_size();
+        let [x, y] = self.position;
+        [
+            [x - width / 2.0, y - height / 2.0],
+            [x + width / 2.0, y + height / 2.0],
+        ]
+    }
+
+    pub fn move_to(&mut self, position: [f32; 2]) {
+        self.target_position = position;
+    }
+
+    pub fn move_by(&mut self, delta: [f32; 2]) {
+        self.target_position[0] += delta[0];
+        self.target_position[1] += delta[1];
+    }
+
+    /// Zoom to `zoom`, keeping `anchor` where it is on screen or zooming into the middle without
+    /// one.
+    pub fn zoom_to(&mut self, zoom: f32, anchor: Option<[f32; 2]>) {
+        self.target_zoom = self.clamp_zoom(zoom);
+        self.zoom_anchor = anchor;
+    }
+
+    /// `zoom` inside of the zoom range. Never panics, even with the range set backwards from the
+    /// inspector: the max wins, and NaNs are ignored.
+    fn clamp_zoom(&self, zoom: f32) -> f32 {
+        zoom.max(self.min_zoom).min(self.max_zoom).max(MIN_ZOOM)
+    }
+
+    /// Zoom in by `lines` of scrolling, out for negative ones.
+    pub fn zoom_by_lines(&mut self, lines: f32, anchor: Option<[f32; 2]>) {
+        self.zoom_to(self.target_zoom * self.zoom_per_line.powf(lines), anchor);
+    }
+
+    /// Jump straight to the target.
+    pub fn snap(&mut self) {
+        self.step(0.0, true);
+    }
+
+    /// Ease toward the target for `delta` seconds.
+    pub fn update(&mut self, delta: f32) {
+        self.step(delta, false);
+    }
+
+    fn step(&mut self, delta: f32, snap: bool) {
+        let t = match snap || self.damping <= 0.0 {
+            true => 1.0,
+            false => 1.0 - (-delta / self.damping).exp(),
+        };
+
+        self.zoom = self.zoom.max(MIN_ZOOM);
+        let last_zoom = self.zoom;
+        self.target_zoom = self.clamp_zoom(self.target_zoom);
+        // Eased in log space so zooming in and out take just as long
+        self.zoom = match t >= 1.0 || (self.target_zoom - self.zoom).abs() < EPSILON {
+            true => self.target_zoom,
+            false => (self.zoom.ln() + (self.target_zoom.ln() - self.zoom.ln()) * t).exp(),
+        };
+        if let Some(anchor) = self.zoom_anchor {
+            let ratio = last_zoom / self.zoom;
+            for ((position, target), anchor) in self
+                .position
+                .iter_mut()
+                .zip(self.target_position.iter_mut())
+                .zip(anchor)
+            {
+                *position = anchor - (anchor - *position) * ratio;
+                *target = anchor - (anchor - *target) * ratio;
+            }
+        }
+        if self.zoom == self.target_zoom {
+            self.zoom_anchor = None;
+        }
+
+        self.target_position = self.clamp(self.target_position);
+        for (position, target) in self.position.iter_mut().zip(self.target_position) {
+            let distance = target - *position;
+            *position = match t >= 1.0 || distance.abs() < EPSILON {
+                true => target,
+                false => *position + distance * t,
+            };
+        }
+        self.position = self.clamp(self.position);
+    }
+
+    /// `position` moved so the screen stays inside of the bounds, or centered on them when they're
+    /// smaller than the screen.
+    fn clamp(&self, mut position: [f32; 2]) -> [f32; 2] {
+        let Some([min, max]) = self.bounds else {
+            return position;
+        };
+        let size = self.view_size();
+        for i in 0..2 {
+            let half = size[i] / 2.0;
+            position[i] = match max[i] - min[i] > size[i] {
+                true => position[i].clamp(min[i] + half, max[i] - half),
+                false => (min[i] + max[i]) / 2.0,
+            };
+        }
+        position
+    }
+}
+
+/// The camera the renderer draws from, the first active one. `None` without any.
+pub fn active_camera(world: &World) -> Option<Camera2D> {
+    world
+        .query::<Camera2D>()
+        .into_iter()
+        .find(|(_, camera)| camera.active)
+        .map(|(_, camera)| (**camera).clone())
+}
+
+/// Zooms the active camera toward the cursor when scrolling.
+pub fn zoom_cameras(world: &World) -> Result<()> {
+    let lines = world.get_resource::<MouseScroll>().lines[1];
+    if lines == 0.0 {
+        return Ok(());
+    }
+    let anchor = world.get_resource::<CursorPosition>().world;
+
+    if let Some((_, mut camera)) = world
+        .query_mut::<Camera2D>()
+        .into_iter()
+        .find(|(_, camera)| camera.active)
+    {
+        camera.zoom_by_lines(lines, anchor);
+    }
+    Ok(())
+}
+
+/// Eases every camera toward its target. Runs on real time so the camera still moves while
+/// paused.
+pub fn update_cameras(world: &World) -> Result<()> {
+    let delta = world.get_resource::<Time>().real_delta_secs();
+    for (_, mut camera) in world.query_mut::<Camera2D>() {
+        camera.update(delta);
+    }
+    Ok(())
+}
+
+/// Spawns `camera` and keeps it moving, add it after
+/// [`input_partial`](crate::ecs::input_state::input_partial) since it zooms with the scroll
+/// wheel.
+pub fn camera_partial(camera: Camera2D) -> PartialManager {
+    PartialManager::new()
+        .add_bundle((Name::new("Camera"), camera))
+        .register_reflect_type::<Camera2D>("camera_2d")
+        .add_systems((zoom_cameras as System, update_cameras as System).order_up())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: [f32; 2], b: [f32; 2]) {
+        assert!(
+            (a[0] - b[0]).abs() < 1e-3 && (a[1] - b[1]).abs() < 1e-3,
+            "{a:?} isn't {b:?}"
+        );
+    }
+
+    /// Where `point` is on screen, in world units from the middle at zoom 1.
+    fn on_screen(camera: &Camera2D, point: [f32; 2]) -> [f32; 2] {
+        [
+            (point[0] - camera.position[0]) * camera.zoom,
+            (point[1] - camera.position[1]) * camera.zoom,
+        ]
+    }
+
+    #[test]
+    fn zooming_keeps_the_anchor_in_place() {
+        let mut camera = Camera2D::new().with_position([1.0, -2.0]);
+        let anchor = [3.0, 0.5];
+        let before = on_screen(&camera, anchor);
+
+        camera.zoom_by_lines(3.0, Some(anchor));
+        camera.snap();
+        assert!(camera.zoom > 1.0);
+        assert_close(on_screen(&camera, anchor), before);
+
+        camera.zoom_by_lines(-5.0, Some(anchor));
+        camera.snap();
+        assert!(camera.zoom < 1.0);
+        assert_close(on_screen(&camera, anchor), before);
+    }
+
+    #[test]
+    fn bounds_keep_the_screen_inside() {
+        let mut camera = Camera2D::new().with_bounds([-20.0, -10.0], [20.0, 10.0]);
+
+        for target in [[100.0, 100.0], [-100.0, 3.0], [5.0, -100.0]] {
+            camera.move_to(target);
+            camera.snap();
+            let [min, max] = camera.visible_rect();
+            assert!(min[0] >= -20.0 - EPSILON && min[1] >= -10.0 - EPSILON);
+            assert!(max[0] <= 20.0 + EPSILON && max[1] <= 10.0 + EPSILON);
+        }
+    }
+
+    #[test]
+    fn bounds_smaller_than_the_screen_are_centered() {
+        let mut camera = Camera2D::new().with_bounds([0.0, 0.0], [4.0, 2.0]);
+
+        camera.move_to([50.0, -50.0]);
+        camera.snap();
+
+        assert_eq!(camera.position, [2.0, 1.0]);
+    }
+
+    #[test]
+    fn no_damping_snaps() {
+        let mut camera = Camera2D::new().with_damping(0.0);
+
+        camera.move_to([5.0, 7.0]);
+        camera.zoom_to(2.0, None);
+        camera.update(1.0 / 60.0);
+
+        assert_eq!(camera.position, [5.0, 7.0]);
+        assert_eq!(camera.zoom, 2.0);
+    }
+
+    #[test]
+    fn damping_eases_toward_the_target() {
+        let mut camera = Camera2D::new();
+
+        camera.move_to([10.0, 0.0]);
+        camera.update(1.0 / 60.0);
+
+        assert!(camera.position[0] > 0.0 && camera.position[0] < 10.0);
+    }
+
+    #[test]
+    fn nan_zoom_stays_in_range() {
+        let mut camera = Camera2D::new();
+
+        camera.zoom_to(f32::NAN, None);
+        camera.snap();
+
+        assert!(camera.zoom.is_finite());
+        assert!((camera.min_zoom..=camera.max_zoom).contains(&camera.zoom));
+    }
+
+    #[test]
+    fn backwards_zoom_range_uses_the_max() {
+        let mut camera = Camera2D::new();
+        // The builder won't take a backwards range, the inspector can still set one
+        camera.min_zoom = 4.0;
+        camera.max_zoom = 2.0;
+
+        for zoom in [1.0, 3.0, 8.0] {
+            camera.zoom_to(zoom, None);
+            camera.snap();
+            assert_eq!(camera.zoom, 2.0);
+        }
+    }
+}
@@ -0,0 +1,527 @@
+//! Moving the [`Camera2D`] around with the keyboard and mouse.
+//!
+//! Panning goes through the game's own actions so it can be rebound with everything else, and is
+//! scaled by the zoom so it always crosses the screen at the same speed. Holding the drag button
+//! (middle mouse by default) drags the world along with the cursor, and pushing the cursor against
+//! the edge of the window scrolls. Raise [`FocusCamera`] to go look at an entity.
+//!
+//! Everything is tuned with the [`CameraControls`] resource, which the game copies out of the
+//! `[camera]` section of the engine config at startup. It's read every frame, so editing
+//! `camera_controls` in the inspector works right away, but the edits aren't saved back to the
+//! config file.
+//!
+//! # Example
+//! ```rs
+//! let manager = Manager::new()?.integrate(camera_controller_partial(
+//!     CameraActions {
+//!         pan_up: GameAction::MoveUp,
+//!         pan_down: GameAction::MoveDown,
+//!         pan_left: GameAction::MoveLeft,
+//!         pan_right: GameAction::MoveRight,
+//!         zoom_in: GameAction::ZoomIn,
+//!         zoom_out: GameAction::ZoomOut,
+//!     },
+//!     config.camera.clone(),
+//! ))?;
+//!
+//! world.raise_event(FocusCamera(mill), NoEventData);
+//! ```
+
+use anyhow::Result;
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::{
+    System, World,
+    actions::{Action, ActionState},
+    camera::Camera2D,
+    entity::Entity,
+    events::{EcsEvent, LemgineEventData},
+    input::{InputCapture, MouseButton},
+    input_state::{ButtonInput, CursorPosition, MouseMotion},
+    order_up::OrderUp,
+    partial_manager::PartialManager,
+    picking::TileGrid,
+    reflect::impl_reflect,
+    spatial::GridPosition,
+    time::Time,
+};
+
+/// How the camera controller behaves.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraControls {
+    /// Panning speed in world units a second at zoom 1, slower when zoomed in.
+    pub pan_speed: f32,
+    /// Seconds to get up to full speed, 0 starts moving at full speed.
+    pub acceleration_time: f32,
+    /// Seconds to stop after letting go, 0 stops dead.
+    pub deceleration_time: f32,
+    /// Lines of scrolling a second while holding a zoom key.
+    pub zoom_speed: f32,
+    /// Hold this to drag the world around, `None` turns dragging off.
+    pub drag_button: Option<MouseButton>,
+    /// How close to the edge of the window in pixels the cursor has to be to scroll, 0 turns edge
+    /// scrolling off.
+    pub edge_scroll_margin: f32,
+    /// Zoom to this when focusing on an entity, 0 keeps the zoom.
+    pub focus_zoom: f32,
+}
+
+// The drag button is left out, there's no reflecting mouse buttons
+impl_reflect!(CameraControls {
+    pan_speed,
+    acceleration_time,
+    deceleration_time,
+    zoom_speed,
+    edge_scroll_margin,
+    focus_zoom,
+});
+
+impl Default for CameraControls {
+    fn default() -> Self {
+        Self {
+            pan_speed: 8.0,
+            acceleration_time: 0.15,
+            deceleration_time: 0.1,
+            zoom_speed: 8.0,
+            drag_button: Some(MouseButton::Middle),
+            edge_scroll_margin: 8.0,
+            focus_zoom: 0.0,
+        }
+    }
+}
+
+/// Which of the game's actions move the camera.
+#[derive(Clone, Copy, Debug)]
+pub struct CameraActions<A: Action> {
+    pub pan_up: A,
+    pub pan_down: A,
+    pub pan_left: A,
+    pub pan_right: A,
+    pub zoom_in: A,
+    pub zoom_out: A,
+}
+
+/// Raise it to point the active camera at the middle of the entity's tile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FocusCamera(pub Entity);
+
+impl EcsEvent for FocusCamera {}
+
+/// How fast the camera is panning in world units a second, and whether it's being dragged.
+#[derive(Default)]
+struct CameraMotion {
+    velocity: [f32; 2],
+    dragging: bool,
+}
+
+/// Moves `current` toward `target` by at most `step`.
+fn approach(current: f32, target: f32, step: f32) -> f32 {
+    match current < target {
+        true => (current + step).min(target),
+        false => (current - step).max(target),
+    }
+}
+
+/// Which way to scroll with the cursor at `position` in a window `size` big.
+fn edge_direction(position: [f32; 2], size: [f32; 2], margin: f32) -> [f32; 2] {
+    let axis = |position: f32, size: f32| match position {
+        p if p <= margin => -1.0,
+        p if p >= size - 1.0 - margin => 1.0,
+        _ => 0.0,
+    };
+    // Window y goes down, world y goes up
+    [axis(position[0], size[0]), -axis(position[1], size[1])]
+}
+
+/// Pans and zooms the active camera.
+pub fn control_camera<A: Action>(world: &World) -> Result<()> {
+    let controls = (**world.get_resource::<CameraControls>()).clone();
+    let actions = **world.get_resource::<CameraActions<A>>();
+    let delta = world.get_resource::<Time>().real_delta_secs();
+    let over_gui = world
+        .try_get_resource::<InputCapture>()
+        .is_some_and(|capture| capture.wants_pointer);
+
+    let Some((_, mut camera)) = world
+        .query_mut::<Camera2D>()
+        .into_iter()
+        .find(|(_, camera)| camera.active)
+    else {
+        return Ok(());
+    };
+    let mut motion = world.get_resource_mut::<CameraMotion>();
+
+    // Dragging keeps whatever was under the cursor under it
+    if let Some(button) = controls.drag_button {
+        let mouse = world.get_resource::<ButtonInput<MouseButton>>();
+        if mouse.just_pressed(button) && !over_gui {
+            motion.dragging = true;
+        } else if !mouse.pressed(button) {
+            motion.dragging = false;
+        }
+    }
+    if motion.dragging {
+        let [dx, dy] = world.get_resource::<MouseMotion>().delta;
+        let units_per_pixel = camera.view_size()[1] / camera.viewport[1].max(1.0);
+        camera.move_by([-dx as f32 * units_per_pixel, dy as f32 * units_per_pixel]);
+        camera.position = camera.target_position;
+        motion.velocity = [0.0; 2];
+        return Ok(());
+    }
+
+    let state = world.get_resource::<ActionState<A>>();
+    let mut direction = [
+        state.axis(actions.pan_left, actions.pan_right),
+        state.axis(actions.pan_down, actions.pan_up),
+    ];
+    if direction == [0.0; 2]
+        && controls.edge_scroll_margin > 0.0
+        && !over_gui
+        && let Some(position) = world.get_resource::<CursorPosition>().window
+    {
+        direction = edge_direction(
+            [position.x as f32, position.y as f32],
+            camera.viewport,
+            controls.edge_scroll_margin,
+        );
+    }
+    let length = (direction[0] * direction[0] + direction[1] * direction[1]).sqrt();
+
+    let speed = controls.pan_speed / camera.zoom;
+    for (velocity, direction) in motion.velocity.iter_mut().zip(direction) {
+        let target = match length > 0.0 {
+            true => direction / length * speed,
+            false => 0.0,
+        };
+        // Speeding up and slowing down are timed from and to full speed
+        let time = match target.abs() > velocity.abs() {
+            true => controls.acceleration_time,
+            false => controls.deceleration_time,
+        };
+        *velocity = match time > 0.0 {
+            true => approach(*velocity, target, speed / time * delta),
+            false => target,
+        };
+    }
+    camera.move_by([motion.velocity[0] * delta, motion.velocity[1] * delta]);
+
+    let zoom = state.axis(actions.zoom_out, actions.zoom_in);
+    if zoom != 0.0 {
+        camera.zoom_by_lines(zoom * controls.zoom_speed * delta, None);
+    }
+
+    Ok(())
+}
+
+/// Points the active camera at the entity of a [`FocusCamera`].
+pub fn focus_camera(world: &World, event: &FocusCamera, _: LemgineEventData) -> Result<()> {
+    let Some(tile) = world
+        .get_component::<GridPosition>(event.0)
+        .map(|tile| **tile)
+    else {
+        warn!(
+            "Can't focus on {}, it isn't on the grid",
+            world.label(event.0)
+        );
+        return Ok(());
+    };
+    let grid = world
+        .try_get_resource::<TileGrid>()
+        .map(|grid| **grid)
+        .unwrap_or_default();
+    let focus_zoom = world.get_resource::<CameraControls>().focus_zoom;
+
+    if let Some((_, mut camera)) = world
+        .query_mut::<Camera2D>()
+        .into_iter()
+        .find(|(_, camera)| camera.active)
+    {
+        camera.move_to(grid.tile_center(tile));
+        if focus_zoom > 0.0 {
+            camera.zoom_to(focus_zoom, None);
+        }
+        world.get_resource_mut::<CameraMotion>().velocity = [0.0; 2];
+    }
+    Ok(())
+}
+
+/// Controls for the active camera with `actions`, tuned by `controls`. Add it after
+/// [`actions_partial`](crate::ecs::actions::actions_partial) and before
+/// [`camera_partial`](crate::ecs::camera::camera_partial) so the camera moves the same frame.
+pub fn camera_controller_partial<A: Action>(
+    actions: CameraActions<A>,
+    controls: CameraControls,
+) -> PartialManager {
+    PartialManager::new()
+        .add_resource(actions)
+        .add_resource(controls)
+        .register_reflect_type::<CameraControls>("camera_controls")
+        .add_resource(CameraMotion::default())
+        .add_systems((control_camera::<A> as System,).order_up())
+        .add_typed_event_handler(focus_camera)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::ecs::{
+        actions::{InputMap, actions_partial},
+        camera::camera_partial,
+        exit::NoEventData,
+        input::KeyCode,
+        input_state::input_partial,
+        test_app::TestApp,
+    };
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    enum TestAction {
+        Up,
+        Down,
+        Left,
+        Right,
+        ZoomIn,
+        ZoomOut,
+    }
+
+    impl Action for TestAction {
+        const ALL: &'static [Self] = &[
+            TestAction::Up,
+            TestAction::Down,
+            TestAction::Left,
+            TestAction::Right,
+            TestAction::ZoomIn,
+            TestAction::ZoomOut,
+        ];
+    }
+
+    /// Panning at one unit a second with no easing anywhere.
+    fn instant_controls() -> CameraControls {
+        CameraControls {
+            pan_speed: 1.0,
+            acceleration_time: 0.0,
+            deceleration_time: 0.0,
+            edge_scroll_margin: 0.0,
+            ..Default::default()
+        }
+    }
+
+    /// A camera at the origin with `controls`, W and S pan and E zooms in. Frames are 10ms.
+    fn camera_app(controls: CameraControls) -> TestApp {
+        let bindings = InputMap::new()
+            .bind(TestAction::Up, KeyCode::KeyW)
+            .bind(TestAction::Down, KeyCode::KeyS)
+            .bind(TestAction::ZoomIn, KeyCode::KeyE);
+
+        let mut app = TestApp::new()
+            .unwrap()
+            .integrate(input_partial())
+            .unwrap()
+            .integrate(actions_partial(bindings))
+            .unwrap()
+            .integrate(camera_controller_partial(
+                CameraActions {
+                    pan_up: TestAction::Up,
+                    pan_down: TestAction::Down,
+                    pan_left: TestAction::Left,
+                    pan_right: TestAction::Right,
+                    zoom_in: TestAction::ZoomIn,
+                    zoom_out: TestAction::ZoomOut,
+                },
+                controls,
+            ))
+            .unwrap()
+            .integrate(camera_partial(Camera2D::new().with_damping(0.0)))
+            .unwrap();
+        app.set_frame_delta(Duration::from_millis(10));
+        app
+    }
+
+    fn camera(app: &TestApp) -> Camera2D {
+        (**app.world().query::<Camera2D>()[0].1).clone()
+    }
+
+    fn over_gui(app: &mut TestApp) {
+        app.world_mut().add_resource(InputCapture {
+            wants_pointer: true,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn pressing_w_for_a_second_moves_the_camera_one_unit() {
+        let mut app = camera_app(instant_controls());
+
+        app.press_key(KeyCode::KeyW);
+        app.update_for(Duration::from_secs(1)).unwrap();
+
+        let [x, y] = camera(&app).position;
+        assert!(x.abs() < 1e-4, "moved sideways to {x}");
+        assert!((y - 1.0).abs() < 1e-4, "moved to {y} instead of 1");
+    }
+
+    #[test]
+    fn letting_go_stops_the_camera() {
+        let mut app = camera_app(instant_controls());
+
+        app.press_key(KeyCode::KeyS);
+        app.update_for(Duration::from_millis(500)).unwrap();
+        app.release_key(KeyCode::KeyS);
+        app.update_for(Duration::from_millis(500)).unwrap();
+
+        let [_, y] = camera(&app).position;
+        assert!((y + 0.5).abs() < 1e-4, "moved to {y} instead of -0.5");
+    }
+
+    #[test]
+    fn speeding_up_and_slowing_down_take_their_time() {
+        let mut app = camera_app(CameraControls {
+            acceleration_time: 0.5,
+            deceleration_time: 0.5,
+            ..instant_controls()
+        });
+
+        // Half as far as at full speed while getting up to it
+        app.press_key(KeyCode::KeyW);
+        app.update_for(Duration::from_millis(500)).unwrap();
+        let [_, sped_up] = camera(&app).position;
+        assert!((sped_up - 0.25).abs() < 0.01, "moved {sped_up} speeding up");
+
+        app.update_for(Duration::from_millis(500)).unwrap();
+        let [_, full_speed] = camera(&app).position;
+        assert!((full_speed - sped_up - 0.5).abs() < 0.01);
+
+        // And again while stopping
+        app.release_key(KeyCode::KeyW);
+        app.update_for(Duration::from_millis(500)).unwrap();
+        let [_, stopped] = camera(&app).position;
+        assert!((stopped - full_speed - 0.25).abs() < 0.01, "slid {stopped}");
+
+        app.update_for(Duration::from_millis(100)).unwrap();
+        assert_eq!(camera(&app).position[1], stopped);
+    }
+
+    #[test]
+    fn holding_a_zoom_key_zooms() {
+        let controls = instant_controls();
+        let zoom_speed = controls.zoom_speed;
+        let mut app = camera_app(controls);
+
+        app.press_key(KeyCode::KeyE);
+        app.update_for(Duration::from_millis(500)).unwrap();
+
+        let expected = Camera2D::default().zoom_per_line.powf(zoom_speed * 0.5);
+        let zoom = camera(&app).zoom;
+        assert!(
+            (zoom - expected).abs() < 1e-3,
+            "zoomed to {zoom} not {expected}"
+        );
+    }
+
+    #[test]
+    fn dragging_keeps_the_world_under_the_cursor() {
+        let mut app = camera_app(instant_controls());
+        app.move_cursor(640.0, 360.0);
+        app.press_mouse(MouseButton::Middle);
+        app.update().unwrap();
+
+        app.move_cursor(740.0, 310.0);
+        app.update().unwrap();
+
+        // Dragging right and up moves the camera left and down, by the world size of the drag
+        let camera = camera(&app);
+        let units_per_pixel = camera.view_size()[1] / camera.viewport[1];
+        let [x, y] = camera.position;
+        assert!((x + 100.0 * units_per_pixel).abs() < 1e-4, "moved to {x}");
+        assert!((y + 50.0 * units_per_pixel).abs() < 1e-4, "moved to {y}");
+    }
+
+    #[test]
+    fn dragging_cant_start_over_the_gui() {
+        let mut app = camera_app(instant_controls());
+        app.move_cursor(640.0, 360.0);
+        app.press_mouse(MouseButton::Middle);
+        // Only after the press, so it gets through and it's up to the controller to ignore it
+        over_gui(&mut app);
+        app.update().unwrap();
+
+        app.move_cursor(740.0, 310.0);
+        app.update().unwrap();
+
+        assert_eq!(camera(&app).position, [0.0, 0.0]);
+    }
+
+    #[test]
+    fn the_cursor_at_the_edge_scrolls() {
+        let mut app = camera_app(CameraControls {
+            edge_scroll_margin: 8.0,
+            ..instant_controls()
+        });
+        let [width, _] = camera(&app).viewport;
+
+        // In the top right corner
+        app.move_cursor(width as f64 - 2.0, 2.0);
+        app.update_for(Duration::from_secs(1)).unwrap();
+
+        let [x, y] = camera(&app).position;
+        let diagonal = 0.5f32.sqrt();
+        assert!((x - diagonal).abs() < 1e-3 && (y - diagonal).abs() < 1e-3);
+
+        // Not in the middle
+        app.move_cursor(width as f64 / 2.0, 300.0);
+        app.update_for(Duration::from_secs(1)).unwrap();
+        assert_eq!(camera(&app).position, [x, y]);
+    }
+
+    #[test]
+    fn the_cursor_over_the_gui_doesnt_scroll() {
+        let mut app = camera_app(CameraControls {
+            edge_scroll_margin: 8.0,
+            ..instant_controls()
+        });
+        over_gui(&mut app);
+
+        app.move_cursor(2.0, 300.0);
+        app.update_for(Duration::from_secs(1)).unwrap();
+
+        assert_eq!(camera(&app).position, [0.0, 0.0]);
+    }
+
+    #[test]
+    fn focusing_goes_to_the_middle_of_the_tile() {
+        let mut app = camera_app(CameraControls {
+            focus_zoom: 2.0,
+            ..instant_controls()
+        });
+        let mill = app.world_mut().spawn_bundle((GridPosition::new(3, -2),));
+
+        app.world().raise_event(FocusCamera(mill), NoEventData);
+        app.update().unwrap();
+
+        let camera = camera(&app);
+        assert_eq!(
+            camera.position,
+            TileGrid::default().tile_center(GridPosition::new(3, -2))
+        );
+        assert_eq!(camera.zoom, 2.0);
+    }
+
+    #[test]
+    fn focusing_on_something_off_the_grid_is_only_a_warning() {
+        let mut app = camera_app(CameraControls {
+            focus_zoom: 2.0,
+            ..instant_controls()
+        });
+        let cloud = app.world_mut().spawn();
+
+        app.world().raise_event(FocusCamera(cloud), NoEventData);
+        app.update().unwrap();
+
+        let camera = camera(&app);
+        assert_eq!(camera.position, [0.0, 0.0]);
+        assert_eq!(camera.zoom, 1.0);
+    }
+}
@@ -0,0 +1,226 @@
+//! Finding out which components changed without looking at all of them.
+//!
+//! Only types someone asked about with [`World::track_changes`] are tracked, everything else costs
+//! nothing. Each [`ChangeReader`] gets its own copy of what changed since it last read, so two
+//! systems watching the same type don't steal changes from each other.
+//!
+//! A component counts as changed when it's inserted or borrowed mutably, whether or not anything
+//! was actually written to it. Keep mutable borrows to the components that are really changing.
+//!
+//! # Example
+//! ```rs
+//! struct Pathfinding {
+//!     walls: ChangeReader<Wall>,
+//! }
+//!
+//! let changes = world.read_changes(&pathfinding.walls);
+//! for entity in changes.changed { .. }
+//! for entity in changes.removed { .. }
+//! ```
+
+use std::{
+    any::{Any, TypeId},
+    collections::{BTreeSet, HashMap},
+    marker::PhantomData,
+    rc::{Rc, Weak},
+    sync::RwLock,
+};
+
+use crate::ecs::{World, entity::Entity};
+
+/// A handle to the changes of `T` one reader hasn't seen yet. Dropping it stops tracking them.
+pub struct ChangeReader<T> {
+    id: usize,
+    type_id: TypeId,
+    tracker: Weak<RwLock<ChangeTracker>>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Drop for ChangeReader<T> {
+    fn drop(&mut self) {
+        // The world might be gone already
+        if let Some(tracker) = self.tracker.upgrade() {
+            tracker.write().unwrap().unregister(self.type_id, self.id);
+        }
+    }
+}
+
+/// What happened to the components of one type since the last read.
+#[derive(Clone, Debug, Default)]
+pub struct ComponentChanges {
+    /// Entities whose component was added or changed, oldest entity first.
+    pub changed: Vec<Entity>,
+    /// Entities that lost the component or were despawned, it's gone so only the entity is left.
+    pub removed: Vec<Entity>,
+}
+
+impl ComponentChanges {
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+#[derive(Default)]
+struct Unread {
+    changed: BTreeSet<Entity>,
+    removed: BTreeSet<Entity>,
+}
+
+/// The readers of one type. Dropped readers leave their slot empty for the next one to reuse.
+#[derive(Default)]
+struct Readers {
+    slots: Vec<Option<Unread>>,
+    free: Vec<usize>,
+}
+
+impl Readers {
+    fn add(&mut self, unread: Unread) -> usize {
+        match self.free.pop() {
+            Some(id) => {
+                self.slots[id] = Some(unread);
+                id
+            }
+            None => {
+                self.slots.push(Some(unread));
+                self.slots.len() - 1
+            }
+        }
+    }
+
+    fn iter_mut(&mut self) -> impl Iterator<Item = &mut Unread> {
+        self.slots.iter_mut().flatten()
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct ChangeTracker {
+    readers: HashMap<TypeId, Readers>,
+}
+
+impl ChangeTracker {
+    pub(crate) fn mark_changed(&mut self, id: TypeId, entity: Entity) {
+        for unread in self
+            .readers
+            .get_mut(&id)
+            .into_iter()
+            .flat_map(Readers::iter_mut)
+        {
+            unread.removed.remove(&entity);
+            unread.changed.insert(entity);
+        }
+    }
+
+    pub(crate) fn mark_removed(&mut self, id: TypeId, entity: Entity) {
+        for unread in self
+            .readers
+            .get_mut(&id)
+            .into_iter()
+            .flat_map(Readers::iter_mut)
+        {
+            unread.changed.remove(&entity);
+            unread.removed.insert(entity);
+        }
+    }
+
+    fn unregister(&mut self, type_id: TypeId, id: usize) {
+        let Some(readers) = self.readers.get_mut(&type_id) else {
+            return;
+        };
+        if readers.slots.get_mut(id).and_then(Option::take).is_some() {
+            readers.free.push(id);
+        }
+    }
+}
+
+impl World {
+    /// Start tracking changes to `T`.
+    ///
+    /// Every `T` already in the world shows up as changed on the first read, so a reader made
+    /// late doesn't miss anything.
+    pub fn track_changes<T: Any>(&self) -> ChangeReader<T> {
+        let existing = self.query::<T>().into_iter().map(|(entity, _)| entity);
+        let unread = Unread {
+            changed: existing.collect(),
+            removed: BTreeSet::new(),
+        };
+
+        let id = self
+            .changes
+            .write()
+            .unwrap()
+            .readers
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .add(unread);
+
+        ChangeReader {
+            id,
+            type_id: TypeId::of::<T>(),
+            tracker: Rc::downgrade(&self.changes),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Everything that happened to `T` since `reader` last read.
+    pub fn read_changes<T: Any>(&self, reader: &ChangeReader<T>) -> ComponentChanges {
+        let mut tracker = self.changes.write().unwrap();
+        let Some(unread) = tracker
+            .readers
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|readers| readers.slots.get_mut(reader.id))
+            .and_then(Option::as_mut)
+        else {
+            return ComponentChanges::default();
+        };
+
+        let unread = std::mem::take(unread);
+        ComponentChanges {
+            changed: unread.changed.into_iter().collect(),
+            removed: unread.removed.into_iter().collect(),
+        }
+    }
+
+    pub(crate) fn mark_changed(&self, id: TypeId, entity: Entity) {
+        self.changes.write().unwrap().mark_changed(id, entity);
+    }
+
+    pub(crate) fn mark_removed(&self, id: TypeId, entity: Entity) {
+        self.changes.write().unwrap().mark_removed(id, entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Wall;
+
+    #[test]
+    fn readers_see_changes_once() {
+        let mut world = World::new();
+        let reader = world.track_changes::<Wall>();
+        let wall = world.spawn_bundle((Wall,));
+
+        assert_eq!(world.read_changes(&reader).changed, vec![wall]);
+        assert!(world.read_changes(&reader).is_empty());
+
+        world.remove::<Wall>(wall);
+        assert_eq!(world.read_changes(&reader).removed, vec![wall]);
+    }
+
+    #[test]
+    fn dropped_readers_free_their_slot() {
+        let mut world = World::new();
+        let kept = world.track_changes::<Wall>();
+        drop(world.track_changes::<Wall>());
+        let reused = world.track_changes::<Wall>();
+
+        let tracker = world.changes.read().unwrap();
+        assert_eq!(tracker.readers[&TypeId::of::<Wall>()].slots.len(), 2);
+        drop(tracker);
+
+        let wall = world.spawn_bundle((Wall,));
+        assert_eq!(world.read_changes(&kept).changed, vec![wall]);
+        assert_eq!(world.read_changes(&reused).changed, vec![wall]);
+    }
+}
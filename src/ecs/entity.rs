@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// A handle to a thing in the [`World`](crate::ecs::World).
+///
+/// An entity is just an id, everything interesting about it lives in its components.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Entity(pub(crate) u64);
+
+impl Entity {
+    /// The raw id. Only unique inside of the world that made it.
+    pub fn id(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Maps entities from one world (or save file) onto the entities they became in another.
+///
+/// Handed to [`MapEntities`] when loading a snapshot.
+#[derive(Clone, Debug, Default)]
+pub struct EntityMap {
+    map: HashMap<Entity, Entity>,
+}
+
+impl EntityMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, from: Entity, to: Entity) {
+        self.map.insert(from, to);
+    }
+
+    /// The entity `from` became, if it was mapped.
+    pub fn get(&self, from: Entity) -> Option<Entity> {
+        self.map.get(&from).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+/// Implemented by components and resources that hold onto [`Entity`]s.
+///
+/// Entity ids aren't stable between runs, so when a snapshot is loaded every entity gets a new id
+/// and anything pointing at one has to be updated.
+///
+/// # Example
+/// ```rs
+/// struct Conveyor {
+///     next: Option<Entity>,
+/// }
+///
+/// impl MapEntities for Conveyor {
+///     fn map_entities(&mut self, map: &EntityMap) {
+///         self.next = self.next.and_then(|e| map.get(e));
+///     }
+/// }
+/// ```
+pub trait MapEntities {
+    fn map_entities(&mut self, map: &EntityMap);
+}
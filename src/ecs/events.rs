@@ -1,9 +1,20 @@
-use std::any::Any;
+use std::any::{Any, TypeId};
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+use std::time::Instant;
+
+use anyhow::Result;
+
+use crate::ecs::{EventSystem, World, ordering::SystemOrder, timings::Stage};
 
 pub type LemgineEvent = Box<dyn EventWrapper>;
 pub type LemgineEventData = Box<dyn EventDataWrapper>;
 
+/// An event handler that gets every event of type `E` along with the event itself.
+pub type TypedEventSystem<E> = fn(&World, &E, LemgineEventData) -> Result<()>;
+
 /// Type to denote what's an ECS Event
 pub trait EcsEvent {}
 
@@ -121,3 +132,256 @@ impl Hash for dyn EventWrapper {
         EventWrapper::hash_dyn(self, state);
     }
 }
+
+/// Decides whether an [`EventHandler`] runs for an event.
+type EventFilter = Rc<dyn Fn(&dyn EventWrapper) -> bool>;
+/// An [`EventHandler`]'s system with the event downcast for it.
+type ErasedEventSystem = Rc<dyn Fn(&World, &dyn EventWrapper, LemgineEventData) -> Result<()>>;
+
+/// A handler for every event of one type, as opposed to the handlers for a single event value
+/// in [`EventHandlers::by_value`].
+///
+/// # Example
+/// ```rs
+/// fn on_machine_broke(world: &World, event: &MachineBroke, data: LemgineEventData) -> Result<()> {
+///     ...
+/// }
+///
+/// PartialManager::new().add_typed_event_handler_with(
+///     EventHandler::new(on_machine_broke)
+///         .with_priority(10)
+///         .with_filter(|event: &MachineBroke| event.id != 0),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct EventHandler {
+    event_type: TypeId,
+    priority: i32,
+    filter: Option<EventFilter>,
+    run: ErasedEventSystem,
+    /// Address of the system, for [`SystemTimings`](crate::ecs::timings::SystemTimings).
+    address: usize,
+}
+
+impl EventHandler {
+    pub fn new<E: EventWrapper + 'static>(system: TypedEventSystem<E>) -> Self {
+        Self {
+            event_type: TypeId::of::<E>(),
+            priority: 0,
+            filter: None,
+            run: Rc::new(move |world, event, data| {
+                // Only ever called with events of the right type, see `EventHandlers::raise`
+                system(world, event.as_any().downcast_ref::<E>().unwrap(), data)
+            }),
+            address: system as usize,
+        }
+    }
+
+    /// Handlers with a higher priority run first, the default is `0`.
+    /// Handlers with the same priority run in the order they were added.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Only run the handler for events `filter` returns `true` for.
+    ///
+    /// # Panics
+    /// Panics if `E` isn't the type of event this handles.
+    pub fn with_filter<E: EventWrapper + 'static>(
+        mut self,
+        filter: impl Fn(&E) -> bool + 'static,
+    ) -> Self {
+        assert_eq!(
+            self.event_type,
+            TypeId::of::<E>(),
+            "Filter is for a different event type than its handler"
+        );
+        self.filter = Some(Rc::new(move |event| {
+            event.as_any().downcast_ref::<E>().is_some_and(&filter)
+        }));
+        self
+    }
+
+    pub fn event_type(&self) -> TypeId {
+        self.event_type
+    }
+
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn wants(&self, event: &dyn EventWrapper) -> bool {
+        self.filter.as_ref().is_none_or(|filter| filter(event))
+    }
+}
+
+/// Every event handler of a [`Manager`](crate::ecs::Manager) or world.
+///
+/// When an event is raised, handlers run from highest to lowest priority. Handlers for an exact
+/// event value don't have a priority of their own and run as if they had a priority of `0`,
+/// before any type handlers with a priority of `0`.
+#[derive(Clone, Default)]
+pub struct EventHandlers {
+    /// Handlers for one exact event value, `MachineBroke { id: 1 }` won't run these for
+    /// `MachineBroke { id: 2 }`.
+    pub by_value: HashMap<LemgineEvent, SystemOrder<EventSystem>>,
+    /// Handlers for every event of a type, kept sorted by priority.
+    pub by_type: HashMap<TypeId, Vec<EventHandler>>,
+}
+
+impl EventHandlers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add handlers for a single event value. Does nothing if that value already has handlers.
+    pub fn add_value_handler(&mut self, event: LemgineEvent, systems: SystemOrder<EventSystem>) {
+        self.by_value.entry(event).or_insert(systems);
+    }
+
+    pub fn add_type_handler(&mut self, handler: EventHandler) {
+        let handlers = self.by_type.entry(handler.event_type).or_default();
+        handlers.push(handler);
+        // Stable, so equal priorities keep the order they were added in
+        handlers.sort_by_key(|h| Reverse(h.priority));
+    }
+
+    /// Add all of the handlers in `other` after the ones in here.
+    pub fn extend(&mut self, other: EventHandlers) {
+        for (event, systems) in other.by_value {
+            match self.by_value.get_mut(&event) {
+                Some(existing) => existing.extend_mut_ref(systems),
+                None => {
+                    self.by_value.insert(event, systems);
+                }
+            }
+        }
+
+        for handler in other.by_type.into_values().flatten() {
+            self.add_type_handler(handler);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_value.is_empty() && self.by_type.is_empty()
+    }
+
+    /// Run every handler interested in `event`.
+    pub fn raise(&self, world: &World, event: &LemgineEvent, data: LemgineEventData) -> Result<()> {
+        let type_handlers = self
+            .by_type
+            .get(&event.as_any().type_id())
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let (before, after) =
+            type_handlers.split_at(type_handlers.partition_point(|h| h.priority > 0));
+
+        let run_typed = |handlers: &[EventHandler]| -> Result<()> {
+            for handler in handlers.iter().filter(|h| h.wants(&**event)) {
+                let start = Instant::now();
+                (handler.run)(world, &**event, data.clone())?;
+                world.record_timing(Stage::Event, handler.address, start.elapsed());
+            }
+            Ok(())
+        };
+
+        run_typed(before)?;
+
+        if let Some(systems) = self.by_value.get(event) {
+            for system in systems.order.iter() {
+                let start = Instant::now();
+                system(world, data.clone())?;
+                world.record_timing(Stage::Event, *system as usize, start.elapsed());
+            }
+        }
+
+        run_typed(after)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{exit::NoEventData, order_up::OrderUp};
+
+    #[derive(Clone, PartialEq, Eq, Hash)]
+    struct MachineBroke {
+        id: u32,
+    }
+
+    impl EcsEvent for MachineBroke {}
+
+    /// The handlers that ran, in order.
+    #[derive(Default)]
+    struct Ran(Vec<&'static str>);
+
+    fn ran(world: &World, name: &'static str) -> Result<()> {
+        world.get_resource_mut::<Ran>().0.push(name);
+        Ok(())
+    }
+
+    fn high(world: &World, _: &MachineBroke, _: LemgineEventData) -> Result<()> {
+        ran(world, "high")
+    }
+
+    fn only_two(world: &World, _: &MachineBroke, _: LemgineEventData) -> Result<()> {
+        ran(world, "only_two")
+    }
+
+    fn first_zero(world: &World, _: &MachineBroke, _: LemgineEventData) -> Result<()> {
+        ran(world, "first_zero")
+    }
+
+    fn second_zero(world: &World, _: &MachineBroke, _: LemgineEventData) -> Result<()> {
+        ran(world, "second_zero")
+    }
+
+    fn low(world: &World, _: &MachineBroke, _: LemgineEventData) -> Result<()> {
+        ran(world, "low")
+    }
+
+    fn one_broke(world: &World, _: LemgineEventData) -> Result<()> {
+        ran(world, "one_broke")
+    }
+
+    fn raise(handlers: &EventHandlers, id: u32) -> Vec<&'static str> {
+        let mut world = World::new();
+        world.add_resource(Ran::default());
+        handlers
+            .raise(
+                &world,
+                &(Box::new(MachineBroke { id }) as LemgineEvent),
+                Box::new(NoEventData),
+            )
+            .unwrap();
+        world.get_resource::<Ran>().0.clone()
+    }
+
+    #[test]
+    fn handlers_run_by_priority_then_insertion_order() {
+        let mut handlers = EventHandlers::new();
+        handlers.add_type_handler(EventHandler::new(low).with_priority(-5));
+        handlers.add_type_handler(EventHandler::new(first_zero));
+        handlers.add_type_handler(
+            EventHandler::new(only_two)
+                .with_priority(5)
+                .with_filter(|event: &MachineBroke| event.id == 2),
+        );
+        handlers.add_type_handler(EventHandler::new(high).with_priority(10));
+        handlers.add_type_handler(EventHandler::new(second_zero));
+        handlers.add_value_handler(
+            Box::new(MachineBroke { id: 1 }),
+            (one_broke as EventSystem,).order_up(),
+        );
+
+        assert_eq!(
+            raise(&handlers, 1),
+            ["high", "one_broke", "first_zero", "second_zero", "low"]
+        );
+        assert_eq!(
+            raise(&handlers, 2),
+            ["high", "only_two", "first_zero", "second_zero", "low"]
+        );
+    }
+}
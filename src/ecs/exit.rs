@@ -0,0 +1,150 @@
+//! Quitting the game without pulling the rug out from under the systems.
+//!
+//! Any system can ask to quit with [`World::exit`] (or by raising [`AppExit`] itself). The
+//! frame finishes as normal, then [`Manager`](crate::ecs::Manager) runs the shutdown systems of
+//! every plugin in the reverse order they were added, so whatever was set up first (the
+//! renderer) is torn down last.
+
+use std::{num::NonZeroU8, process::ExitCode};
+
+use crate::ecs::{
+    World,
+    events::{EcsEvent, EcsEventData, EventWrapper},
+};
+
+/// Raise this to quit, the code is what the process exits with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AppExit {
+    Success,
+    Error(NonZeroU8),
+}
+
+impl EcsEvent for AppExit {}
+
+impl AppExit {
+    /// An error exit with `code`, `0` is bumped up to `1` so it still counts as an error.
+    pub fn error(code: u8) -> Self {
+        Self::Error(NonZeroU8::new(code).unwrap_or(NonZeroU8::MIN))
+    }
+
+    pub fn code(&self) -> u8 {
+        match self {
+            AppExit::Success => 0,
+            AppExit::Error(code) => code.get(),
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        *self == AppExit::Success
+    }
+}
+
+impl From<AppExit> for ExitCode {
+    fn from(exit: AppExit) -> Self {
+        ExitCode::from(exit.code())
+    }
+}
+
+/// Event data for events that don't need any, like [`AppExit`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoEventData;
+
+impl EcsEventData for NoEventData {}
+
+/// The first exit asked for, later ones are ignored.
+#[derive(Default)]
+pub struct ExitRequest(Option<AppExit>);
+
+impl World {
+    /// Ask to quit once this frame is over.
+    ///
+    /// # Example
+    /// ```rs
+    /// if save_failed {
+    ///     world.exit(AppExit::error(2));
+    /// }
+    /// ```
+    pub fn exit(&self, exit: AppExit) {
+        self.raise_event(exit, NoEventData);
+    }
+
+    /// The exit asked for so far, if any.
+    pub fn exit_requested(&self) -> Option<AppExit> {
+        self.try_get_resource::<ExitRequest>()
+            .and_then(|request| request.0)
+    }
+
+    /// Called with every raised event so an [`AppExit`] gets noticed no matter how it was raised.
+    pub(crate) fn note_exit(&self, event: &dyn EventWrapper) {
+        let Some(exit) = event.as_any().downcast_ref::<AppExit>() else {
+            return;
+        };
+
+        if let Some(mut request) = self.try_get_resource_mut::<ExitRequest>() {
+            request.0.get_or_insert(*exit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{Result, anyhow};
+
+    use super::*;
+    use crate::ecs::{
+        ShutdownSystem, order_up::OrderUp, partial_manager::PartialManager, test_app::TestApp,
+    };
+
+    /// The shutdown systems that ran, in order.
+    #[derive(Default)]
+    struct ShutdownLog(Vec<&'static str>);
+
+    fn renderer_shutdown(world: &mut World) -> Result<()> {
+        world.get_resource_mut::<ShutdownLog>().0.push("renderer");
+        Ok(())
+    }
+
+    fn save_shutdown(world: &mut World) -> Result<()> {
+        world.get_resource_mut::<ShutdownLog>().0.push("save");
+        Ok(())
+    }
+
+    fn broken_shutdown(world: &mut World) -> Result<()> {
+        world.get_resource_mut::<ShutdownLog>().0.push("broken");
+        Err(anyhow!("Disk is full"))
+    }
+
+    fn app_with(shutdown_systems: &[ShutdownSystem]) -> TestApp {
+        let mut app = TestApp::new().unwrap().add_resource(ShutdownLog::default());
+        for system in shutdown_systems {
+            app = app
+                .integrate(PartialManager::new().add_shutdown_systems((*system,).order_up()))
+                .unwrap();
+        }
+        app
+    }
+
+    fn log(app: &TestApp) -> Vec<&'static str> {
+        app.resource::<ShutdownLog>().0.clone()
+    }
+
+    #[test]
+    fn plugins_shut_down_in_reverse_order() {
+        let mut app = app_with(&[renderer_shutdown, save_shutdown]);
+
+        let exit = app.manager.shutdown(AppExit::error(3));
+
+        assert_eq!(exit, AppExit::error(3));
+        assert_eq!(log(&app), ["save", "renderer"]);
+    }
+
+    #[test]
+    fn a_failed_shutdown_still_runs_the_rest() {
+        let mut app = app_with(&[renderer_shutdown, broken_shutdown]);
+
+        let exit = app.manager.shutdown(AppExit::Success);
+
+        assert_eq!(exit, AppExit::error(1));
+        assert_eq!(log(&app), ["broken", "renderer"]);
+    }
+}
@@ -0,0 +1,111 @@
+//! Narrowing down queries by what other components an entity has.
+//!
+//! Mostly for tags, zero-sized components that only mark an entity as something:
+//! ```rs
+//! #[derive(Default)]
+//! struct Selected;
+//! struct Powered;
+//!
+//! world.insert(mill, Selected);
+//!
+//! for (entity, position) in world.query_filtered::<GridPosition, (With<Selected>, Without<Powered>)>() {
+//!     // every selected entity that isn't powered
+//! }
+//! ```
+
+use std::{any::Any, marker::PhantomData};
+
+use seq_macro::seq;
+
+use crate::ecs::{World, entity::Entity};
+
+/// Something an entity has to match to show up in a filtered query.
+pub trait QueryFilter {
+    fn matches(world: &World, entity: Entity) -> bool;
+}
+
+/// Only entities that have a `T`.
+pub struct With<T>(PhantomData<T>);
+
+/// Only entities that don't have a `T`.
+pub struct Without<T>(PhantomData<T>);
+
+impl<T: Any> QueryFilter for With<T> {
+    fn matches(world: &World, entity: Entity) -> bool {
+        world.has_component::<T>(entity)
+    }
+}
+
+impl<T: Any> QueryFilter for Without<T> {
+    fn matches(world: &World, entity: Entity) -> bool {
+        !world.has_component::<T>(entity)
+    }
+}
+
+/// No filter at all.
+impl QueryFilter for () {
+    fn matches(_: &World, _: Entity) -> bool {
+        true
+    }
+}
+
+/// Generates [`QueryFilter`] impls for tuples, an entity has to match every filter in it.
+///
+/// `gen_filter_impl!()` generates tuples with 1 to 8 filters, `gen_filter_impl!(3)` only the one
+/// with 4.
+macro_rules! gen_filter_impl {
+    () => {
+        seq!(N in 0..8 {
+            gen_filter_impl!(N);
+        });
+    };
+    ($n:literal) => {
+        seq!(T in 0..=$n {
+            impl<#(F~T: QueryFilter,)*> QueryFilter for (#(F~T,)*) {
+                fn matches(world: &World, entity: Entity) -> bool {
+                    #(F~T::matches(world, entity) &&)* true
+                }
+            }
+        });
+    };
+}
+
+gen_filter_impl!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::name::Name;
+
+    struct Powered;
+
+    struct Broken;
+
+    fn names<F: QueryFilter>(world: &World) -> Vec<String> {
+        world
+            .query_filtered::<Name, F>()
+            .into_iter()
+            .map(|(_, name)| name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn filters_by_other_components() {
+        let mut world = World::new();
+        let mill = world.spawn_bundle((Name::new("Mill"), Powered));
+        world.spawn_bundle((Name::new("Mill"), Powered, Broken));
+        world.spawn_bundle((Name::new("Silo"),));
+        world.spawn_bundle((Powered,));
+
+        assert_eq!(world.find_by_name("Mill"), Some(mill));
+        assert_eq!(world.find_all_by_name("Mill").len(), 2);
+        assert_eq!(world.find_by_name("Conveyor"), None);
+
+        assert_eq!(names::<With<Powered>>(&world), ["Mill", "Mill"]);
+        assert_eq!(names::<Without<Powered>>(&world), ["Silo"]);
+
+        let working = world.query_filtered::<Name, (With<Powered>, Without<Broken>)>();
+        assert_eq!(working.len(), 1);
+        assert_eq!(working[0].0, mill);
+    }
+}
@@ -0,0 +1,106 @@
+//! Parent and child entities, e.g. a machine and the ports sticking out of it.
+//!
+//! Both sides are stored: children have a [`Parent`] and parents have [`Children`]. Use the
+//! methods on [`World`] to change them so the two stay in sync.
+
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::{
+    World,
+    entity::{Entity, EntityMap, MapEntities},
+    reflect::impl_reflect,
+};
+
+/// The entity this one belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Parent(pub Entity);
+
+impl_reflect!(Parent(0));
+
+impl MapEntities for Parent {
+    fn map_entities(&mut self, map: &EntityMap) {
+        if let Some(entity) = map.get(self.0) {
+            self.0 = entity;
+        }
+    }
+}
+
+/// The entities that belong to this one, in the order they were added.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Children(pub Vec<Entity>);
+
+impl_reflect!(Children(0));
+
+impl MapEntities for Children {
+    fn map_entities(&mut self, map: &EntityMap) {
+        for child in self.0.iter_mut() {
+            if let Some(entity) = map.get(*child) {
+                *child = entity;
+            }
+        }
+    }
+}
+
+impl World {
+    /// Make `child` belong to `parent`, taking it away from its old parent if it had one.
+    ///
+    /// # Panics
+    /// Panics if either entity doesn't exist.
+    pub fn set_parent(&mut self, child: Entity, parent: Entity) {
+        self.remove_parent(child);
+        debug!(
+            "{} now belongs to {}",
+            self.label(child),
+            self.label(parent)
+        );
+
+        self.insert(child, Parent(parent));
+        if let Some(mut children) = self.get_component_mut::<Children>(parent) {
+            children.0.push(child);
+            return;
+        }
+        self.insert(parent, Children(vec![child]));
+    }
+
+    /// Take `child` away from its parent, it stays alive on its own.
+    pub fn remove_parent(&mut self, child: Entity) {
+        let Some(parent) = self.get_component::<Parent>(child).map(|p| p.0) else {
+            return;
+        };
+        self.remove::<Parent>(child);
+
+        if let Some(mut children) = self.get_component_mut::<Children>(parent) {
+            children.0.retain(|c| *c != child);
+        }
+    }
+
+    pub fn parent(&self, entity: Entity) -> Option<Entity> {
+        self.get_component::<Parent>(entity).map(|p| p.0)
+    }
+
+    pub fn children(&self, entity: Entity) -> Vec<Entity> {
+        self.get_component::<Children>(entity)
+            .map(|c| c.0.clone())
+            .unwrap_or_default()
+    }
+
+    /// Despawn an entity along with all of its children, and their children and so on.
+    /// Returns `false` if the entity didn't exist.
+    pub fn despawn_recursive(&mut self, entity: Entity) -> bool {
+        if !self.contains_entity(entity) {
+            return false;
+        }
+
+        self.remove_parent(entity);
+        debug!("Despawning {} and everything under it", self.label(entity));
+
+        let mut stack = vec![entity];
+        while let Some(entity) = stack.pop() {
+            stack.extend(self.children(entity));
+            self.despawn(entity);
+        }
+
+        true
+    }
+}
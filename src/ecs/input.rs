@@ -0,0 +1,210 @@
+//! Player input, separate from the rest of winit's events.
+//!
+//! [`Manager`](crate::ecs::Manager) turns keyboard and mouse events from winit into
+//! [`InputEvent`]s and hands them to the input systems. Unlike winit's events these can be saved
+//! and made up, which is what recording, replays and tests need. Gameplay should read input from
+//! here instead of from a [`WinitEventSystem`](crate::ecs::WinitEventSystem).
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "render")]
+use winit::{event::WindowEvent, keyboard::PhysicalKey};
+
+#[cfg(not(feature = "render"))]
+pub use crate::ecs::windowless::{
+    ElementState, KeyCode, MouseButton, MouseScrollDelta, PhysicalPosition,
+};
+#[cfg(feature = "render")]
+pub use winit::{
+    dpi::PhysicalPosition,
+    event::{ElementState, MouseButton, MouseScrollDelta},
+    keyboard::KeyCode,
+};
+
+/// A single bit of player input.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+    Key {
+        code: KeyCode,
+        state: ElementState,
+        /// Held down long enough for the OS to repeat it.
+        repeat: bool,
+    },
+    MouseButton {
+        button: MouseButton,
+        state: ElementState,
+    },
+    /// Where the cursor is, in pixels from the top left of the window.
+    CursorMoved(PhysicalPosition<f64>),
+    CursorEntered,
+    CursorLeft,
+    MouseWheel(MouseScrollDelta),
+    /// The window gained (`true`) or lost (`false`) focus.
+    Focused(bool),
+}
+
+impl InputEvent {
+    /// The input in a winit event, `None` if it isn't input.
+    #[cfg(feature = "render")]
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        Some(match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                // Keys winit doesn't know the code of can't be saved or rebound anyway
+                let PhysicalKey::Code(code) = event.physical_key else {
+                    return None;
+                };
+                InputEvent::Key {
+                    code,
+                    state: event.state,
+                    repeat: event.repeat,
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => InputEvent::MouseButton {
+                button: *button,
+                state: *state,
+            },
+            WindowEvent::CursorMoved { position, .. } => InputEvent::CursorMoved(*position),
+            WindowEvent::CursorEntered { .. } => InputEvent::CursorEntered,
+            WindowEvent::CursorLeft { .. } => InputEvent::CursorLeft,
+            WindowEvent::MouseWheel { delta, .. } => InputEvent::MouseWheel(*delta),
+            WindowEvent::Focused(focused) => InputEvent::Focused(*focused),
+            _ => return None,
+        })
+    }
+
+    /// A key press, for faking input.
+    pub fn key_pressed(code: KeyCode) -> Self {
+        InputEvent::Key {
+            code,
+            state: ElementState::Pressed,
+            repeat: false,
+        }
+    }
+
+    pub fn key_released(code: KeyCode) -> Self {
+        InputEvent::Key {
+            code,
+            state: ElementState::Released,
+            repeat: false,
+        }
+    }
+}
+
+/// What the gui is keeping for itself, from egui's `EventResponse` for the last window event.
+///
+/// The [`Manager`](crate::ecs::Manager) drops input the gui took before the input systems see it,
+/// so typing in a text box doesn't also move the camera and recordings only have what reached the
+/// game. Filled in by the engine, nothing is ever captured without one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InputCapture {
+    /// The gui used the event, like a click on a button.
+    pub consumed: bool,
+    /// Something like a text box has focus, keys go to it.
+    pub wants_keyboard: bool,
+    /// The pointer is over a gui window or dragging something in one.
+    pub wants_pointer: bool,
+}
+
+impl InputCapture {
+    /// Whether `input` belongs to the gui. Releases always get through, otherwise a key let go of
+    /// over the gui would stay held in the game.
+    pub fn captures(&self, input: &InputEvent) -> bool {
+        match input {
+            InputEvent::Key {
+                state: ElementState::Pressed,
+                ..
+            } => self.consumed || self.wants_keyboard,
+            InputEvent::MouseButton {
+                state: ElementState::Pressed,
+                ..
+            }
+            | InputEvent::MouseWheel(_) => self.consumed || self.wants_pointer,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{input_state::ButtonInput, input_state::input_partial, test_app::TestApp};
+
+    const KEY_PRESS: InputEvent = InputEvent::Key {
+        code: KeyCode::KeyW,
+        state: ElementState::Pressed,
+        repeat: false,
+    };
+    const KEY_RELEASE: InputEvent = InputEvent::Key {
+        code: KeyCode::KeyW,
+        state: ElementState::Released,
+        repeat: false,
+    };
+    const CLICK: InputEvent = InputEvent::MouseButton {
+        button: MouseButton::Left,
+        state: ElementState::Pressed,
+    };
+    const UNCLICK: InputEvent = InputEvent::MouseButton {
+        button: MouseButton::Left,
+        state: ElementState::Released,
+    };
+    const WHEEL: InputEvent = InputEvent::MouseWheel(MouseScrollDelta::LineDelta(0.0, 1.0));
+    const MOVE: InputEvent = InputEvent::CursorMoved(PhysicalPosition::new(1.0, 2.0));
+
+    /// Which of the inputs above `capture` takes.
+    fn captured(capture: InputCapture) -> Vec<InputEvent> {
+        [KEY_PRESS, KEY_RELEASE, CLICK, UNCLICK, WHEEL, MOVE]
+            .into_iter()
+            .filter(|input| capture.captures(input))
+            .collect()
+    }
+
+    #[test]
+    fn nothing_is_captured_by_default() {
+        assert_eq!(captured(InputCapture::default()), []);
+    }
+
+    #[test]
+    fn consumed_takes_presses_and_the_wheel() {
+        let capture = InputCapture {
+            consumed: true,
+            ..Default::default()
+        };
+        assert_eq!(captured(capture), [KEY_PRESS, CLICK, WHEEL]);
+    }
+
+    #[test]
+    fn wants_keyboard_takes_key_presses() {
+        let capture = InputCapture {
+            wants_keyboard: true,
+            ..Default::default()
+        };
+        assert_eq!(captured(capture), [KEY_PRESS]);
+    }
+
+    #[test]
+    fn wants_pointer_takes_clicks_and_the_wheel() {
+        let capture = InputCapture {
+            wants_pointer: true,
+            ..Default::default()
+        };
+        assert_eq!(captured(capture), [CLICK, WHEEL]);
+    }
+
+    #[test]
+    fn captured_presses_dont_reach_the_game_but_releases_do() {
+        let mut app = TestApp::new().unwrap().integrate(input_partial()).unwrap();
+        app.press_key(KeyCode::KeyW);
+        app.update().unwrap();
+
+        app.world_mut().add_resource(InputCapture {
+            wants_keyboard: true,
+            ..Default::default()
+        });
+        app.press_key(KeyCode::KeyA);
+        app.release_key(KeyCode::KeyW);
+        app.update().unwrap();
+
+        app.assert_resource::<ButtonInput<KeyCode>>(|keys| {
+            !keys.pressed(KeyCode::KeyA) && keys.just_released(KeyCode::KeyW)
+        });
+    }
+}
@@ -0,0 +1,338 @@
+//! What the keyboard and mouse are doing this frame, without writing an input system.
+//!
+//! Input is queued up as it comes in and applied all at once at the start of the update, so every
+//! system in a frame sees the same thing. A key pressed and let go within one frame is both
+//! just pressed and just released, but not pressed. Losing focus lets go of everything, the
+//! releases would never come otherwise.
+//!
+//! # Example
+//! ```rs
+//! let keys = world.get_resource::<ButtonInput<KeyCode>>();
+//! if keys.just_pressed(KeyCode::Space) { .. }
+//!
+//! let mouse = world.get_resource::<ButtonInput<MouseButton>>();
+//! let cursor = world.get_resource::<CursorPosition>();
+//! if mouse.just_pressed(MouseButton::Left) && let Some(world_position) = cursor.world { .. }
+//!
+//! let zoom = world.get_resource::<MouseScroll>().lines[1];
+//! ```
+
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+
+use crate::ecs::{
+    InputSystem, System, World,
+    input::{InputEvent, KeyCode, MouseButton, MouseScrollDelta, PhysicalPosition},
+    order_up::OrderUp,
+    partial_manager::PartialManager,
+};
+
+/// How many pixels of touchpad scrolling count as a line.
+pub const PIXELS_PER_LINE: f64 = 20.0;
+
+/// Which of some kind of button are down, and which changed this frame.
+#[derive(Clone, Debug)]
+pub struct ButtonInput<T: Copy + Ord> {
+    pressed: BTreeSet<T>,
+    just_pressed: BTreeSet<T>,
+    just_released: BTreeSet<T>,
+}
+
+impl<T: Copy + Ord> Default for ButtonInput<T> {
+    fn default() -> Self {
+        Self {
+            pressed: BTreeSet::new(),
+            just_pressed: BTreeSet::new(),
+            just_released: BTreeSet::new(),
+        }
+    }
+}
+
+impl<T: Copy + Ord> ButtonInput<T> {
+    pub fn press(&mut self, button: T) {
+        if self.pressed.insert(button) {
+            self.just_pressed.insert(button);
+        }
+    }
+
+    pub fn release(&mut self, button: T) {
+        if self.pressed.remove(&button) {
+            self.just_released.insert(button);
+        }
+    }
+
+    /// Let go of everything that's down, each one shows up as just released.
+    pub fn release_all(&mut self) {
+        self.just_released.append(&mut self.pressed);
+    }
+
+    pub fn pressed(&self, button: T) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    pub fn just_pressed(&self, button: T) -> bool {
+        self.just_pressed.contains(&button)
+    }
+
+    pub fn just_released(&self, button: T) -> bool {
+        self.just_released.contains(&button)
+    }
+
+    pub fn any_pressed(&self, buttons: impl IntoIterator<Item = T>) -> bool {
+        buttons.into_iter().any(|b| self.pressed(b))
+    }
+
+    pub fn any_just_pressed(&self, buttons: impl IntoIterator<Item = T>) -> bool {
+        buttons.into_iter().any(|b| self.just_pressed(b))
+    }
+
+    /// Everything held down, in the same order every run.
+    pub fn get_pressed(&self) -> impl Iterator<Item = T> + '_ {
+        self.pressed.iter().copied()
+    }
+
+    pub fn get_just_pressed(&self) -> impl Iterator<Item = T> + '_ {
+        self.just_pressed.iter().copied()
+    }
+
+    pub fn get_just_released(&self) -> impl Iterator<Item = T> + '_ {
+        self.just_released.iter().copied()
+    }
+
+    /// Forget what changed, for the start of a new frame.
+    pub fn clear(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+    }
+
+    /// Forget everything without anything counting as released.
+    pub fn reset_all(&mut self) {
+        self.pressed.clear();
+        self.clear();
+    }
+}
+
+/// Where the cursor is, `None` while it's outside of the window.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CursorPosition {
+    /// In pixels from the top left of the window.
+    pub window: Option<PhysicalPosition<f64>>,
+    /// Where that is in the world, filled in by the engine so always `None` without one.
+    pub world: Option<[f32; 2]>,
+}
+
+/// How far the cursor moved this frame, in pixels. Only counts movement inside of the window.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MouseMotion {
+    pub delta: [f64; 2],
+}
+
+/// How far the wheel turned this frame, in lines. Positive `y` is away from the player.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MouseScroll {
+    pub lines: [f32; 2],
+}
+
+/// Input that came in since the last update.
+#[derive(Default)]
+struct PendingInput(Vec<InputEvent>);
+
+pub fn queue_input(world: &World, input: &InputEvent) -> Result<()> {
+    world.get_resource_mut::<PendingInput>().0.push(*input);
+    Ok(())
+}
+
+/// Applies the input queued up since the last frame.
+pub fn update_input(world: &World) -> Result<()> {
+    let pending = std::mem::take(&mut world.get_resource_mut::<PendingInput>().0);
+
+    let mut keys = world.get_resource_mut::<ButtonInput<KeyCode>>();
+    let mut buttons = world.get_resource_mut::<ButtonInput<MouseButton>>();
+    let mut cursor = world.get_resource_mut::<CursorPosition>();
+    let mut motion = world.get_resource_mut::<MouseMotion>();
+    let mut scroll = world.get_resource_mut::<MouseScroll>();
+
+    keys.clear();
+    buttons.clear();
+    motion.delta = [0.0; 2];
+    scroll.lines = [0.0; 2];
+
+    for input in pending {
+        match input {
+            InputEvent::Key { repeat: true, .. } => {}
+            InputEvent::Key { code, state, .. } => match state.is_pressed() {
+                true => keys.press(code),
+                false => keys.release(code),
+            },
+            InputEvent::MouseButton { button, state } => match state.is_pressed() {
+                true => buttons.press(button),
+                false => buttons.release(button),
+            },
+            InputEvent::CursorMoved(position) => {
+                if let Some(last) = cursor.window {
+                    motion.delta[0] += position.x - last.x;
+                    motion.delta[1] += position.y - last.y;
+                }
+                cursor.window = Some(position);
+            }
+            InputEvent::CursorLeft => {
+                cursor.window = None;
+                cursor.world = None;
+            }
+            InputEvent::MouseWheel(MouseScrollDelta::LineDelta(x, y)) => {
+                scroll.lines[0] += x;
+                scroll.lines[1] += y;
+            }
+            InputEvent::MouseWheel(MouseScrollDelta::PixelDelta(delta)) => {
+                scroll.lines[0] += (delta.x / PIXELS_PER_LINE) as f32;
+                scroll.lines[1] += (delta.y / PIXELS_PER_LINE) as f32;
+            }
+            InputEvent::Focused(false) => {
+                keys.release_all();
+                buttons.release_all();
+            }
+            InputEvent::CursorEntered | InputEvent::Focused(true) => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// [`ButtonInput`]s for keys and mouse buttons, [`CursorPosition`], [`MouseMotion`] and
+/// [`MouseScroll`]. Integrate it first so every other plugin sees the frame's input.
+pub fn input_partial() -> PartialManager {
+    PartialManager::new()
+        .add_resource(PendingInput::default())
+        .add_resource(ButtonInput::<KeyCode>::default())
+        .add_resource(ButtonInput::<MouseButton>::default())
+        .add_resource(CursorPosition::default())
+        .add_resource(MouseMotion::default())
+        .add_resource(MouseScroll::default())
+        .add_input_systems((queue_input as InputSystem,).order_up())
+        .add_systems((update_input as System,).order_up())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{input::ElementState, test_app::TestApp};
+
+    fn input_app() -> TestApp {
+        TestApp::new().unwrap().integrate(input_partial()).unwrap()
+    }
+
+    fn keys(app: &TestApp) -> ButtonInput<KeyCode> {
+        (**app.resource::<ButtonInput<KeyCode>>()).clone()
+    }
+
+    #[test]
+    fn press_and_release_in_one_frame() {
+        let mut app = input_app();
+
+        app.press_key(KeyCode::KeyE);
+        app.release_key(KeyCode::KeyE);
+        app.update().unwrap();
+
+        let keys = keys(&app);
+        assert!(keys.just_pressed(KeyCode::KeyE));
+        assert!(keys.just_released(KeyCode::KeyE));
+        assert!(!keys.pressed(KeyCode::KeyE));
+    }
+
+    #[test]
+    fn just_pressed_only_lasts_a_frame() {
+        let mut app = input_app();
+
+        app.press_key(KeyCode::KeyE);
+        app.update().unwrap();
+        app.update().unwrap();
+
+        let keys = keys(&app);
+        assert!(keys.pressed(KeyCode::KeyE));
+        assert!(!keys.just_pressed(KeyCode::KeyE));
+    }
+
+    #[test]
+    fn repeats_are_ignored() {
+        let mut app = input_app();
+        app.press_key(KeyCode::KeyE);
+        app.update().unwrap();
+
+        app.send_input(InputEvent::Key {
+            code: KeyCode::KeyE,
+            state: ElementState::Pressed,
+            repeat: true,
+        });
+        // A repeat doesn't count as pressing a key that wasn't down either
+        app.send_input(InputEvent::Key {
+            code: KeyCode::KeyQ,
+            state: ElementState::Pressed,
+            repeat: true,
+        });
+        app.update().unwrap();
+
+        let keys = keys(&app);
+        assert!(keys.pressed(KeyCode::KeyE));
+        assert!(!keys.just_pressed(KeyCode::KeyE));
+        assert!(!keys.pressed(KeyCode::KeyQ));
+    }
+
+    #[test]
+    fn losing_focus_releases_everything() {
+        let mut app = input_app();
+        app.press_key(KeyCode::KeyW);
+        app.press_mouse(MouseButton::Left);
+        app.update().unwrap();
+
+        app.send_input(InputEvent::Focused(false));
+        app.update().unwrap();
+
+        let keys = keys(&app);
+        assert!(!keys.pressed(KeyCode::KeyW));
+        assert!(keys.just_released(KeyCode::KeyW));
+        app.assert_resource::<ButtonInput<MouseButton>>(|buttons| {
+            !buttons.pressed(MouseButton::Left) && buttons.just_released(MouseButton::Left)
+        });
+    }
+
+    #[test]
+    fn pixel_scrolling_is_turned_into_lines() {
+        let mut app = input_app();
+
+        app.send_input(InputEvent::MouseWheel(MouseScrollDelta::PixelDelta(
+            PhysicalPosition::new(PIXELS_PER_LINE / 2.0, -PIXELS_PER_LINE * 3.0),
+        )));
+        app.scroll(1.0);
+        app.update().unwrap();
+
+        app.assert_resource::<MouseScroll>(|scroll| scroll.lines == [0.5, -2.0]);
+
+        app.update().unwrap();
+        app.assert_resource::<MouseScroll>(|scroll| scroll.lines == [0.0, 0.0]);
+    }
+
+    #[test]
+    fn motion_starts_once_the_cursor_position_is_known() {
+        let mut app = input_app();
+
+        app.move_cursor(100.0, 100.0);
+        app.update().unwrap();
+        app.assert_resource::<MouseMotion>(|motion| motion.delta == [0.0, 0.0]);
+
+        app.move_cursor(110.0, 95.0);
+        app.move_cursor(115.0, 90.0);
+        app.update().unwrap();
+        app.assert_resource::<MouseMotion>(|motion| motion.delta == [15.0, -10.0]);
+        app.assert_resource::<CursorPosition>(|cursor| {
+            cursor.window == Some(PhysicalPosition::new(115.0, 90.0))
+        });
+
+        // Coming back in somewhere else isn't motion
+        app.send_input(InputEvent::CursorLeft);
+        app.update().unwrap();
+        app.move_cursor(0.0, 0.0);
+        app.update().unwrap();
+        app.assert_resource::<MouseMotion>(|motion| motion.delta == [0.0, 0.0]);
+    }
+}
@@ -0,0 +1,82 @@
+//! Names for entities, so logs and the inspector can say `Mill (12)` instead of `Entity(12)`.
+//!
+//! Names don't have to be unique, they're for people. Use an [`Entity`] to hold onto something.
+
+use std::fmt::{self, Display};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::{World, entity::Entity, reflect::impl_reflect};
+
+/// What an entity is called.
+///
+/// # Example
+/// ```rs
+/// let mill = world.spawn_bundle((Name::new("Mill"), GridPosition::new(4, 2)));
+///
+/// let mill = world.find_by_name("Mill").unwrap();
+/// info!("{} broke down", world.label(mill));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Name(pub String);
+
+impl_reflect!(Name(0));
+
+impl Name {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self(name.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for Name {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for Name {
+    fn from(name: String) -> Self {
+        Self(name)
+    }
+}
+
+impl World {
+    /// The oldest entity called `name`.
+    pub fn find_by_name(&self, name: &str) -> Option<Entity> {
+        self.query::<Name>()
+            .into_iter()
+            .find(|(_, n)| n.as_str() == name)
+            .map(|(entity, _)| entity)
+    }
+
+    /// Every entity called `name`, oldest first.
+    pub fn find_all_by_name(&self, name: &str) -> Vec<Entity> {
+        self.query::<Name>()
+            .into_iter()
+            .filter(|(_, n)| n.as_str() == name)
+            .map(|(entity, _)| entity)
+            .collect()
+    }
+
+    pub fn name(&self, entity: Entity) -> Option<String> {
+        self.get_component::<Name>(entity).map(|n| n.0.clone())
+    }
+
+    /// How to show `entity` to a person, `Mill (12)` if it has a [`Name`] and `Entity 12` if not.
+    pub fn label(&self, entity: Entity) -> String {
+        match self.get_component::<Name>(entity) {
+            Some(name) => format!("{name} ({})", entity.id()),
+            None => format!("Entity {}", entity.id()),
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use crate::ecs::ordering::SystemOrder;
-use crate::ecs::{EventSystem, StartupSystem, System, WinitEventSystem};
+use crate::ecs::{
+    EventSystem, InputSystem, ShutdownSystem, StartupSystem, System, WinitEventSystem,
+};
 
 use seq_macro::seq;
 
@@ -69,3 +71,5 @@ gen_order_up_impl! {WinitEventSystem}
 gen_order_up_impl! {EventSystem}
 gen_order_up_impl! {System}
 gen_order_up_impl! {StartupSystem}
+gen_order_up_impl! {ShutdownSystem}
+gen_order_up_impl! {InputSystem}
@@ -5,10 +5,17 @@ use std::{
     sync::RwLock,
 };
 
+use serde::{Serialize, de::DeserializeOwned};
+
 use crate::ecs::{
-    Component, EventSystem, Resource, StartupSystem, System, WinitEventSystem,
-    events::{EventWrapper, LemgineEvent},
+    Component, EventSystem, InputSystem, Resource, ShutdownSystem, StartupSystem, System,
+    WinitEventSystem,
+    bundle::Bundle,
+    entity::MapEntities,
+    events::{EventHandler, EventHandlers, EventWrapper, TypedEventSystem},
     ordering::SystemOrder,
+    reflect::Reflect,
+    registry::TypeRegistry,
 };
 
 /// A way to create a local version of [`Manager`] that can be tacked onto
@@ -27,10 +34,22 @@ use crate::ecs::{
 pub struct PartialManager {
     pub resources: HashMap<TypeId, Resource>,
     pub components: HashMap<TypeId, Vec<Component>>,
+    /// Each one becomes an entity once this is integrated.
+    pub bundles: Vec<Vec<(TypeId, Box<dyn Any>)>>,
     pub startup_systems: SystemOrder<StartupSystem>,
     pub systems: SystemOrder<System>,
+    pub fixed_systems: SystemOrder<System>,
     pub winit_event_systems: SystemOrder<WinitEventSystem>,
-    pub event_systems: HashMap<LemgineEvent, SystemOrder<EventSystem>>,
+    pub input_systems: SystemOrder<InputSystem>,
+    pub event_systems: EventHandlers,
+    pub shutdown_systems: SystemOrder<ShutdownSystem>,
+    pub type_registry: TypeRegistry,
+}
+
+impl Default for PartialManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PartialManager {
@@ -38,10 +57,15 @@ impl PartialManager {
         Self {
             resources: HashMap::new(),
             components: HashMap::new(),
+            bundles: vec![],
             startup_systems: SystemOrder::empty(),
             systems: SystemOrder::empty(),
+            fixed_systems: SystemOrder::empty(),
             winit_event_systems: SystemOrder::empty(),
-            event_systems: HashMap::new(),
+            input_systems: SystemOrder::empty(),
+            event_systems: EventHandlers::new(),
+            shutdown_systems: SystemOrder::empty(),
+            type_registry: TypeRegistry::new(),
         }
     }
 
@@ -66,6 +90,12 @@ impl PartialManager {
         self
     }
 
+    /// Add an entity made of every component in `bundle`.
+    pub fn add_bundle<B: Bundle>(mut self, bundle: B) -> Self {
+        self.bundles.push(bundle.into_components());
+        self
+    }
+
     /// Add a system that will run once at the beginning.
     ///
     /// Uses the [`StartupSystem`] type.
@@ -85,6 +115,15 @@ impl PartialManager {
         self
     }
 
+    /// Runs for every bit of player input, before the frame's systems.
+    /// Unlike winit event systems these also get the input of replays.
+    ///
+    /// Uses the [`InputSystem`] type.
+    pub fn add_input_systems<S: Into<SystemOrder<InputSystem>>>(mut self, systems: S) -> Self {
+        self.input_systems = systems.into();
+        self
+    }
+
     /// Runs every frame.
     ///
     /// Uses the [`System`] type.
@@ -93,7 +132,30 @@ impl PartialManager {
         self
     }
 
-    /// An event handler. Only called when an event is raised in a system.
+    /// Runs at a fixed rate of virtual time, zero or more times a frame.
+    /// Use [`Time::fixed_delta_secs`](crate::ecs::time::Time::fixed_delta_secs) as the delta inside of these.
+    ///
+    /// Uses the [`System`] type.
+    pub fn add_fixed_systems<S: Into<SystemOrder<System>>>(mut self, systems: S) -> Self {
+        self.fixed_systems = systems.into();
+        self
+    }
+
+    /// Runs once when the game quits, after the last frame is done.
+    ///
+    /// Plugins are shut down in the reverse order they were integrated, so a plugin can still
+    /// use anything set up by the ones integrated before it.
+    ///
+    /// Uses the [`ShutdownSystem`] type.
+    pub fn add_shutdown_systems<S: Into<SystemOrder<ShutdownSystem>>>(
+        mut self,
+        systems: S,
+    ) -> Self {
+        self.shutdown_systems = systems.into();
+        self
+    }
+
+    /// An event handler. Only called when an event equal to `event` is raised in a system.
     ///
     /// Uses the [`EventSystem`] type.
     pub fn add_event_handler<E: EventWrapper + 'static, S: Into<SystemOrder<EventSystem>>>(
@@ -102,8 +164,62 @@ impl PartialManager {
         system: S,
     ) -> Self {
         self.event_systems
-            .entry(Box::new(event))
-            .or_insert(system.into());
+            .add_value_handler(Box::new(event), system.into());
+        self
+    }
+
+    /// An event handler for every event of type `E`, gets the event that was raised.
+    ///
+    /// # Example
+    /// ```rs
+    /// fn on_machine_broke(world: &World, event: &MachineBroke, data: LemgineEventData) -> Result<()> {
+    ///     info!("Machine {} broke", event.id);
+    ///     Ok(())
+    /// }
+    ///
+    /// PartialManager::new().add_typed_event_handler(on_machine_broke);
+    /// ```
+    ///
+    /// Uses the [`TypedEventSystem`] type.
+    pub fn add_typed_event_handler<E: EventWrapper + 'static>(
+        mut self,
+        system: TypedEventSystem<E>,
+    ) -> Self {
+        self.event_systems
+            .add_type_handler(EventHandler::new(system));
+        self
+    }
+
+    /// Same as [`Self::add_typed_event_handler`] but with a priority or filter,
+    /// see [`EventHandler`].
+    pub fn add_typed_event_handler_with(mut self, handler: EventHandler) -> Self {
+        self.event_systems.add_type_handler(handler);
+        self
+    }
+
+    /// Opt a component or resource type in to being saved in snapshots.
+    ///
+    /// `name` is what the type is called in save files, so keep it stable.
+    pub fn register_type<T: Any + Serialize + DeserializeOwned>(
+        mut self,
+        name: &'static str,
+    ) -> Self {
+        self.type_registry.register::<T>(name);
+        self
+    }
+
+    /// Same as [`Self::register_type`] but for types that hold onto entities.
+    pub fn register_mapped_type<T: Any + Serialize + DeserializeOwned + MapEntities>(
+        mut self,
+        name: &'static str,
+    ) -> Self {
+        self.type_registry.register_mapped::<T>(name);
+        self
+    }
+
+    /// Let the inspector and console look inside of a component or resource type.
+    pub fn register_reflect_type<T: Reflect>(mut self, name: &'static str) -> Self {
+        self.type_registry.register_reflect::<T>(name);
         self
     }
 }
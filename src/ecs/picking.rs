@@ -0,0 +1,488 @@
+//! What's under the cursor.
+//!
+//! [`CursorPosition::world`] is where the cursor is through the active camera, [`TileGrid`] turns
+//! that into a tile and [`update_picking`] finds the topmost entity with [`SpriteBounds`] there,
+//! only looking at the tiles around the cursor.
+//! The result is kept in [`Picked`] for polling, and [`PickEvent`]s are raised when the cursor
+//! moves on or off of an entity or something gets clicked. Nothing is picked while the cursor is
+//! over the gui.
+//!
+//! # Example
+//! ```rs
+//! fn on_pick(world: &World, event: &PickEvent, data: LemgineEventData) -> Result<()> {
+//!     let pick = data.downcast_ref::<PickData>().unwrap();
+//!     if *event == PickEvent::Clicked(MouseButton::Left) && pick.entity.is_none() {
+//!         world.queue_command(move |world| place_building(world, pick.tile));
+//!     }
+//!     Ok(())
+//! }
+//!
+//! PartialManager::new().add_typed_event_handler(on_pick);
+//! ```
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::{
+    System, World,
+    changes::ChangeReader,
+    entity::Entity,
+    events::{EcsEvent, EcsEventData},
+    input::{InputCapture, MouseButton},
+    input_state::{ButtonInput, CursorPosition},
+    order_up::OrderUp,
+    partial_manager::PartialManager,
+    reflect::impl_reflect,
+    spatial::{GridPosition, SpatialIndex},
+};
+
+/// How big the tiles of [`GridPosition`] are in the world. Tile (0, 0) goes from the origin to
+/// `(tile_size, tile_size)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileGrid {
+    pub tile_size: f32,
+}
+
+impl_reflect!(TileGrid { tile_size });
+
+impl Default for TileGrid {
+    fn default() -> Self {
+        Self { tile_size: 1.0 }
+    }
+}
+
+impl TileGrid {
+    pub fn new(tile_size: f32) -> Self {
+        Self { tile_size }
+    }
+
+    /// The tile `point` is on.
+    pub fn tile_at(&self, point: [f32; 2]) -> GridPosition {
+        GridPosition::new(
+            (point[0] / self.tile_size).floor() as i32,
+            (point[1] / self.tile_size).floor() as i32,
+        )
+    }
+
+    /// The middle of `tile` in the world.
+    pub fn tile_center(&self, tile: GridPosition) -> [f32; 2] {
+        [
+            (tile.x as f32 + 0.5) * self.tile_size,
+            (tile.y as f32 + 0.5) * self.tile_size,
+        ]
+    }
+}
+
+/// The rectangle an entity's sprite covers, centered on the middle of its [`GridPosition`].
+/// Entities need both to be picked.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpriteBounds {
+    /// Width and height in world units.
+    pub size: [f32; 2],
+    /// How far the middle of the sprite is from the middle of the tile.
+    #[serde(default)]
+    pub offset: [f32; 2],
+    /// Higher layers are drawn on top, and picked first.
+    #[serde(default)]
+    pub layer: i32,
+    /// Which goes on top within a layer, higher first.
+    #[serde(default)]
+    pub order: i32,
+}
+
+impl_reflect!(SpriteBounds {
+    size,
+    offset,
+    layer,
+    order
+});
+
+impl SpriteBounds {
+    pub fn new(size: [f32; 2]) -> Self {
+        Self {
+            size,
+            offset: [0.0; 2],
+            layer: 0,
+            order: 0,
+        }
+    }
+
+    pub fn with_offset(mut self, offset: [f32; 2]) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    pub fn with_order(mut self, order: i32) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// How far from the middle of its tile the sprite goes, in world units.
+    fn reach(&self) -> f32 {
+        (0..2)
+            .map(|i| self.offset[i].abs() + self.size[i] / 2.0)
+            .fold(0.0, f32::max)
+    }
+
+    /// Whether `point` is on the sprite when it's on `tile`.
+    pub fn contains(&self, grid: &TileGrid, tile: GridPosition, point: [f32; 2]) -> bool {
+        let center = grid.tile_center(tile);
+        (0..2).all(|i| (point[i] - center[i] - self.offset[i]).abs() <= self.size[i] / 2.0)
+    }
+}
+
+/// How far any sprite reaches from its tile, so [`pick`] knows how many tiles around the point
+/// could have something on them. It only ever grows, shrinking it would mean looking at every
+/// sprite again.
+#[derive(Default)]
+struct SpriteReach {
+    reader: Option<ChangeReader<SpriteBounds>>,
+    reach: f32,
+}
+
+impl SpriteReach {
+    /// Catch up with the sprites that changed, and return how many tiles away from the tile under
+    /// a point a sprite covering it can be.
+    fn tiles(&mut self, world: &World, grid: &TileGrid) -> i32 {
+        let reader = self
+            .reader
+            .get_or_insert_with(|| world.track_changes::<SpriteBounds>());
+        for entity in world.read_changes(reader).changed {
+            if let Some(bounds) = world.get_component::<SpriteBounds>(entity) {
+                self.reach = self.reach.max(bounds.reach());
+            }
+        }
+        // The point can be anywhere on its tile, so half a tile more
+        (self.reach / grid.tile_size + 0.5).ceil() as i32
+    }
+}
+
+/// The topmost entity at `point`, see [`SpriteBounds::layer`] and [`SpriteBounds::order`].
+/// Entities that tie on both go by whichever was spawned last.
+///
+/// Only the tiles around `point` are looked at when there's a [`SpatialIndex`] of
+/// [`GridPosition`], every sprite otherwise.
+pub fn pick(world: &World, point: [f32; 2]) -> Option<Entity> {
+    let grid = world
+        .try_get_resource::<TileGrid>()
+        .map(|grid| **grid)
+        .unwrap_or_default();
+    let nearby = match (
+        world.try_get_resource::<SpatialIndex>(),
+        world.try_get_resource_mut::<SpriteReach>(),
+    ) {
+        (Some(index), Some(mut reach)) => {
+            let reach = reach.tiles(world, &grid);
+            let tile = grid.tile_at(point);
+            index.in_rect(
+                GridPosition::new(tile.x.saturating_sub(reach), tile.y.saturating_sub(reach)),
+                GridPosition::new(tile.x.saturating_add(reach), tile.y.saturating_add(reach)),
+            )
+        }
+        _ => world
+            .query::<SpriteBounds>()
+            .into_iter()
+            .map(|(entity, _)| entity)
+            .collect(),
+    };
+
+    nearby
+        .into_iter()
+        .filter_map(|entity| {
+            let bounds = **world.get_component::<SpriteBounds>(entity)?;
+            let tile = **world.get_component::<GridPosition>(entity)?;
+            bounds
+                .contains(&grid, tile, point)
+                .then_some((entity, bounds))
+        })
+        .max_by_key(|(entity, bounds)| (bounds.layer, bounds.order, *entity))
+        .map(|(entity, _)| entity)
+}
+
+/// What's under the cursor this frame, all `None` while it's outside of the window or over the
+/// gui.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Picked {
+    pub world: Option<[f32; 2]>,
+    pub tile: Option<GridPosition>,
+    pub entity: Option<Entity>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PickEvent {
+    /// The cursor moved onto the entity.
+    HoverStarted(Entity),
+    /// The cursor moved off of the entity, or it's gone.
+    HoverEnded(Entity),
+    /// The button was pressed with the cursor over the world, [`PickData::entity`] is `None` for
+    /// an empty tile.
+    Clicked(MouseButton),
+}
+
+impl EcsEvent for PickEvent {}
+
+/// Where a [`PickEvent`] happened, `world` and `tile` are zeroes when the cursor left the window.
+#[derive(Clone, Copy, Debug)]
+pub struct PickData {
+    pub world: [f32; 2],
+    pub tile: GridPosition,
+    pub entity: Option<Entity>,
+}
+
+impl EcsEventData for PickData {}
+
+/// Works out what's under the cursor and raises the [`PickEvent`]s.
+pub fn update_picking(world: &World) -> Result<()> {
+    let over_gui = world
+        .try_get_resource::<InputCapture>()
+        .is_some_and(|capture| capture.wants_pointer);
+    let point = match over_gui {
+        true => None,
+        false => world.get_resource::<CursorPosition>().world,
+    };
+
+    let grid = world
+        .try_get_resource::<TileGrid>()
+        .map(|grid| **grid)
+        .unwrap_or_default();
+    let picked = Picked {
+        world: point,
+        tile: point.map(|point| grid.tile_at(point)),
+        entity: point.and_then(|point| pick(world, point)),
+    };
+    let last = std::mem::replace(&mut **world.get_resource_mut::<Picked>(), picked);
+
+    let data = |entity| PickData {
+        world: picked.world.unwrap_or_default(),
+        tile: picked.tile.unwrap_or_default(),
+        entity,
+    };
+    if last.entity != picked.entity {
+        if let Some(entity) = last.entity {
+            world.raise_event(PickEvent::HoverEnded(entity), data(Some(entity)));
+        }
+        if let Some(entity) = picked.entity {
+            world.raise_event(PickEvent::HoverStarted(entity), data(Some(entity)));
+        }
+    }
+
+    if point.is_some() {
+        for button in world
+            .get_resource::<ButtonInput<MouseButton>>()
+            .get_just_pressed()
+        {
+            world.raise_event(PickEvent::Clicked(button), data(picked.entity));
+        }
+    }
+
+    Ok(())
+}
+
+/// [`Picked`] and the [`PickEvent`]s, add it after
+/// [`input_partial`](crate::ecs::input_state::input_partial) and the engine so the cursor is
+/// already in the world, and after [`spatial_partial`](crate::ecs::spatial::spatial_partial) of
+/// [`GridPosition`] so it only has to look around the cursor.
+pub fn picking_partial(grid: TileGrid) -> PartialManager {
+    PartialManager::new()
+        .add_resource(grid)
+        .add_resource(Picked::default())
+        .add_resource(SpriteReach::default())
+        .register_type::<SpriteBounds>("sprite_bounds")
+        .register_reflect_type::<SpriteBounds>("sprite_bounds")
+        .register_reflect_type::<TileGrid>("tile_grid")
+        .add_systems((update_picking as System,).order_up())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{
+        events::LemgineEventData, input_state::input_partial, spatial::spatial_partial,
+        test_app::TestApp,
+    };
+
+    /// Every [`PickEvent`] raised so far and the entity it was raised with.
+    #[derive(Default)]
+    struct SeenPicks(Vec<(PickEvent, Option<Entity>)>);
+
+    fn see_pick(world: &World, event: &PickEvent, data: LemgineEventData) -> Result<()> {
+        let entity = data.downcast_ref::<PickData>().unwrap().entity;
+        world
+            .get_resource_mut::<SeenPicks>()
+            .0
+            .push((*event, entity));
+        Ok(())
+    }
+
+    fn picking_app() -> TestApp {
+        TestApp::new()
+            .unwrap()
+            .integrate(input_partial())
+            .unwrap()
+            .integrate(spatial_partial::<GridPosition>())
+            .unwrap()
+            .integrate(picking_partial(TileGrid::new(1.0)))
+            .unwrap()
+            .integrate(
+                PartialManager::new()
+                    .add_resource(SeenPicks::default())
+                    .add_typed_event_handler(see_pick),
+            )
+            .unwrap()
+    }
+
+    /// Put the cursor over `point` in the world, what the engine does through the camera.
+    fn point_at(app: &mut TestApp, point: Option<[f32; 2]>) {
+        app.resource_mut::<CursorPosition>().world = point;
+    }
+
+    /// Run a frame and return the [`PickEvent`]s raised in it.
+    fn picks_in_frame(app: &mut TestApp) -> Vec<(PickEvent, Option<Entity>)> {
+        app.update().unwrap();
+        // The events are handled at the start of the next frame at the latest
+        app.update().unwrap();
+        std::mem::take(&mut app.resource_mut::<SeenPicks>().0)
+    }
+
+    fn spawn(app: &mut TestApp, tile: GridPosition, bounds: SpriteBounds) -> Entity {
+        app.world_mut().spawn_bundle((tile, bounds))
+    }
+
+    #[test]
+    fn picks_sprites_reaching_over_from_other_tiles() {
+        let mut app = picking_app();
+        let tall = spawn(
+            &mut app,
+            GridPosition::new(0, 0),
+            SpriteBounds::new([1.0, 1.0]).with_offset([0.0, 3.0]),
+        );
+        app.update().unwrap();
+
+        assert_eq!(pick(app.world(), [0.5, 3.5]), Some(tall));
+        assert_eq!(pick(app.world(), [0.5, 1.5]), None);
+    }
+
+    #[test]
+    fn order_breaks_ties_within_a_layer() {
+        let mut app = picking_app();
+        let tile = GridPosition::new(2, 2);
+        let front = spawn(&mut app, tile, SpriteBounds::new([1.0; 2]).with_order(1));
+        let _back = spawn(&mut app, tile, SpriteBounds::new([1.0; 2]));
+        let top = spawn(
+            &mut app,
+            tile,
+            SpriteBounds::new([0.5; 2]).with_layer(1).with_order(-5),
+        );
+        app.update().unwrap();
+
+        assert_eq!(pick(app.world(), [2.5, 2.5]), Some(top));
+        assert_eq!(pick(app.world(), [2.1, 2.1]), Some(front));
+    }
+
+    #[test]
+    fn hovering_and_clicking_raise_events_for_the_entity() {
+        let mut app = picking_app();
+        let mill = spawn(
+            &mut app,
+            GridPosition::new(1, 1),
+            SpriteBounds::new([1.0; 2]),
+        );
+        let silo = spawn(
+            &mut app,
+            GridPosition::new(3, 1),
+            SpriteBounds::new([1.0; 2]),
+        );
+
+        point_at(&mut app, Some([1.5, 1.5]));
+        assert_eq!(
+            picks_in_frame(&mut app),
+            [(PickEvent::HoverStarted(mill), Some(mill))]
+        );
+
+        app.press_mouse(MouseButton::Left);
+        assert_eq!(
+            picks_in_frame(&mut app),
+            [(PickEvent::Clicked(MouseButton::Left), Some(mill))]
+        );
+        app.release_mouse(MouseButton::Left);
+
+        point_at(&mut app, Some([3.5, 1.5]));
+        assert_eq!(
+            picks_in_frame(&mut app),
+            [
+                (PickEvent::HoverEnded(mill), Some(mill)),
+                (PickEvent::HoverStarted(silo), Some(silo)),
+            ]
+        );
+        app.assert_resource::<Picked>(|picked| {
+            picked.entity == Some(silo) && picked.tile == Some(GridPosition::new(3, 1))
+        });
+
+        // Clicking nothing is still a click
+        point_at(&mut app, Some([2.5, 1.5]));
+        app.press_mouse(MouseButton::Right);
+        assert_eq!(
+            picks_in_frame(&mut app),
+            [
+                (PickEvent::HoverEnded(silo), Some(silo)),
+                (PickEvent::Clicked(MouseButton::Right), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn nothing_is_picked_over_the_gui() {
+        let mut app = picking_app();
+        spawn(
+            &mut app,
+            GridPosition::new(1, 1),
+            SpriteBounds::new([1.0; 2]),
+        );
+
+        point_at(&mut app, Some([1.5, 1.5]));
+        app.press_mouse(MouseButton::Left);
+        // Only after the click, so it gets through and it's up to picking to ignore it
+        app.world_mut().add_resource(InputCapture {
+            wants_pointer: true,
+            ..Default::default()
+        });
+
+        assert_eq!(picks_in_frame(&mut app), []);
+        app.assert_resource::<Picked>(|picked| *picked == Picked::default());
+    }
+
+    #[test]
+    fn sprites_reaching_over_a_neighbour_are_picked_over_it() {
+        let mut app = picking_app();
+        let tall = spawn(
+            &mut app,
+            GridPosition::new(0, 0),
+            SpriteBounds::new([1.0, 1.0])
+                .with_offset([0.0, 1.0])
+                .with_order(1),
+        );
+        let below = spawn(
+            &mut app,
+            GridPosition::new(0, 1),
+            SpriteBounds::new([1.0; 2]),
+        );
+
+        point_at(&mut app, Some([0.5, 1.5]));
+        assert_eq!(
+            picks_in_frame(&mut app),
+            [(PickEvent::HoverStarted(tall), Some(tall))]
+        );
+
+        point_at(&mut app, Some([0.5, 1.9]));
+        app.update().unwrap();
+        app.assert_resource::<Picked>(|picked| {
+            picked.entity == Some(tall) && picked.tile == Some(GridPosition::new(0, 1))
+        });
+        assert_ne!(Some(below), app.resource::<Picked>().entity);
+    }
+}
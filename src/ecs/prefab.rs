@@ -0,0 +1,338 @@
+//! Entities described in RON files instead of code.
+//!
+//! Every `.ron` file in the prefab folder (`resources/prefabs` by default) is one prefab, named
+//! after the file. Components are keyed by the name their type was registered under with
+//! [`TypeRegistry::register`](crate::ecs::registry::TypeRegistry::register), and children are
+//! spawned with a [`Parent`](crate::ecs::hierarchy::Parent) pointing back at the entity above them.
+//!
+//! # Example
+//! `resources/prefabs/bug.ron`:
+//! ```ron
+//! (
+//!     components: {
+//!         "grid_position": (x: 0, y: 0),
+//!         "health": (10),
+//!     },
+//!     children: [
+//!         (prefab: Some("antenna")),
+//!         (
+//!             prefab: Some("antenna"),
+//!             components: { "grid_position": (x: 1, y: 0) },
+//!         ),
+//!     ],
+//! )
+//! ```
+//!
+//! ```rs
+//! let bug = world.spawn_prefab("bug")?;
+//! ```
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow};
+use log::*;
+use ron::value::RawValue;
+use serde::Deserialize;
+
+use crate::ecs::{World, entity::Entity};
+
+/// Where [`Prefabs::load_default`] looks.
+pub const PREFAB_DIR: &str = "resources/prefabs";
+
+/// A single entity in a prefab file, and everything below it.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PrefabNode {
+    /// Start off as a copy of this prefab, then add `components` on top.
+    pub prefab: Option<String>,
+    /// Registered type name to the component in RON, parsed when the prefab is spawned.
+    pub components: HashMap<String, Box<RawValue>>,
+    pub children: Vec<PrefabNode>,
+}
+
+/// Every loaded prefab by name. Add it as a resource to use [`World::spawn_prefab`].
+#[derive(Default)]
+pub struct Prefabs {
+    prefabs: HashMap<String, PrefabNode>,
+}
+
+impl Prefabs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load every prefab in [`PREFAB_DIR`].
+    pub fn load_default() -> Result<Self> {
+        Self::load_dir(PREFAB_DIR)
+    }
+
+    /// Load every `.ron` file in `dir`. A missing folder just means there are no prefabs.
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref();
+        let mut prefabs = Self::new();
+
+        if !dir.exists() {
+            debug!("Prefab folder {} doesn't exist.", dir.display());
+            return Ok(prefabs);
+        }
+
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<_, _>>()?;
+        paths.sort();
+
+        for path in paths {
+            if path.extension().is_some_and(|e| e == "ron") {
+                prefabs.load_file(&path)?;
+            }
+        }
+
+        info!("Loaded {} prefabs from {}", prefabs.len(), dir.display());
+        Ok(prefabs)
+    }
+
+    /// Load a single prefab, named after the file.
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let name = path
+            .file_stem()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| anyhow!("Prefab {} has no usable name.", path.display()))?;
+
+        let source = fs::read_to_string(path)?;
+        self.add_ron(name, &source)
+            .map_err(|err| anyhow!("Failed to load prefab {}: {err}", path.display()))
+    }
+
+    /// Parse a prefab from a string, replacing any prefab already called `name`.
+    pub fn add_ron(&mut self, name: &str, source: &str) -> Result<()> {
+        let node: PrefabNode = ron::from_str(source)?;
+        self.add(name, node);
+        Ok(())
+    }
+
+    pub fn add(&mut self, name: &str, node: PrefabNode) {
+        if self.prefabs.insert(name.to_string(), node).is_some() {
+            debug!("Replaced prefab {name}");
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PrefabNode> {
+        self.prefabs.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.prefabs.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.prefabs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prefabs.is_empty()
+    }
+}
+
+impl World {
+    /// Spawn the prefab called `name` along with all of its children, returns the top entity.
+    ///
+    /// Nothing is spawned if any part of the prefab fails to load.
+    pub fn spawn_prefab(&mut self, name: &str) -> Result<Entity> {
+        let mut built = vec![];
+        self.build_prefab(
+            &PrefabNode {
+                prefab: Some(name.to_string()),
+                ..Default::default()
+            },
+            &mut vec![],
+            &mut built,
+        )?;
+
+        Ok(self.spawn_built_prefab(built.pop().unwrap(), None))
+    }
+
+    /// Parse every component of `node` up front so a typo halfway down doesn't leave half a
+    /// prefab behind. `stack` holds the prefabs being built, to catch prefabs including themselves.
+    fn build_prefab(
+        &self,
+        node: &PrefabNode,
+        stack: &mut Vec<String>,
+        built: &mut Vec<BuiltPrefab>,
+    ) -> Result<()> {
+        let mut base = vec![];
+        if let Some(name) = &node.prefab {
+            if stack.contains(name) {
+                return Err(anyhow!(
+                    "Prefab {name} includes itself ({} -> {name}).",
+                    stack.join(" -> ")
+                ));
+            }
+
+            let prefab = self
+                .try_get_resource::<Prefabs>()
+                .ok_or_else(|| anyhow!("There is no Prefabs resource."))?
+                .get(name)
+                .cloned()
+                .ok_or_else(|| anyhow!("There is no prefab called {name}."))?;
+
+            stack.push(name.clone());
+            self.build_prefab(&prefab, stack, &mut base)?;
+            stack.pop();
+        }
+        let mut prefab = base.pop().unwrap_or_default();
+
+        let registry = self.type_registry();
+        for (type_name, ron) in node.components.iter() {
+            let registration = registry
+                .get_by_name(type_name)
+                .ok_or_else(|| anyhow!("No type is registered as {type_name}."))?;
+            let component = registration
+                .from_ron(ron.get_ron())
+                .map_err(|err| anyhow!("Couldn't read {type_name}: {err}"))?;
+
+            prefab
+                .components
+                .retain(|(id, _)| *id != registration.type_id);
+            prefab.components.push((registration.type_id, component));
+        }
+        drop(registry);
+
+        for child in node.children.iter() {
+            self.build_prefab(child, stack, &mut prefab.children)?;
+        }
+
+        built.push(prefab);
+        Ok(())
+    }
+
+    fn spawn_built_prefab(&mut self, prefab: BuiltPrefab, parent: Option<Entity>) -> Entity {
+        let entity = self.spawn();
+        for (id, component) in prefab.components {
+            self.insert_boxed(entity, id, component);
+        }
+        if let Some(parent) = parent {
+            self.set_parent(entity, parent);
+        }
+
+        for child in prefab.children {
+            self.spawn_built_prefab(child, Some(entity));
+        }
+
+        entity
+    }
+}
+
+/// A prefab with its components parsed, ready to be spawned.
+#[derive(Default)]
+struct BuiltPrefab {
+    components: Vec<(TypeId, Box<dyn Any>)>,
+    children: Vec<BuiltPrefab>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use super::*;
+    use crate::ecs::{hierarchy::Parent, name::Name, picking::SpriteBounds, spatial::GridPosition};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Health(u32);
+
+    fn prefab_world(prefabs: Prefabs) -> World {
+        let mut world = World::with_defaults();
+        world.register_type::<Health>("health");
+        world.add_resource(prefabs);
+        world
+    }
+
+    #[test]
+    fn spawns_a_prefab_from_ron() {
+        let mut prefabs = Prefabs::new();
+        prefabs
+            .add_ron("antenna", r#"(components: { "name": ("Antenna") })"#)
+            .unwrap();
+        prefabs
+            .add_ron(
+                "bug",
+                r#"(
+                    components: {
+                        "grid_position": (x: 2, y: 3),
+                        "health": (10),
+                    },
+                    children: [
+                        (
+                            prefab: Some("antenna"),
+                            components: { "grid_position": (x: 1, y: 0) },
+                        ),
+                    ],
+                )"#,
+            )
+            .unwrap();
+        let mut world = prefab_world(prefabs);
+
+        let bug = world.spawn_prefab("bug").unwrap();
+
+        assert_eq!(
+            **world.get_component::<GridPosition>(bug).unwrap(),
+            GridPosition::new(2, 3)
+        );
+        assert_eq!(**world.get_component::<Health>(bug).unwrap(), Health(10));
+
+        let antenna = world
+            .entities()
+            .find(|e| {
+                world
+                    .get_component::<Parent>(*e)
+                    .is_some_and(|p| p.0 == bug)
+            })
+            .unwrap();
+        assert_eq!(
+            **world.get_component::<Name>(antenna).unwrap(),
+            Name("Antenna".to_string())
+        );
+        assert_eq!(
+            **world.get_component::<GridPosition>(antenna).unwrap(),
+            GridPosition::new(1, 0)
+        );
+    }
+
+    #[test]
+    fn a_bad_prefab_spawns_nothing() {
+        let mut prefabs = Prefabs::new();
+        prefabs
+            .add_ron(
+                "bug",
+                r#"(
+                    components: { "health": (10) },
+                    children: [(components: { "health": ("lots") })],
+                )"#,
+            )
+            .unwrap();
+        let mut world = prefab_world(prefabs);
+
+        assert!(world.spawn_prefab("bug").is_err());
+        assert_eq!(world.entities().count(), 0);
+    }
+
+    #[test]
+    fn shipped_prefabs_spawn() {
+        let prefabs = Prefabs::load_default().unwrap();
+        let names: Vec<String> = prefabs.names().map(str::to_string).collect();
+        assert!(!names.is_empty());
+
+        let mut world = prefab_world(prefabs);
+        world.register_type::<SpriteBounds>("sprite_bounds");
+        for name in names {
+            world
+                .spawn_prefab(&name)
+                .unwrap_or_else(|err| panic!("Prefab {name} doesn't spawn: {err}"));
+        }
+    }
+}
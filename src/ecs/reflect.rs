@@ -0,0 +1,840 @@
+//! Looking inside of components and resources without knowing their type.
+//!
+//! The [`World`] only knows things by [`TypeId`], so anything that wants to list or edit what's in
+//! it by name (the inspector, the console) goes through [`Reflect`] and the
+//! [`TypeRegistry`](crate::ecs::registry::TypeRegistry).
+//!
+//! # Example
+//! ```rs
+//! struct MovementData {
+//!     up: bool,
+//!     speed: f32,
+//! }
+//! impl_reflect!(MovementData { up, speed });
+//!
+//! world.set_resource_path("movement", "speed", "2.5")?;
+//! ```
+
+use std::{
+    any::{Any, TypeId, type_name},
+    fmt::{self, Display},
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow};
+
+use crate::ecs::{World, entity::Entity};
+
+/// A reflected value, either a plain value or a tree of them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReflectValue {
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+    Entity(Entity),
+    Struct(Vec<(String, ReflectValue)>),
+    List(Vec<ReflectValue>),
+    /// Something that can be shown but not broken down or edited.
+    Opaque(String),
+}
+
+impl ReflectValue {
+    /// Parse `s` into the same kind of value as `self`.
+    ///
+    /// Used by text inputs where the only thing known is what the field currently holds.
+    pub fn parse_like(&self, s: &str) -> Result<ReflectValue> {
+        let s = s.trim();
+        Ok(match self {
+            ReflectValue::Bool(_) => ReflectValue::Bool(s.parse()?),
+            ReflectValue::Int(_) => ReflectValue::Int(s.parse()?),
+            ReflectValue::UInt(_) => ReflectValue::UInt(s.parse()?),
+            ReflectValue::Float(_) => ReflectValue::Float(s.parse()?),
+            ReflectValue::String(_) => ReflectValue::String(s.to_string()),
+            ReflectValue::Entity(_) => ReflectValue::Entity(Entity(s.parse()?)),
+            ReflectValue::Struct(_) | ReflectValue::List(_) | ReflectValue::Opaque(_) => {
+                return Err(anyhow!("Can't parse \"{s}\" into a non plain value"));
+            }
+        })
+    }
+}
+
+impl Display for ReflectValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReflectValue::Bool(v) => write!(f, "{v}"),
+            ReflectValue::Int(v) => write!(f, "{v}"),
+            ReflectValue::UInt(v) => write!(f, "{v}"),
+            ReflectValue::Float(v) => write!(f, "{v}"),
+            ReflectValue::String(v) => write!(f, "{v:?}"),
+            ReflectValue::Entity(v) => write!(f, "Entity({})", v.id()),
+            ReflectValue::Struct(fields) => {
+                write!(f, "{{ ")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{name}: {value}")?;
+                }
+                write!(f, " }}")
+            }
+            ReflectValue::List(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                write!(f, "]")
+            }
+            ReflectValue::Opaque(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+/// A type that can be looked at and changed without knowing what it is.
+///
+/// Plain values (numbers, strings, ...) only implement [`Self::value`] and [`Self::set_value`].
+/// Anything with fields also lists them so they can be walked with a path like `"position.0"`.
+///
+/// Implement this for structs with [`impl_reflect`].
+pub trait Reflect: Any {
+    /// Rust name of the type.
+    fn reflect_type_name(&self) -> &'static str {
+        type_name::<Self>()
+    }
+
+    /// Names of the fields of this value, empty for plain values.
+    fn field_names(&self) -> Vec<String> {
+        vec![]
+    }
+
+    fn field(&self, _name: &str) -> Option<&dyn Reflect> {
+        None
+    }
+
+    fn field_mut(&mut self, _name: &str) -> Option<&mut dyn Reflect> {
+        None
+    }
+
+    /// Whether the field can be looked at but not changed, [`Reflect::field_mut`] is `None` for
+    /// those.
+    fn is_read_only(&self, _name: &str) -> bool {
+        false
+    }
+
+    /// The current value.
+    fn value(&self) -> ReflectValue;
+
+    /// Overwrite the current value, converting between numbers where it fits.
+    fn set_value(&mut self, value: ReflectValue) -> Result<()>;
+}
+
+impl dyn Reflect {
+    /// Walk down a `.` separated path of field names. An empty path is `self`.
+    pub fn path(&self, path: &str) -> Result<&dyn Reflect> {
+        let mut current = self;
+        for name in path.split('.').filter(|s| !s.is_empty()) {
+            current = current.field(name).ok_or_else(|| {
+                anyhow!("{} has no field \"{name}\"", current.reflect_type_name())
+            })?;
+        }
+        Ok(current)
+    }
+
+    pub fn path_mut(&mut self, path: &str) -> Result<&mut dyn Reflect> {
+        let mut current = self;
+        for name in path.split('.').filter(|s| !s.is_empty()) {
+            let type_name = current.reflect_type_name();
+            if current.is_read_only(name) {
+                return Err(anyhow!("{type_name}.{name} is read-only"));
+            }
+            current = current
+                .field_mut(name)
+                .ok_or_else(|| anyhow!("{type_name} has no field \"{name}\""))?;
+        }
+        Ok(current)
+    }
+
+    pub fn get_path(&self, path: &str) -> Result<ReflectValue> {
+        Ok(self.path(path)?.value())
+    }
+
+    pub fn set_path(&mut self, path: &str, value: ReflectValue) -> Result<()> {
+        self.path_mut(path)?.set_value(value)
+    }
+
+    /// Parse `value` into whatever the field at `path` holds and set it.
+    pub fn set_path_str(&mut self, path: &str, value: &str) -> Result<()> {
+        let field = self.path_mut(path)?;
+        let value = field.value().parse_like(value)?;
+        field.set_value(value)
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        (self as &dyn Any).downcast_ref::<T>()
+    }
+
+    pub fn downcast_mut<T: Any>(&mut self) -> Option<&mut T> {
+        (self as &mut dyn Any).downcast_mut::<T>()
+    }
+}
+
+fn mismatch<T: ?Sized>(value: &ReflectValue) -> anyhow::Error {
+    anyhow!("Can't set a {} to {value}", type_name::<T>())
+}
+
+macro_rules! impl_reflect_int {
+    ($variant:ident, $inner:ty, $($t:ty),*) => {
+        $(
+            impl Reflect for $t {
+                fn value(&self) -> ReflectValue {
+                    ReflectValue::$variant(*self as $inner)
+                }
+
+                fn set_value(&mut self, value: ReflectValue) -> Result<()> {
+                    *self = match value {
+                        ReflectValue::Int(v) => <$t>::try_from(v)?,
+                        ReflectValue::UInt(v) => <$t>::try_from(v)?,
+                        ReflectValue::Float(v) if v.fract() == 0.0 => <$t>::try_from(v as i64)?,
+                        _ => return Err(mismatch::<$t>(&value)),
+                    };
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_reflect_int!(Int, i64, i8, i16, i32, i64, isize);
+impl_reflect_int!(UInt, u64, u8, u16, u32, u64, usize);
+
+macro_rules! impl_reflect_float {
+    ($($t:ty),*) => {
+        $(
+            impl Reflect for $t {
+                fn value(&self) -> ReflectValue {
+                    ReflectValue::Float(*self as f64)
+                }
+
+                fn set_value(&mut self, value: ReflectValue) -> Result<()> {
+                    *self = match value {
+                        ReflectValue::Int(v) => v as $t,
+                        ReflectValue::UInt(v) => v as $t,
+                        ReflectValue::Float(v) => v as $t,
+                        _ => return Err(mismatch::<$t>(&value)),
+                    };
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_reflect_float!(f32, f64);
+
+impl Reflect for bool {
+    fn value(&self) -> ReflectValue {
+        ReflectValue::Bool(*self)
+    }
+
+    fn set_value(&mut self, value: ReflectValue) -> Result<()> {
+        match value {
+            ReflectValue::Bool(v) => *self = v,
+            _ => return Err(mismatch::<bool>(&value)),
+        }
+        Ok(())
+    }
+}
+
+impl Reflect for String {
+    fn value(&self) -> ReflectValue {
+        ReflectValue::String(self.clone())
+    }
+
+    fn set_value(&mut self, value: ReflectValue) -> Result<()> {
+        match value {
+            ReflectValue::String(v) => *self = v,
+            _ => return Err(mismatch::<String>(&value)),
+        }
+        Ok(())
+    }
+}
+
+impl Reflect for Entity {
+    fn value(&self) -> ReflectValue {
+        ReflectValue::Entity(*self)
+    }
+
+    fn set_value(&mut self, value: ReflectValue) -> Result<()> {
+        match value {
+            ReflectValue::Entity(v) => *self = v,
+            _ => return Err(mismatch::<Entity>(&value)),
+        }
+        Ok(())
+    }
+}
+
+/// Shown and set in seconds.
+impl Reflect for Duration {
+    fn value(&self) -> ReflectValue {
+        ReflectValue::Float(self.as_secs_f64())
+    }
+
+    fn set_value(&mut self, value: ReflectValue) -> Result<()> {
+        let mut secs = 0.0f64;
+        secs.set_value(value)?;
+        *self = Duration::try_from_secs_f64(secs)?;
+        Ok(())
+    }
+}
+
+impl<T: Reflect> Reflect for Option<T> {
+    fn field_names(&self) -> Vec<String> {
+        match self {
+            Some(_) => vec!["0".to_string()],
+            None => vec![],
+        }
+    }
+
+    fn field(&self, name: &str) -> Option<&dyn Reflect> {
+        match (self, name) {
+            (Some(v), "0") => Some(v),
+            _ => None,
+        }
+    }
+
+    fn field_mut(&mut self, name: &str) -> Option<&mut dyn Reflect> {
+        match (self, name) {
+            (Some(v), "0") => Some(v),
+            _ => None,
+        }
+    }
+
+    fn value(&self) -> ReflectValue {
+        match self {
+            Some(v) => v.value(),
+            None => ReflectValue::Opaque("None".to_string()),
+        }
+    }
+
+    fn set_value(&mut self, value: ReflectValue) -> Result<()> {
+        match self {
+            Some(v) => v.set_value(value),
+            None => Err(anyhow!("Can't set a value inside of None")),
+        }
+    }
+}
+
+/// Shared by arrays and `Vec`s, fields are the indices.
+fn list_field_names(len: usize) -> Vec<String> {
+    (0..len).map(|i| i.to_string()).collect()
+}
+
+fn set_list<T: Reflect>(items: &mut [T], value: ReflectValue) -> Result<()> {
+    let ReflectValue::List(values) = value else {
+        return Err(mismatch::<[T]>(&value));
+    };
+    if values.len() != items.len() {
+        return Err(anyhow!(
+            "Expected {} values but got {}",
+            items.len(),
+            values.len()
+        ));
+    }
+    for (item, value) in items.iter_mut().zip(values) {
+        item.set_value(value)?;
+    }
+    Ok(())
+}
+
+impl<T: Reflect, const N: usize> Reflect for [T; N] {
+    fn field_names(&self) -> Vec<String> {
+        list_field_names(N)
+    }
+
+    fn field(&self, name: &str) -> Option<&dyn Reflect> {
+        Some(self.get(name.parse::<usize>().ok()?)?)
+    }
+
+    fn field_mut(&mut self, name: &str) -> Option<&mut dyn Reflect> {
+        Some(self.get_mut(name.parse::<usize>().ok()?)?)
+    }
+
+    fn value(&self) -> ReflectValue {
+        ReflectValue::List(self.iter().map(|v| v.value()).collect())
+    }
+
+    fn set_value(&mut self, value: ReflectValue) -> Result<()> {
+        set_list(self, value)
+    }
+}
+
+impl<T: Reflect> Reflect for Vec<T> {
+    fn field_names(&self) -> Vec<String> {
+        list_field_names(self.len())
+    }
+
+    fn field(&self, name: &str) -> Option<&dyn Reflect> {
+        Some(self.get(name.parse::<usize>().ok()?)?)
+    }
+
+    fn field_mut(&mut self, name: &str) -> Option<&mut dyn Reflect> {
+        Some(self.get_mut(name.parse::<usize>().ok()?)?)
+    }
+
+    fn value(&self) -> ReflectValue {
+        ReflectValue::List(self.iter().map(|v| v.value()).collect())
+    }
+
+    fn set_value(&mut self, value: ReflectValue) -> Result<()> {
+        set_list(self, value)
+    }
+}
+
+/// Implement [`Reflect`] for a struct by listing the fields to expose.
+/// Every listed field has to be [`Reflect`] too, fields left out are hidden. Fields after
+/// `read_only` can be looked at but not set, setting the whole struct skips them.
+///
+/// # Example
+/// ```rs
+/// impl_reflect!(MovementData { up, down, left, right });
+/// impl_reflect!(GridPosition(0, 1));
+/// impl_reflect!(Machine { speed } read_only { items_made });
+/// ```
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __impl_reflect {
+    ($ty:ident { $($field:ident),* $(,)? } read_only { $($read_only:ident),* $(,)? }) => {
+        $crate::ecs::reflect::impl_reflect!(@impl $ty, [$($field),*], [$($read_only),*]);
+    };
+    ($ty:ident { $($field:ident),* $(,)? }) => {
+        $crate::ecs::reflect::impl_reflect!(@impl $ty, [$($field),*], []);
+    };
+    ($ty:ident ( $($field:tt),* $(,)? )) => {
+        $crate::ecs::reflect::impl_reflect!(@impl $ty, [$($field),*], []);
+    };
+    (@impl $ty:ident, [$($field:tt),*], [$($read_only:tt),*]) => {
+        impl $crate::ecs::reflect::Reflect for $ty {
+            fn field_names(&self) -> Vec<String> {
+                vec![
+                    $(stringify!($field).to_string(),)*
+                    $(stringify!($read_only).to_string(),)*
+                ]
+            }
+
+            fn field(&self, name: &str) -> Option<&dyn $crate::ecs::reflect::Reflect> {
+                match name {
+                    $(stringify!($field) => Some(&self.$field),)*
+                    $(stringify!($read_only) => Some(&self.$read_only),)*
+                    _ => None,
+                }
+            }
+
+            fn field_mut(&mut self, name: &str) -> Option<&mut dyn $crate::ecs::reflect::Reflect> {
+                match name {
+                    $(stringify!($field) => Some(&mut self.$field),)*
+                    _ => None,
+                }
+            }
+
+            fn is_read_only(&self, name: &str) -> bool {
+                [$(stringify!($read_only)),*].contains(&name)
+            }
+
+            fn value(&self) -> $crate::ecs::reflect::ReflectValue {
+                $crate::ecs::reflect::ReflectValue::Struct(vec![
+                    $((
+                        stringify!($field).to_string(),
+                        $crate::ecs::reflect::Reflect::value(&self.$field),
+                    ),)*
+                    $((
+                        stringify!($read_only).to_string(),
+                        $crate::ecs::reflect::Reflect::value(&self.$read_only),
+                    ),)*
+                ])
+            }
+
+            fn set_value(&mut self, value: $crate::ecs::reflect::ReflectValue) -> anyhow::Result<()> {
+                let $crate::ecs::reflect::ReflectValue::Struct(fields) = value else {
+                    return Err(anyhow::anyhow!("Can't set {} to {value}", stringify!($ty)));
+                };
+                for (name, value) in fields {
+                    if $crate::ecs::reflect::Reflect::is_read_only(self, &name) {
+                        continue;
+                    }
+                    $crate::ecs::reflect::Reflect::field_mut(self, &name)
+                        .ok_or_else(|| anyhow::anyhow!("{} has no field \"{name}\"", stringify!($ty)))?
+                        .set_value(value)?;
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+pub use crate::__impl_reflect as impl_reflect;
+
+/// Reflection access to whatever is in the [`World`].
+///
+/// Only types registered with
+/// [`TypeRegistry::register_reflect`](crate::ecs::registry::TypeRegistry::register_reflect)
+/// can be looked at.
+impl World {
+    /// Every resource type in the world, registered or not.
+    pub fn resource_type_ids(&self) -> Vec<TypeId> {
+        self.resources.keys().copied().collect()
+    }
+
+    /// Every component type `entity` has, registered or not.
+    pub fn component_type_ids(&self, entity: Entity) -> Vec<TypeId> {
+        self.components
+            .iter()
+            .filter(|(_, storage)| storage.contains_key(&entity))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Run `f` on the resource with this [`TypeId`].
+    /// `None` if there's no such resource or it isn't registered for reflection.
+    pub fn reflect_resource<R>(&self, id: TypeId, f: impl FnOnce(&dyn Reflect) -> R) -> Option<R> {
+        let registration = self.type_registry().get(id)?.clone();
+        let resource = self.resources.get(&id)?.read().ok()?;
+        Some(f(registration.as_reflect(&**resource)?))
+    }
+
+    pub fn reflect_resource_mut<R>(
+        &self,
+        id: TypeId,
+        f: impl FnOnce(&mut dyn Reflect) -> R,
+    ) -> Option<R> {
+        let registration = self.type_registry().get(id)?.clone();
+        let mut resource = self.resources.get(&id)?.write().ok()?;
+        Some(f(registration.as_reflect_mut(&mut **resource)?))
+    }
+
+    /// Run `f` on the component of `entity` with this [`TypeId`].
+    /// `None` if there's no such component or it isn't registered for reflection.
+    pub fn reflect_component<R>(
+        &self,
+        entity: Entity,
+        id: TypeId,
+        f: impl FnOnce(&dyn Reflect) -> R,
+    ) -> Option<R> {
+        let registration = self.type_registry().get(id)?.clone();
+        let component = self.components.get(&id)?.get(&entity)?.read().ok()?;
+        Some(f(registration.as_reflect(&**component)?))
+    }
+
+    /// Only counts as a change for [`World::track_changes`] when `f` actually changed the value.
+    pub fn reflect_component_mut<R>(
+        &self,
+        entity: Entity,
+        id: TypeId,
+        f: impl FnOnce(&mut dyn Reflect) -> R,
+    ) -> Option<R> {
+        let registration = self.type_registry().get(id)?.clone();
+        let mut component = self.components.get(&id)?.get(&entity)?.write().ok()?;
+        let reflect = registration.as_reflect_mut(&mut **component)?;
+
+        let before = reflect.value();
+        let result = f(reflect);
+        if reflect.value() != before {
+            self.mark_changed(id, entity);
+        }
+        Some(result)
+    }
+
+    fn type_id_by_name(&self, name: &str) -> Result<TypeId> {
+        self.type_registry()
+            .get_by_name(name)
+            .filter(|r| r.is_reflectable())
+            .map(|r| r.type_id)
+            .ok_or_else(|| anyhow!("No type called \"{name}\" is registered for reflection"))
+    }
+
+    /// Read a field of a resource by the name it was registered under, e.g.
+    /// `world.get_resource_path("time", "time_scale")`.
+    pub fn get_resource_path(&self, name: &str, path: &str) -> Result<ReflectValue> {
+        self.reflect_resource(self.type_id_by_name(name)?, |r| r.get_path(path))
+            .ok_or_else(|| anyhow!("There's no {name} resource"))?
+    }
+
+    /// Parse `value` and write it into a field of a resource.
+    pub fn set_resource_path(&self, name: &str, path: &str, value: &str) -> Result<()> {
+        self.reflect_resource_mut(self.type_id_by_name(name)?, |r| r.set_path_str(path, value))
+            .ok_or_else(|| anyhow!("There's no {name} resource"))?
+    }
+
+    pub fn get_component_path(
+        &self,
+        entity: Entity,
+        name: &str,
+        path: &str,
+    ) -> Result<ReflectValue> {
+        self.reflect_component(entity, self.type_id_by_name(name)?, |r| r.get_path(path))
+            .ok_or_else(|| anyhow!("{} has no {name} component", self.label(entity)))?
+    }
+
+    pub fn set_component_path(
+        &self,
+        entity: Entity,
+        name: &str,
+        path: &str,
+        value: &str,
+    ) -> Result<()> {
+        self.reflect_component_mut(entity, self.type_id_by_name(name)?, |r| {
+            r.set_path_str(path, value)
+        })
+        .ok_or_else(|| anyhow!("{} has no {name} component", self.label(entity)))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::spatial::GridPosition;
+
+    #[derive(Debug, PartialEq)]
+    struct Engine {
+        speed: f32,
+        name: String,
+    }
+
+    impl_reflect!(Engine { speed, name });
+
+    #[derive(Debug, PartialEq)]
+    struct Train {
+        engine: Engine,
+        cars: [u8; 2],
+        following: Option<Entity>,
+        miles: u32,
+    }
+
+    impl_reflect!(Train { engine, cars, following } read_only { miles });
+
+    fn train() -> Train {
+        Train {
+            engine: Engine {
+                speed: 1.5,
+                name: "Puffer".to_string(),
+            },
+            cars: [3, 4],
+            following: None,
+            miles: 10,
+        }
+    }
+
+    #[test]
+    fn paths_go_through_nested_fields() {
+        let mut train = train();
+        let reflect: &mut dyn Reflect = &mut train;
+
+        assert_eq!(
+            reflect.get_path("engine.speed").unwrap(),
+            ReflectValue::Float(1.5)
+        );
+        assert_eq!(reflect.get_path("cars.1").unwrap(), ReflectValue::UInt(4));
+        assert_eq!(reflect.get_path("miles").unwrap(), ReflectValue::UInt(10));
+        assert!(reflect.path("engine.wheels").is_err());
+        assert!(reflect.path("cars.2").is_err());
+
+        reflect
+            .path_mut("engine.name")
+            .unwrap()
+            .set_value(ReflectValue::String("Chuffer".to_string()))
+            .unwrap();
+        reflect.set_path("cars.0", ReflectValue::Int(7)).unwrap();
+        assert_eq!(train.engine.name, "Chuffer");
+        assert_eq!(train.cars, [7, 4]);
+    }
+
+    #[test]
+    fn read_only_fields_cant_be_changed() {
+        let mut train = Train {
+            following: Some(Entity(0)),
+            ..train()
+        };
+        let reflect: &mut dyn Reflect = &mut train;
+
+        assert!(reflect.path_mut("miles").is_err());
+        assert!(reflect.set_path_str("miles", "20").is_err());
+
+        // Setting the whole struct skips them
+        let mut value = reflect.value();
+        let ReflectValue::Struct(fields) = &mut value else {
+            panic!("{value} isn't a struct");
+        };
+        for (name, value) in fields.iter_mut() {
+            if name == "miles" {
+                *value = ReflectValue::UInt(20);
+            }
+        }
+        reflect.set_value(value).unwrap();
+        assert_eq!(train.miles, 10);
+    }
+
+    #[test]
+    fn set_path_str_parses_like_the_field() {
+        let mut world = World::new();
+        let entity = world.spawn();
+        let mut train = train();
+        train.following = Some(Entity(0));
+        let reflect: &mut dyn Reflect = &mut train;
+
+        reflect.set_path_str("engine.speed", " 2.5 ").unwrap();
+        reflect.set_path_str("engine.name", "Chuffer").unwrap();
+        reflect.set_path_str("cars.1", "9").unwrap();
+        reflect
+            .set_path_str("following.0", &entity.0.to_string())
+            .unwrap();
+
+        assert_eq!(train.engine.speed, 2.5);
+        assert_eq!(train.engine.name, "Chuffer");
+        assert_eq!(train.cars, [3, 9]);
+        assert_eq!(train.following, Some(entity));
+    }
+
+    #[test]
+    fn parse_like_matches_each_kind() {
+        let cases = [
+            (ReflectValue::Bool(false), "true", ReflectValue::Bool(true)),
+            (ReflectValue::Int(0), "-3", ReflectValue::Int(-3)),
+            (ReflectValue::UInt(0), "3", ReflectValue::UInt(3)),
+            (ReflectValue::Float(0.0), "0.25", ReflectValue::Float(0.25)),
+            (
+                ReflectValue::String(String::new()),
+                "mill",
+                ReflectValue::String("mill".to_string()),
+            ),
+            (
+                ReflectValue::Entity(Entity(0)),
+                "5",
+                ReflectValue::Entity(Entity(5)),
+            ),
+        ];
+        for (like, s, expected) in cases {
+            assert_eq!(like.parse_like(s).unwrap(), expected);
+        }
+
+        for (like, s) in [
+            (ReflectValue::Bool(false), "yes"),
+            (ReflectValue::Int(0), "1.5"),
+            (ReflectValue::UInt(0), "-1"),
+            (ReflectValue::Float(0.0), "fast"),
+            (ReflectValue::Entity(Entity(0)), "mill"),
+        ] {
+            assert!(like.parse_like(s).is_err(), "parsed {s:?} like {like}");
+        }
+    }
+
+    #[test]
+    fn parse_like_rejects_values_that_arent_plain() {
+        for like in [
+            ReflectValue::Struct(vec![]),
+            ReflectValue::List(vec![]),
+            ReflectValue::Opaque("None".to_string()),
+        ] {
+            assert!(like.parse_like("1").is_err(), "parsed into {like}");
+        }
+        // Which is what a struct field gets
+        assert!(
+            (&mut train() as &mut dyn Reflect)
+                .set_path_str("engine", "1")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn set_value_rejects_other_kinds() {
+        let mut train = train();
+        let reflect: &mut dyn Reflect = &mut train;
+
+        assert!(
+            reflect
+                .set_path("engine.speed", ReflectValue::Bool(true))
+                .is_err()
+        );
+        assert!(
+            reflect
+                .set_path("engine.name", ReflectValue::Float(1.0))
+                .is_err()
+        );
+        assert!(reflect.set_path("cars.0", ReflectValue::Int(-1)).is_err());
+        assert!(
+            reflect
+                .set_path("cars.0", ReflectValue::Float(0.5))
+                .is_err()
+        );
+        assert!(
+            reflect
+                .set_path("cars", ReflectValue::List(vec![ReflectValue::UInt(1)]))
+                .is_err()
+        );
+        assert!(reflect.set_value(ReflectValue::UInt(1)).is_err());
+        // Nothing inside of None to set
+        assert!(
+            reflect
+                .set_path("following", ReflectValue::Entity(Entity(0)))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn world_paths_report_whats_missing() {
+        let mut world = World::with_defaults();
+        world.register_reflect_type::<Train>("train");
+
+        assert!(world.set_resource_path("train", "cars.0", "1").is_err());
+        assert!(world.set_resource_path("boat", "cars.0", "1").is_err());
+
+        world.add_resource(train());
+        world.set_resource_path("train", "cars.0", "1").unwrap();
+        assert_eq!(world.get_resource::<Train>().cars, [1, 4]);
+        assert!(world.set_resource_path("train", "wheels", "1").is_err());
+
+        let without = world.spawn();
+        let gone = world.spawn_bundle((GridPosition::new(1, 2),));
+        world.despawn(gone);
+        for entity in [without, gone] {
+            assert!(
+                world
+                    .get_component_path(entity, "grid_position", "x")
+                    .is_err()
+            );
+            assert!(
+                world
+                    .set_component_path(entity, "grid_position", "x", "3")
+                    .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn only_writes_count_as_changes() {
+        let mut world = World::with_defaults();
+        let entity = world.spawn_bundle((GridPosition::new(1, 2),));
+        let reader = world.track_changes::<GridPosition>();
+        world.read_changes(&reader);
+
+        let id = TypeId::of::<GridPosition>();
+        world.reflect_component_mut(entity, id, |r| r.value());
+        world
+            .set_component_path(entity, "grid_position", "x", "1")
+            .unwrap();
+        assert!(world.read_changes(&reader).is_empty());
+
+        world
+            .set_component_path(entity, "grid_position", "x", "3")
+            .unwrap();
+        assert_eq!(world.read_changes(&reader).changed, vec![entity]);
+        assert_eq!(
+            world
+                .get_component_path(entity, "grid_position", "x")
+                .unwrap(),
+            ReflectValue::Int(3)
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use std::{
+    any::{Any, TypeId, type_name},
+    collections::HashMap,
+};
+
+use anyhow::{Result, anyhow};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::ecs::{
+    entity::{EntityMap, MapEntities},
+    reflect::Reflect,
+};
+
+/// How to (de)serialize a registered type.
+#[derive(Clone, Copy)]
+struct SerdeFns {
+    serialize: fn(&dyn Any) -> Result<Vec<u8>>,
+    deserialize: fn(&[u8]) -> Result<Box<dyn Any>>,
+    from_ron: fn(&str) -> Result<Box<dyn Any>>,
+    map_entities: Option<fn(&mut dyn Any, &EntityMap)>,
+}
+
+/// How to get a [`Reflect`] out of a registered type.
+#[derive(Clone, Copy)]
+struct ReflectFns {
+    as_reflect: fn(&dyn Any) -> Option<&dyn Reflect>,
+    as_reflect_mut: fn(&mut dyn Any) -> Option<&mut dyn Reflect>,
+}
+
+/// Everything the [`World`](crate::ecs::World) knows about a registered type.
+///
+/// The functions work on `dyn Any` since that's how the world stores things.
+#[derive(Clone)]
+pub struct TypeRegistration {
+    /// Stable name of the type, this is what ends up in save files and what the inspector shows.
+    /// Don't change it once there are saves using it.
+    pub name: &'static str,
+    pub type_id: TypeId,
+    pub type_name: &'static str,
+    serde: Option<SerdeFns>,
+    reflect: Option<ReflectFns>,
+}
+
+impl TypeRegistration {
+    /// Whether the type was registered with [`TypeRegistry::register`] and so ends up in snapshots.
+    pub fn is_serializable(&self) -> bool {
+        self.serde.is_some()
+    }
+
+    pub fn is_reflectable(&self) -> bool {
+        self.reflect.is_some()
+    }
+
+    pub fn serialize(&self, value: &dyn Any) -> Result<Vec<u8>> {
+        let serde = self
+            .serde
+            .ok_or_else(|| anyhow!("{} isn't registered for serialization", self.name))?;
+        (serde.serialize)(value)
+    }
+
+    pub fn deserialize(&self, bytes: &[u8]) -> Result<Box<dyn Any>> {
+        let serde = self
+            .serde
+            .ok_or_else(|| anyhow!("{} isn't registered for serialization", self.name))?;
+        (serde.deserialize)(bytes)
+    }
+
+    /// Parse a value written by hand in RON, this is how prefabs get their components.
+    pub fn from_ron(&self, ron: &str) -> Result<Box<dyn Any>> {
+        let serde = self
+            .serde
+            .ok_or_else(|| anyhow!("{} isn't registered for serialization", self.name))?;
+        (serde.from_ron)(ron)
+    }
+
+    /// Remap any [`Entity`](crate::ecs::entity::Entity) held by `value`.
+    /// Does nothing for types registered without [`MapEntities`].
+    pub fn map_entities(&self, value: &mut dyn Any, map: &EntityMap) {
+        if let Some(map_entities) = self.serde.and_then(|serde| serde.map_entities) {
+            map_entities(value, map);
+        }
+    }
+
+    /// View `value` through [`Reflect`]. `None` if the type wasn't registered with
+    /// [`TypeRegistry::register_reflect`] or `value` isn't this type.
+    pub fn as_reflect<'a>(&self, value: &'a dyn Any) -> Option<&'a dyn Reflect> {
+        (self.reflect?.as_reflect)(value)
+    }
+
+    pub fn as_reflect_mut<'a>(&self, value: &'a mut dyn Any) -> Option<&'a mut dyn Reflect> {
+        (self.reflect?.as_reflect_mut)(value)
+    }
+}
+
+/// List of component and resource types the [`World`](crate::ecs::World) knows more about than
+/// just their [`TypeId`].
+///
+/// Types opt in to each capability on their own:
+/// - [`Self::register`] puts the type in snapshots and lets prefabs use it.
+/// - [`Self::register_reflect`] lets the inspector and console look inside of it.
+///
+/// # Example
+/// ```rs
+/// #[derive(Serialize, Deserialize)]
+/// struct Health(u32);
+/// impl_reflect!(Health(0));
+///
+/// Manager::new()?
+///     .register_type::<Health>("health")
+///     .register_reflect_type::<Health>("health");
+/// ```
+#[derive(Clone, Default)]
+pub struct TypeRegistry {
+    registrations: HashMap<TypeId, TypeRegistration>,
+    names: HashMap<&'static str, TypeId>,
+}
+
+impl TypeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `T` for serialization under `name`.
+    pub fn register<T: Any + Serialize + DeserializeOwned>(&mut self, name: &'static str) {
+        self.entry::<T>(name).serde = Some(SerdeFns {
+            serialize: serialize::<T>,
+            deserialize: deserialize::<T>,
+            from_ron: from_ron::<T>,
+            map_entities: None,
+        });
+    }
+
+    /// Same as [`Self::register`] but for types holding onto entities.
+    pub fn register_mapped<T: Any + Serialize + DeserializeOwned + MapEntities>(
+        &mut self,
+        name: &'static str,
+    ) {
+        self.entry::<T>(name).serde = Some(SerdeFns {
+            serialize: serialize::<T>,
+            deserialize: deserialize::<T>,
+            from_ron: from_ron::<T>,
+            map_entities: Some(map_entities::<T>),
+        });
+    }
+
+    /// Register `T` for reflection under `name`.
+    pub fn register_reflect<T: Reflect>(&mut self, name: &'static str) {
+        self.entry::<T>(name).reflect = Some(ReflectFns {
+            as_reflect: as_reflect::<T>,
+            as_reflect_mut: as_reflect_mut::<T>,
+        });
+    }
+
+    /// Get the registration for `T`, making an empty one if there isn't one yet.
+    ///
+    /// # Panics
+    /// Panics if `name` is taken by another type or `T` is already registered under another name.
+    fn entry<T: Any>(&mut self, name: &'static str) -> &mut TypeRegistration {
+        self.insert(TypeRegistration {
+            name,
+            type_id: TypeId::of::<T>(),
+            type_name: type_name::<T>(),
+            serde: None,
+            reflect: None,
+        });
+
+        self.registrations.get_mut(&TypeId::of::<T>()).unwrap()
+    }
+
+    /// Add a registration, merging it into the existing one for that type.
+    fn insert(&mut self, registration: TypeRegistration) {
+        if let Some(old) = self.names.get(registration.name)
+            && *old != registration.type_id
+        {
+            panic!(
+                "Type name \"{}\" is already used by {}",
+                registration.name, self.registrations[old].type_name
+            );
+        }
+
+        match self.registrations.get_mut(&registration.type_id) {
+            Some(existing) => {
+                assert_eq!(
+                    existing.name, registration.name,
+                    "{} is already registered under another name",
+                    registration.type_name
+                );
+                existing.serde = registration.serde.or(existing.serde);
+                existing.reflect = registration.reflect.or(existing.reflect);
+            }
+            None => {
+                self.names.insert(registration.name, registration.type_id);
+                self.registrations
+                    .insert(registration.type_id, registration);
+            }
+        }
+    }
+
+    /// Add everything from `other` to this registry.
+    pub fn extend(&mut self, other: TypeRegistry) {
+        for (_, registration) in other.registrations {
+            self.insert(registration);
+        }
+    }
+
+    pub fn get(&self, type_id: TypeId) -> Option<&TypeRegistration> {
+        self.registrations.get(&type_id)
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<&TypeRegistration> {
+        self.registrations.get(self.names.get(name)?)
+    }
+
+    pub fn contains(&self, type_id: TypeId) -> bool {
+        self.registrations.contains_key(&type_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &TypeRegistration> {
+        self.registrations.values()
+    }
+}
+
+fn serialize<T: Any + Serialize>(value: &dyn Any) -> Result<Vec<u8>> {
+    let value = value
+        .downcast_ref::<T>()
+        .ok_or_else(|| anyhow!("Expected a {}", type_name::<T>()))?;
+    Ok(bincode::serialize(value)?)
+}
+
+fn deserialize<T: Any + DeserializeOwned>(bytes: &[u8]) -> Result<Box<dyn Any>> {
+    Ok(Box::new(bincode::deserialize::<T>(bytes)?))
+}
+
+fn from_ron<T: Any + DeserializeOwned>(ron: &str) -> Result<Box<dyn Any>> {
+    Ok(Box::new(ron::from_str::<T>(ron)?))
+}
+
+fn map_entities<T: Any + MapEntities>(value: &mut dyn Any, map: &EntityMap) {
+    if let Some(value) = value.downcast_mut::<T>() {
+        value.map_entities(map);
+    }
+}
+
+fn as_reflect<T: Reflect>(value: &dyn Any) -> Option<&dyn Reflect> {
+    Some(value.downcast_ref::<T>()? as &dyn Reflect)
+}
+
+fn as_reflect_mut<T: Reflect>(value: &mut dyn Any) -> Option<&mut dyn Reflect> {
+    Some(value.downcast_mut::<T>()? as &mut dyn Reflect)
+}
@@ -0,0 +1,284 @@
+//! Recording player input to a file and playing it back.
+//!
+//! A recording has the seed of [`GameRng`], the fixed timestep and then for every frame how long
+//! it took and the input that came in during it. Replaying feeds the same input in on the same
+//! frames with the same deltas, so as long as gameplay only reads input through input systems and
+//! randomness through [`GameRng`], the replay ends up exactly where the recording did.
+//!
+//! ```sh
+//! gristmill --record bug.rec
+//! gristmill --replay bug.rec
+//! gristmill --replay bug.rec --headless
+//! ```
+//!
+//! # Format
+//! The magic bytes `GMRC`, a little endian `u32` version and then a bincode encoded [`Recording`].
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow};
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::{
+    World,
+    input::InputEvent,
+    rng::{GameRng, reseed_worlds},
+    time::Time,
+    worlds::SubWorld,
+};
+
+const RECORDING_MAGIC: &[u8; 4] = b"GMRC";
+
+/// Bump this whenever [`Recording`] or [`InputEvent`] change shape.
+pub const RECORDING_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    /// [`Time::frame_count`] when the frame started.
+    pub frame: u64,
+    /// [`Time::fixed_tick_count`] when the frame started, used to notice replays going off track.
+    pub tick: u64,
+    /// Real time the frame took.
+    pub delta: Duration,
+    pub inputs: Vec<InputEvent>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Recording {
+    pub seed: u64,
+    pub fixed_timestep: Duration,
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl Recording {
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(RECORDING_MAGIC)?;
+        writer.write_all(&RECORDING_VERSION.to_le_bytes())?;
+        bincode::serialize_into(&mut writer, self)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn load<R: Read>(mut reader: R) -> Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != RECORDING_MAGIC {
+            return Err(anyhow!("Not an input recording."));
+        }
+
+        let mut version = [0; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != RECORDING_VERSION {
+            return Err(anyhow!(
+                "Recording is version {version} but only version {RECORDING_VERSION} is supported."
+            ));
+        }
+
+        Ok(bincode::deserialize_from(reader)?)
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.save(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::load(BufReader::new(File::open(path)?))
+    }
+}
+
+/// Where [`Manager`](crate::ecs::Manager) gets its input from.
+#[derive(Default)]
+pub enum InputMode {
+    /// Straight from the window.
+    #[default]
+    Live,
+    /// From the window, while writing it all down to be saved to `path` on exit.
+    Recording {
+        path: PathBuf,
+        recording: Option<Recording>,
+    },
+    /// From a recording, the window's input is ignored.
+    Replaying {
+        recording: Recording,
+        next_frame: usize,
+    },
+}
+
+impl InputMode {
+    pub fn is_replaying(&self) -> bool {
+        matches!(self, InputMode::Replaying { .. })
+    }
+
+    /// Whether a replay has played back every frame it has.
+    pub fn is_finished(&self) -> bool {
+        match self {
+            InputMode::Replaying {
+                recording,
+                next_frame,
+            } => *next_frame >= recording.frames.len(),
+            _ => false,
+        }
+    }
+
+    /// Set up `world` and the `sub_worlds` the way the recording started out, call before anything
+    /// runs.
+    pub(crate) fn start(&mut self, world: &World, sub_worlds: &[SubWorld]) {
+        match self {
+            InputMode::Live => {}
+            InputMode::Recording { path, recording } => {
+                info!("Recording input to {}", path.display());
+                *recording = Some(Recording {
+                    seed: world.get_resource::<GameRng>().seed(),
+                    fixed_timestep: world.get_resource::<Time>().fixed_timestep(),
+                    frames: vec![],
+                });
+            }
+            InputMode::Replaying { recording, .. } => {
+                info!("Replaying {} frames of input", recording.frames.len());
+                // Sub worlds are seeded from the main world when they're added, which was before
+                // the recorded seed was known
+                reseed_worlds(world, sub_worlds, recording.seed);
+                let worlds = std::iter::once(world).chain(sub_worlds.iter().map(|w| &w.world));
+                for world in worlds {
+                    world
+                        .get_resource_mut::<Time>()
+                        .set_fixed_timestep(recording.fixed_timestep);
+                }
+            }
+        }
+    }
+
+    /// The input for the next frame and, when replaying, the delta it has to use.
+    ///
+    /// Returns `None` once a replay has run out of frames.
+    pub(crate) fn next_frame(
+        &mut self,
+        world: &World,
+        live_inputs: Vec<InputEvent>,
+    ) -> Option<(Vec<InputEvent>, Option<Duration>)> {
+        let InputMode::Replaying {
+            recording,
+            next_frame,
+        } = self
+        else {
+            return Some((live_inputs, None));
+        };
+
+        let frame = recording.frames.get(*next_frame)?;
+        *next_frame += 1;
+
+        let tick = world.get_resource::<Time>().fixed_tick_count();
+        if frame.tick != tick {
+            warn!(
+                "Replay is out of sync on frame {}: recorded at fixed tick {} but is at {tick}",
+                frame.frame, frame.tick
+            );
+        }
+
+        Some((frame.inputs.clone(), Some(frame.delta)))
+    }
+
+    /// Write down a finished frame. `tick` is the fixed tick count from before the frame ran.
+    pub(crate) fn end_frame(&mut self, world: &World, tick: u64, inputs: Vec<InputEvent>) {
+        let InputMode::Recording {
+            recording: Some(recording),
+            ..
+        } = self
+        else {
+            return;
+        };
+
+        let time = world.get_resource::<Time>();
+        recording.frames.push(RecordedFrame {
+            // `frame_count` has already gone up for this frame
+            frame: time.frame_count() - 1,
+            tick,
+            delta: time.real_delta(),
+            inputs,
+        });
+    }
+
+    /// Save the recording if there is one.
+    pub(crate) fn finish(&mut self) -> Result<()> {
+        let InputMode::Recording { path, recording } = self else {
+            return Ok(());
+        };
+        let Some(recording) = recording.take() else {
+            return Ok(());
+        };
+
+        recording.save_to_file(&*path)?;
+        info!(
+            "Saved {} frames of input to {}",
+            recording.frames.len(),
+            path.display()
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, thread};
+
+    use rand::RngCore;
+
+    use super::*;
+    use crate::ecs::{Manager, System, order_up::OrderUp, partial_manager::PartialManager};
+
+    /// Every number the sim world drew, one a fixed tick.
+    struct Draws(Vec<u64>);
+
+    fn draw(world: &World) -> Result<()> {
+        let number = world
+            .get_resource_mut::<GameRng>()
+            .stream("draw")
+            .next_u64();
+        world.get_resource_mut::<Draws>().0.push(number);
+        Ok(())
+    }
+
+    /// Runs 10 frames with a sim world in `mode`, both worlds starting from `seed`. Returns what
+    /// the sim world drew.
+    fn run_session(seed: u64, mode: impl FnOnce(Manager) -> Result<Manager>) -> Vec<u64> {
+        let manager = Manager::new().unwrap();
+        **manager.world.get_resource_mut::<GameRng>() = GameRng::new(seed);
+        let sim = PartialManager::new()
+            .add_resource(Draws(vec![]))
+            .add_fixed_systems((draw as System,).order_up());
+        let mut manager = mode(manager.add_world("sim", sim).unwrap()).unwrap();
+
+        manager.input_mode.start(&manager.world, &manager.worlds);
+        for _ in 0..10 {
+            manager.run_frame(vec![]).unwrap();
+            if !manager.input_mode.is_replaying() {
+                thread::sleep(Duration::from_millis(20));
+            }
+        }
+        manager.input_mode.finish().unwrap();
+
+        let sim = manager.get_world("sim").unwrap();
+        sim.get_resource::<Draws>().0.clone()
+    }
+
+    #[test]
+    fn replays_sub_worlds_like_they_were_recorded() {
+        let path = env::temp_dir().join(format!("gristmill-replay-{}.rec", std::process::id()));
+
+        let recorded = run_session(1, |manager| Ok(manager.record_input(&path)));
+        // Starting from another seed, the replay has to bring its own
+        let replayed = run_session(2, |manager| manager.replay_input(&path));
+        fs::remove_file(&path).unwrap();
+
+        assert!(!recorded.is_empty());
+        assert_eq!(recorded, replayed);
+    }
+}
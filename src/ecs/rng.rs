@@ -0,0 +1,236 @@
+//! Randomness that can be replayed.
+//!
+//! Everything random in the game should come out of [`GameRng`] so a seed is enough to get the
+//! exact same map and the exact same bug behaviour again. The seed comes from the engine config or
+//! `--seed`, is logged at startup and saved in snapshots.
+//!
+//! Each system (or entity) asks for its own named stream, streams don't affect each other, so
+//! adding a new system that rolls dice doesn't change what the map generator sees. Every world
+//! has its own rng, seeded from the main world's seed and its name.
+
+use std::collections::HashMap;
+
+use log::*;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::{World, entity::Entity, worlds::SubWorld};
+
+/// The random number generators of the [`World`](crate::ecs::World).
+///
+/// # Example
+/// ```rs
+/// use rand::Rng;
+///
+/// let mut rng = world.get_resource_mut::<GameRng>();
+/// let width = rng.stream("map_gen").gen_range(32..64);
+/// let wander = rng.entity_stream("bug_ai", bug).gen_bool(0.1);
+/// ```
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "SavedRng", into = "SavedRng")]
+pub struct GameRng {
+    seed: u64,
+    streams: HashMap<u64, ChaCha8Rng>,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            streams: HashMap::new(),
+        }
+    }
+
+    /// Start from `seed`, or from a random one without it. Either way it's logged so the run can
+    /// be repeated.
+    pub fn from_seed(seed: Option<u64>) -> Self {
+        let seed = seed.unwrap_or_else(rand::random);
+        info!("Random seed: {seed} (pass --seed {seed} to get the same run again)");
+        Self::new(seed)
+    }
+
+    /// The seed of the world called `world` when the main world starts from `seed`. Differs
+    /// between worlds, so a stream doesn't give the same numbers in all of them.
+    pub fn world_seed(seed: u64, world: &str) -> u64 {
+        mix(seed, stream_key(world))
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Throw away every stream and start over from `seed`.
+    pub fn reseed(&mut self, seed: u64) {
+        info!("Random seed changed to {seed}");
+        self.seed = seed;
+        self.streams.clear();
+    }
+
+    /// The stream called `name`, made the first time it's asked for.
+    ///
+    /// Use a name unique to the system, two systems sharing a stream will change each other's
+    /// numbers.
+    pub fn stream(&mut self, name: &str) -> &mut ChaCha8Rng {
+        self.stream_by_key(stream_key(name))
+    }
+
+    /// A stream for `entity` alone, so how many other entities there are doesn't matter.
+    ///
+    /// Entities get new ids when a save is loaded, so their streams start over after loading.
+    pub fn entity_stream(&mut self, name: &str, entity: Entity) -> &mut ChaCha8Rng {
+        self.stream_by_key(mix(stream_key(name), entity.id()))
+    }
+
+    fn stream_by_key(&mut self, key: u64) -> &mut ChaCha8Rng {
+        let seed = self.seed;
+        self.streams.entry(key).or_insert_with(|| {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            rng.set_stream(key);
+            rng
+        })
+    }
+
+    /// A new generator split off of the stream called `name`, for handing to code that wants
+    /// to own its rng, like a map generator running on another thread.
+    pub fn fork(&mut self, name: &str) -> ChaCha8Rng {
+        ChaCha8Rng::seed_from_u64(self.stream(name).next_u64())
+    }
+}
+
+/// Start the rng of `world` over from `seed`, and the rng of each of `sub_worlds` from its
+/// [`GameRng::world_seed`].
+pub(crate) fn reseed_worlds(world: &World, sub_worlds: &[SubWorld], seed: u64) {
+    world.get_resource_mut::<GameRng>().reseed(seed);
+    for sub_world in sub_worlds {
+        sub_world
+            .world
+            .get_resource_mut::<GameRng>()
+            .reseed(GameRng::world_seed(seed, &sub_world.name));
+    }
+}
+
+/// What's in a save file, the seed and how far along each stream is.
+#[derive(Serialize, Deserialize)]
+struct SavedRng {
+    seed: u64,
+    streams: Vec<(u64, u128)>,
+}
+
+impl From<GameRng> for SavedRng {
+    fn from(rng: GameRng) -> Self {
+        let mut streams: Vec<(u64, u128)> = rng
+            .streams
+            .iter()
+            .map(|(key, stream)| (*key, stream.get_word_pos()))
+            .collect();
+        streams.sort();
+
+        Self {
+            seed: rng.seed,
+            streams,
+        }
+    }
+}
+
+impl From<SavedRng> for GameRng {
+    fn from(saved: SavedRng) -> Self {
+        let mut rng = GameRng::new(saved.seed);
+        for (key, word_pos) in saved.streams {
+            rng.stream_by_key(key).set_word_pos(word_pos);
+        }
+        rng
+    }
+}
+
+/// FNV-1a, stream keys end up in save files so this can't be `DefaultHasher` which is allowed
+/// to change between Rust versions.
+fn stream_key(name: &str) -> u64 {
+    name.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Combine two keys into one, the finalizer of SplitMix64.
+fn mix(a: u64, b: u64) -> u64 {
+    let mut z = a ^ b.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{Manager, partial_manager::PartialManager};
+
+    fn draw(rng: &mut GameRng, name: &str, count: usize) -> Vec<u64> {
+        (0..count).map(|_| rng.stream(name).next_u64()).collect()
+    }
+
+    #[test]
+    fn streams_dont_affect_each_other() {
+        let mut untouched = GameRng::new(7);
+        let mut drawn_from = GameRng::new(7);
+
+        draw(&mut drawn_from, "a", 100);
+
+        assert_eq!(
+            draw(&mut drawn_from, "b", 10),
+            draw(&mut untouched, "b", 10)
+        );
+    }
+
+    #[test]
+    fn same_seed_and_name_give_the_same_numbers() {
+        let mut first = GameRng::new(7);
+        let mut second = GameRng::new(7);
+
+        assert_eq!(draw(&mut first, "a", 10), draw(&mut second, "a", 10));
+        assert_ne!(draw(&mut first, "b", 10), draw(&mut second, "c", 10));
+        assert_ne!(
+            draw(&mut GameRng::new(7), "a", 10),
+            draw(&mut GameRng::new(8), "a", 10)
+        );
+    }
+
+    #[test]
+    fn worlds_get_their_own_seed() {
+        let sim = GameRng::world_seed(7, "sim");
+
+        assert_eq!(sim, GameRng::world_seed(7, "sim"));
+        assert_ne!(sim, 7);
+        assert_ne!(sim, GameRng::world_seed(7, "ui"));
+        assert_ne!(sim, GameRng::world_seed(8, "sim"));
+    }
+
+    #[test]
+    fn with_seed_gives_every_world_its_own_numbers() {
+        let manager = Manager::new()
+            .unwrap()
+            .add_world("sim", PartialManager::new())
+            .unwrap()
+            .with_seed(Some(7));
+        let sim = manager.get_world("sim").unwrap();
+
+        assert_eq!(manager.world.get_resource::<GameRng>().seed(), 7);
+        assert_eq!(
+            sim.get_resource::<GameRng>().seed(),
+            GameRng::world_seed(7, "sim")
+        );
+        assert_ne!(
+            draw(&mut manager.world.get_resource_mut::<GameRng>(), "a", 10),
+            draw(&mut sim.get_resource_mut::<GameRng>(), "a", 10)
+        );
+    }
+
+    #[test]
+    fn saving_keeps_the_streams_where_they_were() {
+        let mut rng = GameRng::new(7);
+        draw(&mut rng, "a", 5);
+
+        let mut loaded: GameRng = bincode::deserialize(&bincode::serialize(&rng).unwrap()).unwrap();
+
+        assert_eq!(draw(&mut loaded, "a", 10), draw(&mut rng, "a", 10));
+    }
+}
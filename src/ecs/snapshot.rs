@@ -0,0 +1,310 @@
+//! Saving and loading the [`World`] for save games.
+//!
+//! Only types registered with [`TypeRegistry`](crate::ecs::registry::TypeRegistry) make it into a
+//! snapshot, everything else (the renderer, window handles, caches) is skipped.
+//!
+//! # Format
+//! A snapshot is the magic bytes `GMSV`, a little endian `u32` version and then a bincode
+//! encoded [`WorldSnapshot`].
+
+use std::{
+    io::{Read, Write},
+    rc::Rc,
+    sync::RwLock,
+};
+
+use anyhow::{Result, anyhow};
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::{
+    World,
+    entity::{Entity, EntityMap},
+};
+
+const SNAPSHOT_MAGIC: &[u8; 4] = b"GMSV";
+
+/// Bump this whenever [`WorldSnapshot`] changes shape.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// A single serialized value along with the registered name of its type.
+#[derive(Serialize, Deserialize)]
+pub struct SerializedValue {
+    pub type_name: String,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SerializedEntity {
+    pub entity: Entity,
+    pub components: Vec<SerializedValue>,
+}
+
+/// Everything saved out of a [`World`].
+#[derive(Serialize, Deserialize)]
+pub struct WorldSnapshot {
+    pub resources: Vec<SerializedValue>,
+    pub entities: Vec<SerializedEntity>,
+}
+
+impl World {
+    /// Take a snapshot of every registered resource and every entity with a registered component.
+    pub fn snapshot(&self) -> Result<WorldSnapshot> {
+        let registry = self.type_registry();
+
+        let mut resources = vec![];
+        for (id, resource) in self.resources.iter() {
+            let Some(registration) = registry.get(*id).filter(|r| r.is_serializable()) else {
+                continue;
+            };
+
+            resources.push(SerializedValue {
+                type_name: registration.name.to_string(),
+                bytes: registration.serialize(&**resource.read().unwrap())?,
+            });
+        }
+        // HashMap order is random, keep snapshots of the same world identical
+        resources.sort_by(|a, b| a.type_name.cmp(&b.type_name));
+
+        let mut entities = vec![];
+        for entity in self.entities() {
+            let mut components = vec![];
+            for (id, storage) in self.components.iter() {
+                let (Some(registration), Some(component)) = (
+                    registry.get(*id).filter(|r| r.is_serializable()),
+                    storage.get(&entity),
+                ) else {
+                    continue;
+                };
+
+                components.push(SerializedValue {
+                    type_name: registration.name.to_string(),
+                    bytes: registration.serialize(&**component.read().unwrap())?,
+                });
+            }
+
+            if components.is_empty() {
+                continue;
+            }
+
+            components.sort_by(|a, b| a.type_name.cmp(&b.type_name));
+            entities.push(SerializedEntity { entity, components });
+        }
+
+        Ok(WorldSnapshot {
+            resources,
+            entities,
+        })
+    }
+
+    /// Write a snapshot of this world. See [`World::snapshot`].
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<()> {
+        let snapshot = self.snapshot()?;
+
+        writer.write_all(SNAPSHOT_MAGIC)?;
+        writer.write_all(&SNAPSHOT_VERSION.to_le_bytes())?;
+        bincode::serialize_into(&mut writer, &snapshot)?;
+        writer.flush()?;
+
+        info!(
+            "Saved {} resources and {} entities",
+            snapshot.resources.len(),
+            snapshot.entities.len()
+        );
+
+        Ok(())
+    }
+
+    /// Read a snapshot written by [`World::save`] and apply it. See [`World::apply_snapshot`].
+    pub fn load<R: Read>(&mut self, mut reader: R) -> Result<EntityMap> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != SNAPSHOT_MAGIC {
+            return Err(anyhow!("Not a world snapshot."));
+        }
+
+        let mut version = [0; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != SNAPSHOT_VERSION {
+            return Err(anyhow!(
+                "Snapshot is version {version} but only version {SNAPSHOT_VERSION} is supported."
+            ));
+        }
+
+        let snapshot: WorldSnapshot = bincode::deserialize_from(reader)?;
+        self.apply_snapshot(snapshot)
+    }
+
+    /// Put the contents of a snapshot into this world.
+    ///
+    /// Resources in the snapshot replace the ones in the world. Every saved entity is spawned as a
+    /// new entity, any existing entities are left alone so despawn them first for a clean load.
+    /// Everything is read before anything changes, so a bad snapshot leaves the world as it was.
+    ///
+    /// Returns how the saved entities map onto the new ones.
+    pub fn apply_snapshot(&mut self, snapshot: WorldSnapshot) -> Result<EntityMap> {
+        let registry = self.type_registry.clone();
+        let registry = registry.read().unwrap();
+
+        let mut resources = vec![];
+        for value in snapshot.resources {
+            let registration = registry.get_by_name(&value.type_name).ok_or_else(|| {
+                anyhow!(
+                    "Snapshot has resource {} which isn't registered.",
+                    value.type_name
+                )
+            })?;
+            resources.push((registration, registration.deserialize(&value.bytes)?));
+        }
+
+        let mut entities = vec![];
+        for saved in snapshot.entities {
+            let mut components = vec![];
+            for value in saved.components {
+                let registration = registry.get_by_name(&value.type_name).ok_or_else(|| {
+                    anyhow!(
+                        "Snapshot has component {} which isn't registered.",
+                        value.type_name
+                    )
+                })?;
+                components.push((registration, registration.deserialize(&value.bytes)?));
+            }
+            entities.push((saved.entity, components));
+        }
+
+        // Nothing can go wrong from here on
+        let mut entity_map = EntityMap::new();
+        for (saved, _) in entities.iter() {
+            entity_map.insert(*saved, self.spawn());
+        }
+
+        for (registration, mut resource) in resources {
+            registration.map_entities(&mut *resource, &entity_map);
+
+            match self.resources.get(&registration.type_id) {
+                // Swap out the inside so clones of this world see the new value too
+                Some(existing) => *existing.write().unwrap() = resource,
+                None => {
+                    self.resources
+                        .insert(registration.type_id, Rc::new(RwLock::new(resource)));
+                }
+            }
+        }
+
+        for (saved, components) in entities {
+            let entity = entity_map.get(saved).unwrap();
+            for (registration, mut component) in components {
+                registration.map_entities(&mut *component, &entity_map);
+                self.insert_boxed(entity, registration.type_id, component);
+            }
+            trace!(
+                "Loaded saved entity {} as {}",
+                saved.id(),
+                self.label(entity)
+            );
+        }
+
+        info!("Loaded {} entities", entity_map.len());
+
+        Ok(entity_map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::entity::MapEntities;
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct Health(u32);
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct Follows(Entity);
+
+    impl MapEntities for Follows {
+        fn map_entities(&mut self, map: &EntityMap) {
+            self.0 = map.get(self.0).unwrap();
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct Score(u64);
+
+    /// Not registered, so it shouldn't be saved.
+    struct Cache;
+
+    fn registered_world() -> World {
+        let mut world = World::new();
+        world.register_type::<Health>("health");
+        world.register_mapped_type::<Follows>("follows");
+        world.register_type::<Score>("score");
+        world
+    }
+
+    fn save_to_bytes(world: &World) -> Vec<u8> {
+        let mut bytes = vec![];
+        world.save(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let mut world = registered_world();
+        // Push the ids up so the loaded entities can't end up with the same ones by chance
+        for _ in 0..5 {
+            world.spawn();
+        }
+        let leader = world.add_component(Health(10));
+        let follower = world.add_component(Health(3));
+        world.insert(follower, Follows(leader));
+        world.insert(follower, Cache);
+        world.add_component(Cache);
+        world.add_resource(Score(42));
+        world.add_resource(Cache);
+
+        let bytes = save_to_bytes(&world);
+        let mut loaded = registered_world();
+        let map = loaded.load(bytes.as_slice()).unwrap();
+
+        // The entity with only a `Cache` has nothing to save
+        assert_eq!(map.len(), 2);
+        let new_leader = map.get(leader).unwrap();
+        let new_follower = map.get(follower).unwrap();
+        assert_ne!(new_leader, leader);
+
+        assert_eq!(
+            **loaded.get_component::<Health>(new_leader).unwrap(),
+            Health(10)
+        );
+        assert_eq!(
+            **loaded.get_component::<Health>(new_follower).unwrap(),
+            Health(3)
+        );
+        assert_eq!(
+            **loaded.get_component::<Follows>(new_follower).unwrap(),
+            Follows(new_leader)
+        );
+        assert_eq!(**loaded.get_resource::<Score>(), Score(42));
+
+        assert!(!loaded.has_component::<Cache>(new_follower));
+        assert!(loaded.try_get_resource::<Cache>().is_none());
+    }
+
+    #[test]
+    fn loading_rejects_other_files() {
+        let mut world = registered_world();
+        world.add_component(Health(10));
+        let bytes = save_to_bytes(&world);
+
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[..4].copy_from_slice(b"PNG\0");
+        assert!(registered_world().load(wrong_magic.as_slice()).is_err());
+
+        let mut wrong_version = bytes;
+        wrong_version[4..8].copy_from_slice(&(SNAPSHOT_VERSION + 1).to_le_bytes());
+        let mut loaded = registered_world();
+        assert!(loaded.load(wrong_version.as_slice()).is_err());
+        assert_eq!(loaded.entities().count(), 0);
+    }
+}
@@ -0,0 +1,272 @@
+//! Looking entities up by where they are on the grid.
+//!
+//! [`SpatialIndex`] keeps a map from tiles to the entities on them, updated from the changes to
+//! [`GridPosition`] (or any other [`SpatialPosition`]) at the start of every update and fixed
+//! update. Add [`spatial_partial`] before the plugins that read it so it's up to date for them.
+//!
+//! # Example
+//! ```rs
+//! let index = world.get_resource::<SpatialIndex>();
+//!
+//! let on_tile = index.at(GridPosition::new(3, 4));
+//! let nearby = index.in_radius(mill_position, 5.0);
+//! let in_selection = index.in_rect(drag_start, drag_end);
+//! ```
+
+use std::{any::Any, collections::HashMap};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::{
+    System, World, changes::ChangeReader, entity::Entity, order_up::OrderUp,
+    partial_manager::PartialManager, reflect::impl_reflect,
+};
+
+/// Which tile an entity is on.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct GridPosition {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl_reflect!(GridPosition { x, y });
+
+impl GridPosition {
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    /// Straight line distance squared, in tiles.
+    pub fn distance_squared(&self, other: GridPosition) -> i64 {
+        let dx = self.x as i64 - other.x as i64;
+        let dy = self.y as i64 - other.y as i64;
+        // Only overflows for tiles at opposite corners of the whole grid
+        dx.saturating_mul(dx).saturating_add(dy.saturating_mul(dy))
+    }
+}
+
+/// A component that puts an entity on a tile, implement it to index something other than
+/// [`GridPosition`], like a free moving transform rounded down to the tile it's over.
+pub trait SpatialPosition: Any {
+    fn tile(&self) -> GridPosition;
+}
+
+impl SpatialPosition for GridPosition {
+    fn tile(&self) -> GridPosition {
+        *self
+    }
+}
+
+/// Every entity with a `P`, by tile.
+pub struct SpatialIndex<P: SpatialPosition = GridPosition> {
+    tiles: HashMap<GridPosition, Vec<Entity>>,
+    entities: HashMap<Entity, GridPosition>,
+    /// Made on the first sync, there's no world to make it from before that.
+    reader: Option<ChangeReader<P>>,
+}
+
+impl<P: SpatialPosition> Default for SpatialIndex<P> {
+    fn default() -> Self {
+        Self {
+            tiles: HashMap::new(),
+            entities: HashMap::new(),
+            reader: None,
+        }
+    }
+}
+
+impl<P: SpatialPosition> SpatialIndex<P> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Catch up with every `P` that changed since the last sync.
+    ///
+    /// The sync systems already do this, only call it when something has to see a change made
+    /// earlier in the same stage.
+    pub fn sync(&mut self, world: &World) {
+        let reader = self
+            .reader
+            .get_or_insert_with(|| world.track_changes::<P>());
+        let changes = world.read_changes(reader);
+
+        for entity in changes.removed {
+            self.remove(entity);
+        }
+        for entity in changes.changed {
+            let Some(tile) = world.get_component::<P>(entity).map(|p| p.tile()) else {
+                continue;
+            };
+            if self.entities.get(&entity) == Some(&tile) {
+                continue;
+            }
+
+            self.remove(entity);
+            self.entities.insert(entity, tile);
+            self.tiles.entry(tile).or_default().push(entity);
+        }
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        let Some(tile) = self.entities.remove(&entity) else {
+            return;
+        };
+        if let Some(on_tile) = self.tiles.get_mut(&tile) {
+            on_tile.retain(|e| *e != entity);
+            if on_tile.is_empty() {
+                self.tiles.remove(&tile);
+            }
+        }
+    }
+
+    /// Everything on `tile`, in the order it got there.
+    pub fn at(&self, tile: GridPosition) -> &[Entity] {
+        self.tiles.get(&tile).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// The tile `entity` is on, as of the last sync.
+    pub fn position(&self, entity: Entity) -> Option<GridPosition> {
+        self.entities.get(&entity).copied()
+    }
+
+    /// Everything inside the rectangle with corners `a` and `b`, both included. Oldest entity
+    /// first.
+    pub fn in_rect(&self, a: GridPosition, b: GridPosition) -> Vec<Entity> {
+        let min = GridPosition::new(a.x.min(b.x), a.y.min(b.y));
+        let max = GridPosition::new(a.x.max(b.x), a.y.max(b.y));
+        self.find(min, max, |_| true)
+    }
+
+    /// Everything whose tile is at most `radius` tiles from `center`. Oldest entity first.
+    pub fn in_radius(&self, center: GridPosition, radius: f32) -> Vec<Entity> {
+        if radius < 0.0 {
+            return vec![];
+        }
+
+        let reach = radius.floor() as i64;
+        let radius_squared = (radius * radius) as i64;
+        // Reaching past the edge of the grid just stops at the edge
+        let clamp = |v: i64| v.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+        self.find(
+            GridPosition::new(
+                clamp((center.x as i64).saturating_sub(reach)),
+                clamp((center.y as i64).saturating_sub(reach)),
+            ),
+            GridPosition::new(
+                clamp((center.x as i64).saturating_add(reach)),
+                clamp((center.y as i64).saturating_add(reach)),
+            ),
+            |tile| tile.distance_squared(center) <= radius_squared,
+        )
+    }
+
+    /// Everything between `min` and `max` that passes `keep`, looking at whichever is smaller, the
+    /// tiles in the area or every entity.
+    fn find(
+        &self,
+        min: GridPosition,
+        max: GridPosition,
+        keep: impl Fn(GridPosition) -> bool,
+    ) -> Vec<Entity> {
+        let width = max.x as i64 - min.x as i64 + 1;
+        let height = max.y as i64 - min.y as i64 + 1;
+        let area = (width as u64).saturating_mul(height as u64);
+        let inside = |tile: GridPosition| {
+            (min.x..=max.x).contains(&tile.x) && (min.y..=max.y).contains(&tile.y) && keep(tile)
+        };
+
+        let mut found: Vec<Entity> = match area <= self.tiles.len() as u64 {
+            true => (min.y..=max.y)
+                .flat_map(|y| (min.x..=max.x).map(move |x| GridPosition::new(x, y)))
+                .filter(|tile| inside(*tile))
+                .flat_map(|tile| self.at(tile).iter().copied())
+                .collect(),
+            false => self
+                .entities
+                .iter()
+                .filter(|(_, tile)| inside(**tile))
+                .map(|(entity, _)| *entity)
+                .collect(),
+        };
+        // HashMap order changes from run to run, which would break replays
+        found.sort();
+        found
+    }
+
+    /// How many entities are indexed.
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
+
+/// Keeps the [`SpatialIndex`] of `P` in sync.
+pub fn sync_spatial_index<P: SpatialPosition>(world: &World) -> Result<()> {
+    if let Some(mut index) = world.try_get_resource_mut::<SpatialIndex<P>>() {
+        index.sync(world);
+    }
+    Ok(())
+}
+
+/// A [`SpatialIndex`] of `P`, synced before the update and fixed systems of plugins added after
+/// it.
+pub fn spatial_partial<P: SpatialPosition>() -> PartialManager {
+    PartialManager::new()
+        .add_resource(SpatialIndex::<P>::new())
+        .add_systems((sync_spatial_index::<P> as System,).order_up())
+        .add_fixed_systems((sync_spatial_index::<P> as System,).order_up())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index_with(world: &mut World, tiles: &[GridPosition]) -> (SpatialIndex, Vec<Entity>) {
+        let entities = tiles
+            .iter()
+            .map(|tile| world.spawn_bundle((*tile,)))
+            .collect();
+        let mut index = SpatialIndex::new();
+        index.sync(world);
+        (index, entities)
+    }
+
+    #[test]
+    fn finds_what_is_in_range() {
+        let mut world = World::new();
+        let (index, entities) = index_with(
+            &mut world,
+            &[
+                GridPosition::new(0, 0),
+                GridPosition::new(2, 0),
+                GridPosition::new(5, 5),
+            ],
+        );
+
+        assert_eq!(index.in_radius(GridPosition::new(0, 0), 2.0), entities[..2]);
+        assert_eq!(
+            index.in_rect(GridPosition::new(5, 5), GridPosition::new(1, 0)),
+            entities[1..]
+        );
+    }
+
+    #[test]
+    fn edges_of_the_grid_dont_overflow() {
+        let mut world = World::new();
+        let corners = [
+            GridPosition::new(i32::MIN, i32::MIN),
+            GridPosition::new(i32::MAX, i32::MAX),
+        ];
+        let (index, entities) = index_with(&mut world, &corners);
+
+        assert_eq!(index.in_radius(corners[1], 3.0), entities[1..]);
+        assert_eq!(index.in_radius(corners[0], f32::MAX), entities);
+        assert_eq!(index.in_rect(corners[0], corners[1]), entities);
+    }
+}
@@ -0,0 +1,506 @@
+//! Running slow work on other threads without holding up the frame.
+//!
+//! Systems hand a job to the [`TaskPool`] resource and get a [`TaskId`] back. The job runs on one
+//! of the pool's worker threads, and on a later frame its result comes back either as an event or
+//! as a command. Jobs never see the [`World`], anything they need has to be moved into them.
+//!
+//! At most [`TaskPool::max_running`] jobs run at once, the rest wait their turn.
+//!
+//! Which frame a result lands on depends on how fast the worker got to it, so results aren't
+//! reproducible when replaying a recording. Only hand the pool work whose result can't change the
+//! simulation, like loading assets, or the replay will drift from what was recorded.
+//!
+//! # Example
+//! ```rs
+//! let mut tasks = world.get_resource_mut::<TaskPool>();
+//!
+//! // Raised as a `TextureLoaded` event with the image as its data
+//! tasks.spawn_event(move |_| (TextureLoaded(name), ImageData::load(&path)));
+//!
+//! // Run as a command once it's done
+//! let id = tasks.spawn_command(
+//!     move |cancel| find_path(start, end, cancel),
+//!     move |world, path| {
+//!         world.insert(bug, path);
+//!         Ok(())
+//!     },
+//! );
+//!
+//! // Changed our mind
+//! tasks.cancel(id);
+//! ```
+
+use std::{
+    any::Any,
+    collections::{HashMap, VecDeque},
+    panic::{AssertUnwindSafe, catch_unwind},
+    sync::{
+        Arc, Condvar, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, Sender, channel},
+    },
+    thread::{self, JoinHandle},
+};
+
+use anyhow::Result;
+use log::*;
+
+use crate::ecs::{
+    System, World,
+    events::{EventDataWrapper, EventWrapper},
+    order_up::OrderUp,
+    partial_manager::PartialManager,
+};
+
+/// Which job a result belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TaskId(u64);
+
+/// Handed to every job so long running ones can give up early once cancelled.
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+type Job = Box<dyn FnOnce(&CancelToken) -> Box<dyn Any + Send> + Send>;
+
+/// What to do with a job's result once it's back on the main thread.
+type Delivery = Box<dyn FnOnce(&World, Box<dyn Any + Send>)>;
+
+struct QueuedJob {
+    id: TaskId,
+    cancel: CancelToken,
+    job: Job,
+}
+
+/// Jobs waiting for a worker, `closed` tells the workers to stop.
+#[derive(Default)]
+struct Queue {
+    jobs: VecDeque<QueuedJob>,
+    closed: bool,
+}
+
+/// A job that hasn't been delivered yet.
+struct Pending {
+    cancel: CancelToken,
+    deliver: Delivery,
+}
+
+/// Runs jobs on a fixed number of worker threads.
+pub struct TaskPool {
+    queue: Arc<(Mutex<Queue>, Condvar)>,
+    finished: Receiver<(TaskId, Option<Box<dyn Any + Send>>)>,
+    pending: HashMap<TaskId, Pending>,
+    workers: Vec<JoinHandle<()>>,
+    next_id: u64,
+}
+
+impl Default for TaskPool {
+    /// One worker for every core but the one running the frame.
+    fn default() -> Self {
+        let cores = thread::available_parallelism().map_or(2, |n| n.get());
+        Self::new(cores.saturating_sub(1).max(1))
+    }
+}
+
+impl TaskPool {
+    /// A pool that runs at most `max_running` jobs at the same time.
+    pub fn new(max_running: usize) -> Self {
+        let max_running = max_running.max(1);
+        let queue = Arc::new((Mutex::new(Queue::default()), Condvar::new()));
+        let (sender, finished) = channel();
+
+        let workers = (0..max_running)
+            .map(|i| {
+                let queue = queue.clone();
+                let sender = sender.clone();
+                thread::Builder::new()
+                    .name(format!("task worker {i}"))
+                    .spawn(move || run_worker(&queue, &sender))
+                    .expect("Couldn't start a task worker thread")
+            })
+            .collect();
+
+        Self {
+            queue,
+            finished,
+            pending: HashMap::new(),
+            workers,
+            next_id: 0,
+        }
+    }
+
+    pub fn max_running(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Run `job` and raise the event and data it returns.
+    pub fn spawn_event<E, D, F>(&mut self, job: F) -> TaskId
+    where
+        E: EventWrapper + Send + 'static,
+        D: EventDataWrapper + Send + 'static,
+        F: FnOnce(&CancelToken) -> (E, D) + Send + 'static,
+    {
+        self.spawn(job, |world, (event, data)| world.raise_event(event, data))
+    }
+
+    /// Run `job` and queue up `then` as a command with what it returns.
+    pub fn spawn_command<T, F, C>(&mut self, job: F, then: C) -> TaskId
+    where
+        T: Send + 'static,
+        F: FnOnce(&CancelToken) -> T + Send + 'static,
+        C: FnOnce(&mut World, T) -> Result<()> + 'static,
+    {
+        self.spawn(job, |world, result| {
+            world.queue_command(move |world| then(world, result))
+        })
+    }
+
+    fn spawn<T, F, D>(&mut self, job: F, deliver: D) -> TaskId
+    where
+        T: Send + 'static,
+        F: FnOnce(&CancelToken) -> T + Send + 'static,
+        D: FnOnce(&World, T) + 'static,
+    {
+        let id = TaskId(self.next_id);
+        self.next_id += 1;
+        let cancel = CancelToken::default();
+
+        self.pending.insert(
+            id,
+            Pending {
+                cancel: cancel.clone(),
+                deliver: Box::new(move |world, result| {
+                    // Can't fail, the result came from this job
+                    deliver(world, *result.downcast::<T>().unwrap())
+                }),
+            },
+        );
+
+        let (queue, ready) = &*self.queue;
+        queue.lock().unwrap().jobs.push_back(QueuedJob {
+            id,
+            cancel,
+            job: Box::new(move |cancel| Box::new(job(cancel))),
+        });
+        ready.notify_one();
+
+        id
+    }
+
+    /// Stop a job. If it hasn't started it never will, if it has its [`CancelToken`] says so.
+    /// Either way its result is thrown away.
+    ///
+    /// Returns `false` if the job was already delivered or cancelled.
+    pub fn cancel(&mut self, id: TaskId) -> bool {
+        let Some(pending) = self.pending.remove(&id) else {
+            return false;
+        };
+        pending.cancel.cancel();
+        true
+    }
+
+    /// Whether the job's result hasn't been delivered yet.
+    pub fn is_pending(&self, id: TaskId) -> bool {
+        self.pending.contains_key(&id)
+    }
+
+    /// How many jobs are waiting, running or waiting to be delivered.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Take every finished job and whatever has to happen with its result.
+    fn take_finished(&mut self) -> Vec<(TaskId, Delivery, Box<dyn Any + Send>)> {
+        let mut finished: Vec<_> = self
+            .finished
+            .try_iter()
+            .filter_map(|(id, result)| {
+                // Cancelled jobs aren't pending anymore
+                let pending = self.pending.remove(&id)?;
+                Some((id, pending.deliver, result?))
+            })
+            .collect();
+        // Same order no matter which worker finished first
+        finished.sort_by_key(|(id, _, _)| *id);
+        finished
+    }
+}
+
+impl Drop for TaskPool {
+    /// Cancels everything and waits for the workers to finish what they're on.
+    fn drop(&mut self) {
+        for pending in self.pending.values() {
+            pending.cancel.cancel();
+        }
+
+        let (queue, ready) = &*self.queue;
+        {
+            let mut queue = queue.lock().unwrap();
+            queue.jobs.clear();
+            queue.closed = true;
+        }
+        ready.notify_all();
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn run_worker(
+    queue: &(Mutex<Queue>, Condvar),
+    finished: &Sender<(TaskId, Option<Box<dyn Any + Send>>)>,
+) {
+    let (queue, ready) = queue;
+    loop {
+        let QueuedJob { id, cancel, job } = {
+            let mut queue = queue.lock().unwrap();
+            loop {
+                if queue.closed {
+                    return;
+                }
+                match queue.jobs.pop_front() {
+                    Some(job) => break job,
+                    None => queue = ready.wait(queue).unwrap(),
+                }
+            }
+        };
+
+        if cancel.is_cancelled() {
+            continue;
+        }
+
+        let result = match catch_unwind(AssertUnwindSafe(|| job(&cancel))) {
+            Ok(result) => Some(result),
+            Err(_) => {
+                error!("Task {id:?} panicked, its result is lost");
+                None
+            }
+        };
+
+        // Nobody's listening anymore when the pool is being dropped
+        if finished.send((id, result)).is_err() {
+            return;
+        }
+    }
+}
+
+/// Hands the results of finished jobs over to the world.
+pub fn deliver_tasks(world: &World) -> Result<()> {
+    let Some(finished) = world
+        .try_get_resource_mut::<TaskPool>()
+        .map(|mut tasks| tasks.take_finished())
+    else {
+        return Ok(());
+    };
+
+    for (id, deliver, result) in finished {
+        trace!("Task {id:?} finished");
+        deliver(world, result);
+    }
+
+    Ok(())
+}
+
+/// A [`TaskPool`] with a worker for every core but one. Events from finished jobs are raised
+/// during the update and handled once every update system has run, still in the same frame.
+pub fn tasks_partial() -> PartialManager {
+    PartialManager::new()
+        .add_resource(TaskPool::default())
+        .add_systems((deliver_tasks as System,).order_up())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{atomic::AtomicUsize, mpsc},
+        time::{Duration, Instant},
+    };
+
+    use super::*;
+    use crate::ecs::{
+        events::{EcsEvent, EcsEventData, LemgineEventData},
+        test_app::TestApp,
+    };
+
+    #[derive(Clone, PartialEq, Eq, Hash)]
+    struct Loaded;
+    impl EcsEvent for Loaded {}
+
+    #[derive(Clone)]
+    struct Payload(u32);
+    impl EcsEventData for Payload {}
+
+    /// Everything delivered so far, in order.
+    #[derive(Default)]
+    struct Delivered(Vec<u32>);
+
+    fn on_loaded(world: &World, _: &Loaded, data: LemgineEventData) -> Result<()> {
+        let Payload(n) = data.downcast_ref::<Payload>().unwrap();
+        world.get_resource_mut::<Delivered>().0.push(*n);
+        Ok(())
+    }
+
+    fn tasks_app(max_running: usize) -> TestApp {
+        let app = TestApp::new()
+            .unwrap()
+            .add_resource(Delivered::default())
+            .integrate(tasks_partial())
+            .unwrap()
+            .integrate(PartialManager::new().add_typed_event_handler(on_loaded))
+            .unwrap();
+        **app.resource_mut::<TaskPool>() = TaskPool::new(max_running);
+        app
+    }
+
+    fn record(world: &mut World, n: u32) -> Result<()> {
+        world.get_resource_mut::<Delivered>().0.push(n);
+        Ok(())
+    }
+
+    /// Run frames until nothing's pending anymore.
+    fn update_until_done(app: &mut TestApp) {
+        let start = Instant::now();
+        while app.resource::<TaskPool>().pending() > 0 {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "tasks never finished"
+            );
+            app.update().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn delivered(app: &TestApp) -> Vec<u32> {
+        app.resource::<Delivered>().0.clone()
+    }
+
+    #[test]
+    fn results_come_back_as_events() {
+        let mut app = tasks_app(1);
+        app.resource_mut::<TaskPool>()
+            .spawn_event(|_| (Loaded, Payload(7)));
+
+        update_until_done(&mut app);
+
+        assert_eq!(delivered(&app), [7]);
+    }
+
+    #[test]
+    fn results_come_back_as_commands() {
+        let mut app = tasks_app(1);
+        app.resource_mut::<TaskPool>().spawn_command(|_| 7, record);
+
+        update_until_done(&mut app);
+
+        assert_eq!(delivered(&app), [7]);
+    }
+
+    #[test]
+    fn cancelled_before_starting_never_runs() {
+        let mut app = tasks_app(1);
+        let (release, blocked) = mpsc::channel::<()>();
+        let ran = Arc::new(AtomicBool::new(false));
+        let mut tasks = app.resource_mut::<TaskPool>();
+        // Keeps the only worker busy so the next job has to wait
+        tasks.spawn_command(
+            move |_| {
+                blocked.recv().unwrap();
+                1
+            },
+            record,
+        );
+        let ran_in_job = ran.clone();
+        let cancelled = tasks.spawn_command(
+            move |_| ran_in_job.store(true, Ordering::Relaxed),
+            |world, ()| record(world, 2),
+        );
+        assert!(tasks.cancel(cancelled));
+        assert!(!tasks.cancel(cancelled));
+        drop(tasks);
+
+        release.send(()).unwrap();
+        update_until_done(&mut app);
+        app.update().unwrap();
+
+        assert_eq!(delivered(&app), [1]);
+        assert!(!ran.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn cancelled_while_running_isnt_delivered() {
+        let mut app = tasks_app(1);
+        let (started, wait_for_start) = mpsc::channel();
+        let (finished, wait_for_finish) = mpsc::channel();
+        let id = app.resource_mut::<TaskPool>().spawn_command(
+            move |cancel| {
+                started.send(()).unwrap();
+                while !cancel.is_cancelled() {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                finished.send(()).unwrap();
+                3
+            },
+            record,
+        );
+
+        wait_for_start.recv().unwrap();
+        assert!(app.resource_mut::<TaskPool>().cancel(id));
+        wait_for_finish.recv().unwrap();
+        // Give the result time to reach the channel before the last frames
+        thread::sleep(Duration::from_millis(20));
+        app.update().unwrap();
+        app.update().unwrap();
+
+        assert!(delivered(&app).is_empty());
+        assert!(!app.resource::<TaskPool>().is_pending(id));
+    }
+
+    #[test]
+    fn no_more_than_max_running_at_once() {
+        let mut app = tasks_app(2);
+        let running = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+        for n in 0..6 {
+            let (running, most) = (running.clone(), most.clone());
+            app.resource_mut::<TaskPool>().spawn_command(
+                move |_| {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    most.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(20));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    n
+                },
+                record,
+            );
+        }
+
+        update_until_done(&mut app);
+
+        assert_eq!(delivered(&app).len(), 6);
+        assert!(most.load(Ordering::SeqCst) <= 2, "ran {most:?} at once");
+    }
+
+    #[test]
+    fn a_panicking_job_doesnt_take_the_pool_down() {
+        let mut app = tasks_app(1);
+        let mut tasks = app.resource_mut::<TaskPool>();
+        let panicked = tasks.spawn_command(|_| -> u32 { panic!("on purpose") }, record);
+        tasks.spawn_command(|_| 2, record);
+        drop(tasks);
+
+        update_until_done(&mut app);
+
+        assert_eq!(delivered(&app), [2]);
+        assert!(!app.resource::<TaskPool>().is_pending(panicked));
+    }
+}
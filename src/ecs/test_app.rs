@@ -0,0 +1,329 @@
+//! A [`Manager`] without a window, for testing systems.
+//!
+//! Input is faked with [`InputEvent`]s which go through the input systems the same way real
+//! input does. Winit event systems only run for events handed to [`TestApp::send_winit_event`],
+//! which needs an event loop to give them. The rng always starts from the same seed so tests
+//! don't change from run to run.
+//!
+//! # Example
+//! ```rs
+//! let mut app = TestApp::new()?
+//!     .integrate(input_partial())?
+//!     .integrate(actions_partial(bindings))?
+//!     .integrate(camera_controller_partial(camera_actions, CameraControls::default()))?
+//!     .integrate(camera_partial(Camera2D::new()))?;
+//!
+//! app.press_key(KeyCode::KeyW);
+//! app.update_for(Duration::from_secs(1))?;
+//!
+//! app.assert_resource::<ButtonInput<KeyCode>>(|keys| keys.pressed(KeyCode::KeyW));
+//! ```
+
+use std::{
+    any::{Any, type_name},
+    sync::{MappedRwLockReadGuard, MappedRwLockWriteGuard},
+    time::Duration,
+};
+
+use anyhow::Result;
+
+use crate::ecs::{
+    Event, EventLoopWindowTarget, Manager, World,
+    entity::Entity,
+    input::{ElementState, InputEvent, KeyCode, MouseButton, MouseScrollDelta, PhysicalPosition},
+    partial_manager::PartialManager,
+    rng::GameRng,
+    time::Time,
+};
+
+/// What the rng of every [`TestApp`] starts from, see [`TestApp::with_seed`].
+pub const TEST_SEED: u64 = 0;
+
+pub struct TestApp {
+    pub manager: Manager,
+    /// Input waiting for the next frame.
+    inputs: Vec<InputEvent>,
+    /// How much time every frame takes, the fixed timestep unless changed.
+    frame_delta: Duration,
+}
+
+impl TestApp {
+    pub fn new() -> Result<Self> {
+        let manager = Manager::new()?;
+        **manager.world.get_resource_mut::<GameRng>() = GameRng::new(TEST_SEED);
+        let frame_delta = manager.world.get_resource::<Time>().fixed_timestep();
+
+        Ok(Self {
+            manager,
+            inputs: vec![],
+            frame_delta,
+        })
+    }
+
+    /// Add a plugin, its startup systems are skipped since there's no event loop for them.
+    pub fn integrate(mut self, partial: PartialManager) -> Result<Self> {
+        self.manager = self.manager.integrate(partial)?;
+        Ok(self)
+    }
+
+    /// Start the rng from `seed` instead of [`TEST_SEED`].
+    pub fn with_seed(self, seed: u64) -> Self {
+        **self.manager.world.get_resource_mut::<GameRng>() = GameRng::new(seed);
+        self
+    }
+
+    pub fn add_resource<T: Any>(mut self, resource: T) -> Self {
+        self.manager.world.add_resource(resource);
+        self
+    }
+
+    pub fn world(&self) -> &World {
+        &self.manager.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.manager.world
+    }
+
+    /// Make every following frame take `delta`.
+    pub fn set_frame_delta(&mut self, delta: Duration) {
+        self.frame_delta = delta;
+    }
+
+    /// Queue up input for the next frame. Input the gui would take, going by the
+    /// [`InputCapture`](crate::ecs::input::InputCapture) resource, is dropped the same way real input is.
+    pub fn send_input(&mut self, input: InputEvent) {
+        let inputs = self.manager.uncaptured(vec![input]);
+        self.inputs.extend(inputs);
+    }
+
+    /// Run the winit systems for `event` right away, like the event loop would. Any input in it
+    /// that the gui doesn't take is queued up for the next frame.
+    pub fn send_winit_event(
+        &mut self,
+        event: Event<()>,
+        elwt: &EventLoopWindowTarget<()>,
+    ) -> Result<()> {
+        let inputs = self.manager.handle_winit_event(event, elwt)?;
+        self.inputs.extend(inputs);
+        Ok(())
+    }
+
+    pub fn press_key(&mut self, code: KeyCode) {
+        self.send_input(InputEvent::key_pressed(code));
+    }
+
+    pub fn release_key(&mut self, code: KeyCode) {
+        self.send_input(InputEvent::key_released(code));
+    }
+
+    pub fn press_mouse(&mut self, button: MouseButton) {
+        self.send_input(InputEvent::MouseButton {
+            button,
+            state: ElementState::Pressed,
+        });
+    }
+
+    pub fn release_mouse(&mut self, button: MouseButton) {
+        self.send_input(InputEvent::MouseButton {
+            button,
+            state: ElementState::Released,
+        });
+    }
+
+    /// Move the cursor to `x`, `y` pixels from the top left of the window.
+    pub fn move_cursor(&mut self, x: f64, y: f64) {
+        self.send_input(InputEvent::CursorMoved(PhysicalPosition::new(x, y)));
+    }
+
+    /// Scroll by `lines`, positive is away from the player.
+    pub fn scroll(&mut self, lines: f32) {
+        self.send_input(InputEvent::MouseWheel(MouseScrollDelta::LineDelta(
+            0.0, lines,
+        )));
+    }
+
+    /// Run a single frame with the queued input.
+    pub fn update(&mut self) -> Result<()> {
+        let inputs = std::mem::take(&mut self.inputs);
+        self.manager.step(&inputs, Some(self.frame_delta))
+    }
+
+    /// Run frames until at least `duration` has gone by.
+    pub fn update_for(&mut self, duration: Duration) -> Result<()> {
+        let mut elapsed = Duration::ZERO;
+        while elapsed < duration {
+            self.update()?;
+            elapsed += self.frame_delta;
+        }
+        Ok(())
+    }
+
+    /// Run exactly `ticks` fixed steps, each in a frame of its own.
+    pub fn run_fixed_ticks(&mut self, ticks: u64) -> Result<()> {
+        let timestep = self.world().get_resource::<Time>().fixed_timestep();
+        let target = self.world().get_resource::<Time>().fixed_tick_count() + ticks;
+
+        while self.world().get_resource::<Time>().fixed_tick_count() < target {
+            let inputs = std::mem::take(&mut self.inputs);
+            self.manager.step(&inputs, Some(timestep))?;
+        }
+        Ok(())
+    }
+
+    pub fn resource<T: Any>(&self) -> MappedRwLockReadGuard<'_, Box<T>> {
+        self.world().get_resource::<T>()
+    }
+
+    pub fn resource_mut<T: Any>(&self) -> MappedRwLockWriteGuard<'_, Box<T>> {
+        self.world().get_resource_mut::<T>()
+    }
+
+    /// # Panics
+    /// Panics if `entity` doesn't have a `T`.
+    pub fn component<T: Any>(&self, entity: Entity) -> MappedRwLockReadGuard<'_, Box<T>> {
+        self.world()
+            .get_component::<T>(entity)
+            .unwrap_or_else(|| panic!("{} has no {}", self.world().label(entity), type_name::<T>()))
+    }
+
+    /// # Panics
+    /// Panics if the resource doesn't exist or `check` returns `false`.
+    #[track_caller]
+    pub fn assert_resource<T: Any>(&self, check: impl FnOnce(&T) -> bool) {
+        let resource = self
+            .world()
+            .try_get_resource::<T>()
+            .unwrap_or_else(|| panic!("There is no {} resource", type_name::<T>()));
+        assert!(check(&resource), "{} isn't as expected", type_name::<T>());
+    }
+
+    /// # Panics
+    /// Panics if `entity` doesn't have a `T` or `check` returns `false`.
+    #[track_caller]
+    pub fn assert_component<T: Any>(&self, entity: Entity, check: impl FnOnce(&T) -> bool) {
+        let component = self.component::<T>(entity);
+        assert!(
+            check(&component),
+            "{} of {} isn't as expected",
+            type_name::<T>(),
+            self.world().label(entity)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::RngCore;
+
+    use super::*;
+    use crate::ecs::{InputSystem, order_up::OrderUp};
+
+    /// Every input the input systems got, in order.
+    #[derive(Default)]
+    struct Received(Vec<InputEvent>);
+
+    fn receive(world: &World, input: &InputEvent) -> Result<()> {
+        world.get_resource_mut::<Received>().0.push(*input);
+        Ok(())
+    }
+
+    fn input_app() -> TestApp {
+        TestApp::new()
+            .unwrap()
+            .integrate(
+                PartialManager::new()
+                    .add_resource(Received::default())
+                    .add_input_systems((receive as InputSystem,).order_up()),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn rng_is_seeded_the_same_every_time() {
+        let first = TestApp::new().unwrap();
+        let second = TestApp::new().unwrap();
+        let mut first = first.resource_mut::<GameRng>();
+        let mut second = second.resource_mut::<GameRng>();
+
+        assert_eq!(first.seed(), TEST_SEED);
+        assert_eq!(
+            first.stream("test").next_u64(),
+            second.stream("test").next_u64()
+        );
+    }
+
+    #[test]
+    fn update_for_runs_whole_frames() {
+        let mut app = TestApp::new().unwrap();
+        app.set_frame_delta(Duration::from_millis(10));
+
+        app.update_for(Duration::from_secs(1)).unwrap();
+
+        app.assert_resource::<Time>(|time| {
+            time.frame_count() == 100 && time.elapsed() == Duration::from_secs(1)
+        });
+    }
+
+    #[test]
+    fn run_fixed_ticks_runs_exactly_that_many() {
+        let mut app = TestApp::new().unwrap();
+
+        app.run_fixed_ticks(5).unwrap();
+
+        app.assert_resource::<Time>(|time| time.fixed_tick_count() == 5);
+    }
+
+    #[test]
+    fn input_waits_for_the_next_frame() {
+        let mut app = input_app();
+
+        app.press_key(KeyCode::KeyW);
+        app.move_cursor(12.0, 34.0);
+        app.assert_resource::<Received>(|received| received.0.is_empty());
+
+        app.update().unwrap();
+        app.assert_resource::<Received>(|received| {
+            received.0
+                == [
+                    InputEvent::key_pressed(KeyCode::KeyW),
+                    InputEvent::CursorMoved(PhysicalPosition::new(12.0, 34.0)),
+                ]
+        });
+
+        app.update().unwrap();
+        app.assert_resource::<Received>(|received| received.0.len() == 2);
+    }
+
+    #[cfg(all(feature = "render", target_os = "linux"))]
+    #[test]
+    #[ignore = "needs a display"]
+    fn winit_events_reach_the_input_systems() {
+        use winit::{
+            event::{DeviceId, WindowEvent},
+            event_loop::EventLoopBuilder,
+            platform::x11::EventLoopBuilderExtX11,
+            window::WindowId,
+        };
+
+        let event_loop = EventLoopBuilder::new()
+            .with_any_thread(true)
+            .build()
+            .unwrap();
+        let mut app = input_app();
+
+        let event = Event::WindowEvent {
+            window_id: unsafe { WindowId::dummy() },
+            event: WindowEvent::CursorMoved {
+                device_id: unsafe { DeviceId::dummy() },
+                position: PhysicalPosition::new(12.0, 34.0),
+            },
+        };
+        app.send_winit_event(event, &event_loop).unwrap();
+        app.update().unwrap();
+
+        app.assert_resource::<Received>(|received| {
+            received.0 == [InputEvent::CursorMoved(PhysicalPosition::new(12.0, 34.0))]
+        });
+    }
+}
@@ -0,0 +1,354 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+
+use crate::ecs::{
+    World,
+    reflect::{Reflect, ReflectValue, impl_reflect},
+};
+
+/// Default length of a single fixed step, 60 ticks per second.
+const DEFAULT_FIXED_TIMESTEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// Upper bound on how much real time a single update can feed into the virtual clock.
+/// Stops a long stall (dragging the window, a breakpoint) from turning into hundreds of fixed ticks.
+const MAX_DELTA: Duration = Duration::from_millis(250);
+
+/// The most fixed steps a single update can queue up at a time scale of one. A long frame would
+/// otherwise queue more ticks than the next frame can run, making that one longer still. Time past
+/// it is dropped, so the fixed clock falls behind virtual time instead.
+///
+/// Fast-forwarding multiplies the cap by the time scale, otherwise every frame longer than
+/// `MAX_FIXED_STEPS_PER_UPDATE / time_scale` fixed steps would lose time and 8x would run slower
+/// than asked for at low frame rates.
+const MAX_FIXED_STEPS_PER_UPDATE: u32 = 8;
+
+/// The clock of the [`World`].
+///
+/// There are two clocks in here:
+/// - Real time, which is what the wall clock says and is never paused or scaled.
+/// - Virtual time, which is real time multiplied by the time scale and stops while paused.
+///
+/// Gameplay should almost always read virtual time, so pausing or fast-forwarding the
+/// factory Just Works. The fixed step accumulator is also fed by virtual time.
+///
+/// # Example
+/// ```rs
+/// let time = world.get_resource::<Time>();
+/// position += speed * time.delta_secs();
+/// ```
+pub struct Time {
+    last_update: Option<Instant>,
+    frame_count: u64,
+
+    real_delta: Duration,
+    real_elapsed: Duration,
+
+    delta: Duration,
+    elapsed: Duration,
+    paused: bool,
+    time_scale: TimeScale,
+
+    fixed_timestep: Duration,
+    fixed_accumulator: Duration,
+    fixed_elapsed: Duration,
+    fixed_tick_count: u64,
+}
+
+// The fixed timestep is left out, setting it to zero from the inspector would hang the fixed loop.
+// The clocks only go forward by updating.
+impl_reflect!(Time { paused, time_scale } read_only {
+    frame_count,
+    real_delta,
+    real_elapsed,
+    delta,
+    elapsed,
+    fixed_tick_count,
+});
+
+/// The time scale, checked when it's set through [`Reflect`] the same way
+/// [`Time::set_time_scale`] checks it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct TimeScale(f32);
+
+impl TimeScale {
+    fn new(scale: f32) -> Result<Self> {
+        match scale.is_finite() && scale >= 0.0 {
+            true => Ok(Self(scale)),
+            false => Err(anyhow!(
+                "Time scale must be finite and not negative, got {scale}"
+            )),
+        }
+    }
+}
+
+impl Reflect for TimeScale {
+    fn value(&self) -> ReflectValue {
+        self.0.value()
+    }
+
+    fn set_value(&mut self, value: ReflectValue) -> Result<()> {
+        let mut scale = self.0;
+        scale.set_value(value)?;
+        *self = Self::new(scale)?;
+        Ok(())
+    }
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Time {
+    pub fn new() -> Self {
+        Self {
+            last_update: None,
+            frame_count: 0,
+            real_delta: Duration::ZERO,
+            real_elapsed: Duration::ZERO,
+            delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            paused: false,
+            time_scale: TimeScale(1.0),
+            fixed_timestep: DEFAULT_FIXED_TIMESTEP,
+            fixed_accumulator: Duration::ZERO,
+            fixed_elapsed: Duration::ZERO,
+            fixed_tick_count: 0,
+        }
+    }
+
+    /// Advance the clocks to `Instant::now()`. Called once per frame by [`Manager`].
+    pub fn update(&mut self) {
+        self.update_with_instant(Instant::now());
+    }
+
+    /// Advance the clocks to `now`.
+    ///
+    /// The first call only records the instant, so the first frame has a delta of zero.
+    pub fn update_with_instant(&mut self, now: Instant) {
+        let real_delta = match self.last_update {
+            Some(last) => now.saturating_duration_since(last),
+            None => Duration::ZERO,
+        };
+        self.last_update = Some(now);
+
+        self.advance_by(real_delta);
+    }
+
+    /// Advance the clocks by a set amount of real time, as if that much time had passed.
+    ///
+    /// Useful when there is no real clock to read, e.g. replays and tests.
+    pub fn advance_by(&mut self, real_delta: Duration) {
+        self.frame_count += 1;
+
+        self.real_delta = real_delta;
+        self.real_elapsed += real_delta;
+
+        self.delta = if self.paused {
+            Duration::ZERO
+        } else if self.time_scale.0 == 1.0 {
+            // Skip the float round trip so stepping by exactly the fixed timestep stays exact
+            real_delta.min(MAX_DELTA)
+        } else {
+            real_delta.min(MAX_DELTA).mul_f32(self.time_scale.0)
+        };
+        self.elapsed += self.delta;
+        self.fixed_accumulator = (self.fixed_accumulator + self.delta).min(self.max_accumulator());
+    }
+
+    /// How much virtual time the fixed step accumulator can hold, see
+    /// [`MAX_FIXED_STEPS_PER_UPDATE`].
+    fn max_accumulator(&self) -> Duration {
+        let max = self.fixed_timestep * MAX_FIXED_STEPS_PER_UPDATE;
+        match self.time_scale.0 > 1.0 {
+            true => max.mul_f32(self.time_scale.0),
+            false => max,
+        }
+    }
+
+    /// Number of frames since the start.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Real time since the last frame. Ignores pausing and the time scale.
+    pub fn real_delta(&self) -> Duration {
+        self.real_delta
+    }
+
+    /// [`Self::real_delta`] in seconds.
+    pub fn real_delta_secs(&self) -> f32 {
+        self.real_delta.as_secs_f32()
+    }
+
+    /// Real time since the start. Ignores pausing and the time scale.
+    pub fn real_elapsed(&self) -> Duration {
+        self.real_elapsed
+    }
+
+    /// Virtual time since the last frame.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    /// [`Self::delta`] in seconds.
+    pub fn delta_secs(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// Virtual time since the start.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// [`Self::elapsed`] in seconds.
+    pub fn elapsed_secs(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    /// Stop virtual time. Real time keeps going.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Start virtual time again.
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// How fast virtual time runs compared to real time, `2.0` is twice as fast.
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale.0
+    }
+
+    /// Set how fast virtual time runs compared to real time.
+    ///
+    /// # Panics
+    /// Panics if `scale` is negative or not finite.
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.time_scale = TimeScale::new(scale).unwrap_or_else(|err| panic!("{err}"));
+    }
+
+    /// The length of a single fixed step in virtual time.
+    pub fn fixed_timestep(&self) -> Duration {
+        self.fixed_timestep
+    }
+
+    /// [`Self::fixed_timestep`] in seconds. This is the delta fixed systems should use.
+    pub fn fixed_delta_secs(&self) -> f32 {
+        self.fixed_timestep.as_secs_f32()
+    }
+
+    /// # Panics
+    /// Panics if `timestep` is zero.
+    pub fn set_fixed_timestep(&mut self, timestep: Duration) {
+        assert!(!timestep.is_zero(), "Fixed timestep can't be zero");
+        self.fixed_timestep = timestep;
+    }
+
+    /// Virtual time simulated by fixed steps so far.
+    pub fn fixed_elapsed(&self) -> Duration {
+        self.fixed_elapsed
+    }
+
+    /// Number of fixed steps since the start.
+    pub fn fixed_tick_count(&self) -> u64 {
+        self.fixed_tick_count
+    }
+
+    /// How far into the next fixed step we are, from `0.0` to `1.0`.
+    /// Handy for interpolating between the last two fixed states when rendering.
+    pub fn fixed_overstep_fraction(&self) -> f32 {
+        self.fixed_accumulator.as_secs_f32() / self.fixed_timestep.as_secs_f32()
+    }
+
+    /// Take one fixed step out of the accumulator if there is enough time in it.
+    ///
+    /// Returns `true` if a step was taken, [`Manager`] runs the fixed systems until this is `false`.
+    pub fn expend_fixed_step(&mut self) -> bool {
+        if self.fixed_accumulator < self.fixed_timestep {
+            return false;
+        }
+
+        self.fixed_accumulator -= self.fixed_timestep;
+        self.fixed_elapsed += self.fixed_timestep;
+        self.fixed_tick_count += 1;
+
+        true
+    }
+}
+
+/// Advances the [`Time`] resource, [`Manager`] calls this at the start of each frame.
+pub fn update_time(world: &World) -> Result<()> {
+    world.get_resource_mut::<Time>().update();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticks_in_update(time: &mut Time, real_delta: Duration) -> u32 {
+        time.advance_by(real_delta);
+        let mut ticks = 0;
+        while time.expend_fixed_step() {
+            ticks += 1;
+        }
+        ticks
+    }
+
+    #[test]
+    fn fixed_steps_follow_virtual_time() {
+        let mut time = Time::new();
+        let timestep = Duration::from_millis(10);
+        time.set_fixed_timestep(timestep);
+
+        assert_eq!(ticks_in_update(&mut time, timestep * 3), 3);
+
+        // Scaling goes through floats, so it can come up a hair short
+        time.set_time_scale(2.0);
+        let ticks: u32 = (0..10).map(|_| ticks_in_update(&mut time, timestep)).sum();
+        assert!((19..=20).contains(&ticks), "{ticks} ticks instead of 20");
+    }
+
+    #[test]
+    fn fixed_steps_per_update_are_capped() {
+        let mut time = Time::new();
+
+        let ticks = ticks_in_update(&mut time, MAX_DELTA);
+
+        assert_eq!(ticks, MAX_FIXED_STEPS_PER_UPDATE);
+        assert!(time.fixed_overstep_fraction() < 1.0);
+
+        // The cap grows with the time scale
+        time.set_time_scale(2.0);
+        let ticks = ticks_in_update(&mut time, MAX_DELTA);
+
+        assert_eq!(ticks, MAX_FIXED_STEPS_PER_UPDATE * 2);
+    }
+
+    #[test]
+    fn fast_forward_keeps_pace_at_a_low_frame_rate() {
+        let mut time = Time::new();
+        time.set_time_scale(8.0);
+
+        // One second at 30 fps
+        let ticks: u32 = (0..30)
+            .map(|_| ticks_in_update(&mut time, Duration::from_secs(1) / 30))
+            .sum();
+
+        // 8 seconds of 60 ticks each, less a little for the float scaling
+        assert!((479..=480).contains(&ticks), "{ticks} ticks instead of 480");
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    collections::HashMap,
+    ffi::c_void,
+    fmt::{self, Display},
+    time::Duration,
+};
+
+/// How much the newest timing counts towards the average.
+const AVERAGE_WEIGHT: f64 = 0.05;
+
+/// Which part of the frame a system runs in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Stage {
+    Startup,
+    WinitEvent,
+    Input,
+    Fixed,
+    Update,
+    Event,
+    Extract,
+    Shutdown,
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Stage::Startup => "Startup",
+            Stage::WinitEvent => "Winit Events",
+            Stage::Input => "Input",
+            Stage::Fixed => "Fixed Update",
+            Stage::Update => "Update",
+            Stage::Event => "Event Handlers",
+            Stage::Extract => "Extract",
+            Stage::Shutdown => "Shutdown",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SystemTiming {
+    pub name: String,
+    pub last: Duration,
+    /// Exponential moving average, smooths out the odd slow frame.
+    pub average: Duration,
+    pub calls: u64,
+}
+
+/// How long every system took, filled in by [`Manager`](crate::ecs::Manager) as it runs them.
+#[derive(Default)]
+pub struct SystemTimings {
+    stages: Vec<(Stage, Vec<SystemTiming>)>,
+    /// Where each system is in `stages`, keyed by the system's address.
+    lookup: HashMap<(Stage, usize), usize>,
+}
+
+impl SystemTimings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that the system at `address` took `elapsed`.
+    pub fn record(&mut self, stage: Stage, address: usize, elapsed: Duration) {
+        let stage_index = match self.stages.iter().position(|(s, _)| *s == stage) {
+            Some(i) => i,
+            None => {
+                self.stages.push((stage, vec![]));
+                self.stages.len() - 1
+            }
+        };
+        let systems = &mut self.stages[stage_index].1;
+
+        let index = *self.lookup.entry((stage, address)).or_insert_with(|| {
+            systems.push(SystemTiming {
+                name: system_name(address),
+                last: Duration::ZERO,
+                average: elapsed,
+                calls: 0,
+            });
+            systems.len() - 1
+        });
+
+        let timing = &mut systems[index];
+        timing.last = elapsed;
+        timing.average = timing
+            .average
+            .mul_f64(1.0 - AVERAGE_WEIGHT)
+            .saturating_add(elapsed.mul_f64(AVERAGE_WEIGHT));
+        timing.calls += 1;
+    }
+
+    /// Every stage that has run so far and its systems, in the order they first ran.
+    pub fn stages(&self) -> &[(Stage, Vec<SystemTiming>)] {
+        &self.stages
+    }
+}
+
+/// Look up the name of the function at `address` with the debug symbols.
+///
+/// Works the same way as the function names in the logs, falls back to the address in builds
+/// without symbols.
+pub fn system_name(address: usize) -> String {
+    let mut name = None;
+    backtrace::resolve(address as *mut c_void, |symbol| {
+        if name.is_none() {
+            // `#` drops the hash on the end
+            name = symbol.name().map(|n| format!("{n:#}"));
+        }
+    });
+
+    name.map(|n| n.strip_prefix("gristmill::").unwrap_or(&n).to_string())
+        .unwrap_or_else(|| format!("{address:#x}"))
+}
@@ -0,0 +1,361 @@
+//! Running more than one [`World`] at once.
+//!
+//! The main world holds the window and renderer, other worlds (the factory simulation, a blueprint
+//! preview) get added with [`Manager::add_world`](crate::ecs::Manager::add_world) and run their own
+//! schedule. Nothing is shared between worlds, data crosses over in two ways:
+//! - Extract systems, which see two worlds at once and copy data between them.
+//! - Messages, which any system can send to a world by name with [`World::send_to`].
+//!
+//! A message that can't be delivered raises [`UndeliveredMessage`] back in the world that sent it.
+
+use std::{
+    any::{Any, TypeId, type_name},
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use log::*;
+
+use crate::ecs::{
+    EventLoop, ShutdownSystem, StartupSystem, System, World, check_events,
+    events::{EcsEvent, EventHandlers},
+    exit::NoEventData,
+    ordering::SystemOrder,
+    partial_manager::PartialManager,
+    run_update,
+    timings::Stage,
+};
+
+/// Name of the world owned directly by [`Manager`](crate::ecs::Manager).
+pub const MAIN_WORLD: &str = "main";
+
+/// A world with its own schedule, run by [`Manager`](crate::ecs::Manager) next to the main world.
+pub struct SubWorld {
+    pub name: String,
+    pub world: World,
+    pub startup_systems: SystemOrder<StartupSystem>,
+    pub systems: SystemOrder<System>,
+    pub fixed_systems: SystemOrder<System>,
+    pub event_systems: EventHandlers,
+    pub shutdown_systems: SystemOrder<ShutdownSystem>,
+}
+
+impl SubWorld {
+    pub fn new(name: &str, partial: PartialManager) -> Result<Self> {
+        if !partial.winit_event_systems.order.is_empty() {
+            warn!("World {name} has winit event systems, only the main world gets winit events.");
+        }
+        if !partial.input_systems.order.is_empty() {
+            warn!("World {name} has input systems, only the main world gets input.");
+        }
+
+        let mut world = World::with_defaults();
+        world.absorb_partial(
+            partial.resources,
+            partial.components,
+            partial.bundles,
+            partial.type_registry,
+        )?;
+
+        Ok(Self {
+            name: name.to_string(),
+            world,
+            startup_systems: partial.startup_systems,
+            systems: partial.systems,
+            fixed_systems: partial.fixed_systems,
+            event_systems: partial.event_systems,
+            shutdown_systems: partial.shutdown_systems,
+        })
+    }
+
+    pub fn startup(&mut self, event_loop: &EventLoop<()>) -> Result<()> {
+        for system in self.startup_systems.order.iter() {
+            let start = Instant::now();
+            system(&mut self.world, event_loop)?;
+            self.world
+                .record_timing(Stage::Startup, *system as usize, start.elapsed());
+        }
+
+        check_events(&self.world, &self.event_systems)?;
+        self.world.apply_commands()
+    }
+
+    /// Run a frame of this world, see [`run_update`] for `delta`.
+    pub fn update(&mut self, delta: Option<Duration>) -> Result<()> {
+        run_update(
+            &mut self.world,
+            &self.fixed_systems,
+            &self.systems,
+            &self.event_systems,
+            delta,
+        )
+    }
+}
+
+/// A message on its way to another world.
+pub(crate) struct Message {
+    pub target: String,
+    /// Rust name of what's in `body`, for reporting it when it can't be delivered.
+    pub type_name: &'static str,
+    pub body: Box<dyn Any>,
+}
+
+/// Messages this world has sent that haven't been handed out yet.
+#[derive(Default)]
+pub struct Outbox {
+    messages: Vec<Message>,
+}
+
+/// Raised in the world that sent a message with [`World::send_to`] when it couldn't be delivered.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct UndeliveredMessage {
+    pub target: String,
+    /// Rust name of the message type.
+    pub message: &'static str,
+    pub reason: UndeliveredReason,
+}
+
+impl EcsEvent for UndeliveredMessage {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UndeliveredReason {
+    /// There's no world with that name.
+    NoSuchWorld,
+    /// The world exists but has no [`Inbox`] to put it in.
+    NoInbox,
+}
+
+/// Messages other worlds sent to this one, grouped by type.
+#[derive(Default)]
+pub struct Inbox {
+    messages: HashMap<TypeId, Vec<Box<dyn Any>>>,
+}
+
+impl World {
+    /// Send `message` to the world called `target`, it can be read there with
+    /// [`World::read_messages`] once the frame's extraction is done.
+    ///
+    /// # Example
+    /// ```rs
+    /// world.send_to("sim", PlaceBuilding { tile, kind });
+    /// ```
+    pub fn send_to<T: Any>(&self, target: &str, message: T) {
+        self.get_resource_mut::<Outbox>().messages.push(Message {
+            target: target.to_string(),
+            type_name: type_name::<T>(),
+            body: Box::new(message),
+        });
+    }
+
+    /// Take every `T` other worlds have sent here, oldest first.
+    pub fn read_messages<T: Any>(&self) -> Vec<T> {
+        self.get_resource_mut::<Inbox>()
+            .messages
+            .remove(&TypeId::of::<T>())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|m| *m.downcast::<T>().unwrap())
+            .collect()
+    }
+
+    pub(crate) fn take_outgoing_messages(&self) -> Vec<Message> {
+        match self.try_get_resource_mut::<Outbox>() {
+            Some(mut outbox) => std::mem::take(&mut outbox.messages),
+            None => vec![],
+        }
+    }
+
+    /// Put `message` in the inbox, or hand it back if there isn't one.
+    pub(crate) fn receive_message(&self, message: Message) -> Result<(), Message> {
+        let Some(mut inbox) = self.try_get_resource_mut::<Inbox>() else {
+            return Err(message);
+        };
+
+        inbox
+            .messages
+            .entry((*message.body).type_id())
+            .or_default()
+            .push(message.body);
+        Ok(())
+    }
+
+    /// Tell this world that it sent `message` for nothing.
+    pub(crate) fn report_undelivered(&self, message: Message, reason: UndeliveredReason) {
+        warn!(
+            "Dropping {} sent to {}: {reason:?}",
+            message.type_name, message.target
+        );
+        self.raise_event(
+            UndeliveredMessage {
+                target: message.target,
+                message: message.type_name,
+                reason,
+            },
+            NoEventData,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{events::LemgineEventData, order_up::OrderUp, test_app::TestApp};
+
+    /// Goes up by one every update of the world it's in.
+    #[derive(Default)]
+    struct Counter(u32);
+
+    /// The last [`Counter`] extracted out of the other world.
+    #[derive(Default)]
+    struct Seen(Option<u32>);
+
+    fn count(world: &World) -> Result<()> {
+        world.get_resource_mut::<Counter>().0 += 1;
+        Ok(())
+    }
+
+    fn extract_counter(from: &World, to: &World) -> Result<()> {
+        to.get_resource_mut::<Seen>().0 = Some(from.get_resource::<Counter>().0);
+        Ok(())
+    }
+
+    fn counting_partial() -> PartialManager {
+        PartialManager::new()
+            .add_resource(Counter::default())
+            .add_resource(Seen::default())
+            .add_systems((count as System,).order_up())
+    }
+
+    fn seen(world: &World) -> Option<u32> {
+        world.get_resource::<Seen>().0
+    }
+
+    #[test]
+    fn extracting_out_of_the_main_world_sees_its_update() {
+        let mut app = TestApp::new()
+            .unwrap()
+            .integrate(counting_partial())
+            .unwrap();
+        app.manager = app
+            .manager
+            .add_world("sim", counting_partial())
+            .unwrap()
+            .add_extract_system(MAIN_WORLD, "sim", extract_counter);
+
+        app.update().unwrap();
+
+        assert_eq!(seen(app.manager.get_world("sim").unwrap()), Some(1));
+        app.update().unwrap();
+        assert_eq!(seen(app.manager.get_world("sim").unwrap()), Some(2));
+    }
+
+    #[test]
+    fn extracting_out_of_a_sub_world_lands_before_the_main_update() {
+        let mut app = TestApp::new()
+            .unwrap()
+            .integrate(counting_partial())
+            .unwrap();
+        app.manager = app
+            .manager
+            .add_world("sim", counting_partial())
+            .unwrap()
+            .add_extract_system("sim", MAIN_WORLD, extract_counter);
+
+        app.update().unwrap();
+
+        assert_eq!(seen(app.world()), Some(1));
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Ping(u32);
+
+    /// Every [`UndeliveredMessage`] the main world heard about.
+    #[derive(Default)]
+    struct Undelivered(Vec<UndeliveredMessage>);
+
+    fn note_undelivered(
+        world: &World,
+        event: &UndeliveredMessage,
+        _: LemgineEventData,
+    ) -> Result<()> {
+        world
+            .get_resource_mut::<Undelivered>()
+            .0
+            .push(event.clone());
+        Ok(())
+    }
+
+    fn messaging_app() -> TestApp {
+        let mut app = TestApp::new()
+            .unwrap()
+            .integrate(
+                PartialManager::new()
+                    .add_resource(Undelivered::default())
+                    .add_typed_event_handler(note_undelivered),
+            )
+            .unwrap();
+        app.manager = app.manager.add_world("sim", PartialManager::new()).unwrap();
+        app
+    }
+
+    fn undelivered(app: &TestApp) -> Vec<UndeliveredMessage> {
+        app.resource::<Undelivered>().0.clone()
+    }
+
+    #[test]
+    fn messages_arrive_on_the_next_update() {
+        let mut app = messaging_app();
+
+        app.world().send_to("sim", Ping(1));
+        app.world().send_to("sim", Ping(2));
+        let sim = app.manager.get_world("sim").unwrap();
+        assert_eq!(sim.read_messages::<Ping>(), []);
+
+        app.update().unwrap();
+
+        let sim = app.manager.get_world("sim").unwrap();
+        assert_eq!(sim.read_messages::<Ping>(), [Ping(1), Ping(2)]);
+        // Reading takes them
+        assert_eq!(sim.read_messages::<Ping>(), []);
+        assert_eq!(undelivered(&app), []);
+    }
+
+    #[test]
+    fn messages_to_unknown_worlds_are_reported() {
+        let mut app = messaging_app();
+
+        app.world().send_to("preview", Ping(1));
+        app.update().unwrap();
+
+        assert_eq!(
+            undelivered(&app),
+            [UndeliveredMessage {
+                target: "preview".to_string(),
+                message: type_name::<Ping>(),
+                reason: UndeliveredReason::NoSuchWorld,
+            }]
+        );
+    }
+
+    #[test]
+    fn messages_to_worlds_without_an_inbox_are_reported() {
+        let mut app = messaging_app();
+        app.manager.worlds[0]
+            .world
+            .resources
+            .remove(&TypeId::of::<Inbox>());
+
+        app.world().send_to("sim", Ping(1));
+        app.update().unwrap();
+
+        assert_eq!(
+            undelivered(&app),
+            [UndeliveredMessage {
+                target: "sim".to_string(),
+                message: type_name::<Ping>(),
+                reason: UndeliveredReason::NoInbox,
+            }]
+        );
+    }
+}
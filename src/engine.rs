@@ -5,15 +5,41 @@ pub use log::*;
 use winit::event::{Event, WindowEvent};
 use winit::event_loop::EventLoopWindowTarget;
 use winit::window::WindowBuilder;
-use winit::{dpi::LogicalSize, event_loop::EventLoop};
+use winit::{
+    dpi::{LogicalSize, PhysicalPosition},
+    event_loop::EventLoop,
+};
 
+use crate::config::EngineConfig;
+use crate::ecs::camera::{Camera2D, active_camera};
+use crate::ecs::exit::AppExit;
+use crate::ecs::input::InputCapture;
+use crate::ecs::input_state::CursorPosition;
 use crate::ecs::order_up::OrderUp;
-use crate::ecs::{StartupSystem, WinitEventSystem, World};
+use crate::ecs::partial_manager::PartialManager;
+use crate::ecs::{ShutdownSystem, StartupSystem, System, WinitEventSystem, World};
+use crate::engine::display::{DisplayState, display_events};
+use crate::engine::frame_pacing::FrameLimiter;
 use crate::engine::gui::GuiApp;
+#[cfg(feature = "gui")]
+use crate::engine::inspector::{Inspector, inspector_events, inspector_open, inspector_ui};
+#[cfg(feature = "gui")]
+use crate::engine::panels::{GuiPanels, draw_panels};
 use crate::engine::vulkan::VulkanApp;
-use crate::systems::prelude::PartialManager;
 
+#[cfg(feature = "gui")]
+pub mod controls;
+pub mod display;
+pub mod frame_pacing;
+#[cfg(feature = "gui")]
 mod gui;
+#[cfg(not(feature = "gui"))]
+#[path = "engine/no_gui.rs"]
+mod gui;
+#[cfg(feature = "gui")]
+mod inspector;
+#[cfg(feature = "gui")]
+pub mod panels;
 mod vertex;
 mod vulkan;
 
@@ -23,35 +49,77 @@ pub struct Engine {
 }
 
 impl Engine {
-    pub fn new(event_loop: &EventLoop<()>) -> Result<Self> {
-        let window = WindowBuilder::new()
-            .with_title("Factory Game")
-            .with_inner_size(LogicalSize::new(1024, 768))
-            .build(event_loop)?;
+    pub fn new(event_loop: &EventLoop<()>, config: &EngineConfig) -> Result<Self> {
+        let monitor = display::pick_monitor(event_loop, config.monitor.as_deref())
+            .or_else(|| event_loop.primary_monitor());
+        let mut builder = WindowBuilder::new()
+            .with_title(&config.title)
+            .with_inner_size(LogicalSize::new(config.width, config.height))
+            .with_fullscreen(display::fullscreen(config.window_mode, monitor));
+        if let Some([x, y]) = config.position {
+            let position = PhysicalPosition::new(x, y);
+            match display::on_a_monitor(event_loop, position) {
+                true => builder = builder.with_position(position),
+                false => warn!("Window position {x}, {y} isn't on any monitor, ignoring it"),
+            }
+        }
+        let window = builder.build(event_loop)?;
         info!("Creating vulkan app");
-        let vulkan_app = unsafe { VulkanApp::create(window)? };
+        let vulkan_app = unsafe { VulkanApp::create(window, config)? };
         Ok(Self {
             vulkan_app,
             minimized: false,
         })
     }
+
+    /// The size of the window's contents in pixels.
+    pub fn viewport(&self) -> [f32; 2] {
+        let size = self.vulkan_app.window.inner_size();
+        [size.width as f32, size.height as f32]
+    }
+
+    /// The camera to draw from, a default one looking at the middle of the world without any.
+    fn camera(&self, world: &World) -> Camera2D {
+        active_camera(world).unwrap_or_else(|| {
+            let mut camera = Camera2D::new();
+            camera.viewport = self.viewport();
+            camera
+        })
+    }
 }
 
-const DT_FPS_60_NANO: u128 = 1_000_000_000 / 60;
-pub struct RedrawTime(Instant);
 pub struct AccumulatedTime(Instant);
 pub struct FPSCounter(u32);
 
 pub fn engine_partial() -> PartialManager {
+    #[cfg(feature = "gui")]
+    let winit_event_systems = (
+        inspector_events as WinitEventSystem,
+        draw_panels as WinitEventSystem,
+        display_events as WinitEventSystem,
+        engine_main as WinitEventSystem,
+        engine_events as WinitEventSystem,
+    )
+        .order_up();
+    #[cfg(not(feature = "gui"))]
+    let winit_event_systems = (
+        display_events as WinitEventSystem,
+        engine_main as WinitEventSystem,
+        engine_events as WinitEventSystem,
+    )
+        .order_up();
+
     PartialManager::new()
         .add_startup_systems((engine_startup as StartupSystem,).order_up())
-        .add_winit_event_systems(
+        .add_systems(
             (
-                engine_main as WinitEventSystem,
-                engine_events as WinitEventSystem,
+                update_camera_viewports as System,
+                update_cursor_world as System,
             )
                 .order_up(),
         )
+        .add_winit_event_systems(winit_event_systems)
+        .add_shutdown_systems((engine_shutdown as ShutdownSystem,).order_up())
 }
 
 pub fn engine_events(
@@ -69,29 +137,103 @@ pub fn engine_events(
     let mut engine = engine.unwrap();
 
     if let Event::WindowEvent { window_id, event } = event {
-        engine.vulkan_app.window_events(&event);
+        let capture = engine.vulkan_app.window_events(&event);
+        if let Some(mut input_capture) = world.try_get_resource_mut::<InputCapture>() {
+            **input_capture = capture;
+        }
     };
 
     Ok(())
 }
 
+/// Keeps every [`Camera2D`] the same shape as the window.
+pub fn update_camera_viewports(world: &World) -> Result<()> {
+    let Some(engine) = world.try_get_resource::<Engine>() else {
+        return Ok(());
+    };
+    let viewport = engine.viewport();
+    if viewport[0] == 0.0 || viewport[1] == 0.0 {
+        return Ok(());
+    }
+    for (_, mut camera) in world.query_mut::<Camera2D>() {
+        camera.viewport = viewport;
+    }
+    Ok(())
+}
+
+/// Works out where the cursor is in the world, after
+/// [`input_partial`](crate::ecs::input_state::input_partial) found out where it is in the window.
+pub fn update_cursor_world(world: &World) -> Result<()> {
+    let (Some(mut cursor), Some(engine)) = (
+        world.try_get_resource_mut::<CursorPosition>(),
+        world.try_get_resource::<Engine>(),
+    ) else {
+        return Ok(());
+    };
+    let camera = engine.camera(world);
+    cursor.world = cursor
+        .window
+        .and_then(|position| engine.vulkan_app.screen_to_world(&camera, position));
+    Ok(())
+}
+
+/// Opens the window with the [`EngineConfig`] resource, adding the default one if there isn't one.
 pub fn engine_startup(world: &mut World, event_loop: &EventLoop<()>) -> Result<()> {
-    let engine = Engine::new(event_loop)?;
+    world.add_resource(EngineConfig::default());
+    let engine = Engine::new(event_loop, &world.get_resource::<EngineConfig>())?;
 
-    world.add_resource(RedrawTime(Instant::now()));
+    let display = DisplayState::new(
+        &world.get_resource::<EngineConfig>(),
+        &engine.vulkan_app.window,
+    );
+    world.add_resource(display);
+    world.add_resource(FrameLimiter::new());
+    world.add_resource(InputCapture::default());
     world.add_resource(AccumulatedTime(Instant::now()));
     world.add_resource(FPSCounter(0));
+    #[cfg(feature = "gui")]
+    {
+        world.add_resource(Inspector::default());
+        let mut panels = GuiPanels::default();
+        panels.add(inspector_open, inspector_ui);
+        world.add_resource(panels);
+    }
     world.add_resource(engine);
 
     Ok(())
 }
 
+/// Saves the window state and destroys the Vulkan app. Shutdown systems run in the reverse order
+/// their plugins were integrated, so this only runs after every other plugin has shut down when
+/// the engine is integrated before any other plugin with shutdown systems.
+pub fn engine_shutdown(world: &mut World) -> Result<()> {
+    let Some(mut engine) = world.try_get_resource_mut::<Engine>() else {
+        return Ok(());
+    };
+
+    let remember_window = world
+        .try_get_resource::<EngineConfig>()
+        .is_some_and(|config| config.remember_window);
+    if remember_window && let Some(display) = world.try_get_resource::<DisplayState>() {
+        let state = display.window_state(&engine.vulkan_app.window);
+        if let Err(err) = state.save() {
+            warn!("Couldn't save the window state: {err}");
+        }
+    }
+
+    unsafe {
+        engine.vulkan_app.destroy();
+    }
+
+    Ok(())
+}
+
 pub fn engine_main(
     world: &World,
     event: Event<()>,
     elwt: &EventLoopWindowTarget<()>,
 ) -> Result<()> {
-    let mut redraw_time = world.get_resource_mut::<RedrawTime>();
+    let mut frame_limiter = world.get_resource_mut::<FrameLimiter>();
     let mut accumulated_time = world.get_resource_mut::<AccumulatedTime>();
 
     let mut fps_counter = world.get_resource_mut::<FPSCounter>();
@@ -104,6 +246,10 @@ pub fn engine_main(
     }
 
     let mut engine = engine.unwrap();
+    let (present_mode, min_frame_time) = match world.try_get_resource::<EngineConfig>() {
+        Some(config) => (config.present_mode, config.min_frame_time()),
+        None => Default::default(),
+    };
 
     if accumulated_time.0.elapsed().as_secs_f32() > 1.0 {
         info!("FPS: {}", fps_counter.0);
@@ -118,14 +264,12 @@ pub fn engine_main(
         }
         Event::WindowEvent { event, .. } => match event {
             // Render a frame if our Vulkan app is not being destroyed.
-            WindowEvent::RedrawRequested
-                if !elwt.exiting()
-                    && !engine.minimized
-                    && redraw_time.0.elapsed().as_nanos() > DT_FPS_60_NANO =>
-            unsafe {
-                engine.vulkan_app.render().unwrap();
+            WindowEvent::RedrawRequested if !elwt.exiting() && !engine.minimized => unsafe {
+                engine.vulkan_app.set_present_mode(present_mode);
+                frame_limiter.wait(min_frame_time);
+                let camera = engine.camera(world);
+                engine.vulkan_app.render(&camera).unwrap();
                 fps_counter.0 += 1;
-                redraw_time.0 = Instant::now();
             },
             WindowEvent::Resized(size) => {
                 if size.width == 0 || size.height == 0 {
@@ -135,12 +279,9 @@ pub fn engine_main(
                     engine.vulkan_app.resized = true;
                 }
             }
-            // Destroy our Vulkan app.
+            // The Vulkan app gets destroyed in `engine_shutdown` once the frame is over.
             WindowEvent::CloseRequested => {
-                elwt.exit();
-                unsafe {
-                    engine.vulkan_app.destroy();
-                }
+                world.exit(AppExit::Success);
             }
             _ => {}
         },
@@ -0,0 +1,263 @@
+//! A window for rebinding the [`InputMap`] while playing. Toggle it with F10.
+//!
+//! Changes are saved to `input.toml` right away.
+
+use anyhow::Result;
+use egui::{Button, Context, Grid, Window};
+use log::*;
+use winit::{
+    event::{ElementState, Event, WindowEvent},
+    event_loop::{EventLoop, EventLoopWindowTarget},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+use crate::{
+    ecs::{
+        StartupSystem, WinitEventSystem, World,
+        actions::{Action, InputMap},
+        order_up::OrderUp,
+        partial_manager::PartialManager,
+    },
+    engine::panels::GuiPanels,
+};
+
+#[derive(Default)]
+pub struct ControlsWindow {
+    pub open: bool,
+}
+
+/// Toggles the controls window on F10.
+pub fn controls_events(
+    world: &World,
+    event: Event<()>,
+    _: &EventLoopWindowTarget<()>,
+) -> Result<()> {
+    if let Event::WindowEvent {
+        event: WindowEvent::KeyboardInput { event, .. },
+        ..
+    } = event
+        && event.physical_key == PhysicalKey::Code(KeyCode::F10)
+        && event.state == ElementState::Pressed
+        && !event.repeat
+    {
+        let mut controls = world.get_resource_mut::<ControlsWindow>();
+        controls.open = !controls.open;
+        debug!("Controls open: {}", controls.open);
+    }
+
+    Ok(())
+}
+
+fn controls_open(world: &World) -> bool {
+    world.get_resource::<ControlsWindow>().open
+}
+
+/// Every action with a button per binding. Clicking one waits for a new key, right clicking
+/// removes it.
+pub fn controls_ui<A: Action>(ctx: &Context, world: &World) {
+    let mut map = world.get_resource_mut::<InputMap<A>>();
+    let capturing = map.capturing();
+    let mut changed = false;
+    let mut open = true;
+
+    Window::new("Controls").open(&mut open).show(ctx, |ui| {
+        Grid::new("controls").striped(true).show(ui, |ui| {
+            for &action in A::ALL {
+                ui.label(action.name());
+                ui.horizontal(|ui| {
+                    for (i, binding) in map.bindings(action).to_vec().into_iter().enumerate() {
+                        let text = match capturing == Some((action, Some(i))) {
+                            true => "Press a key...".to_string(),
+                            false => binding.to_string(),
+                        };
+                        let response = ui.button(text);
+                        if response.clicked() {
+                            map.start_capture(action, Some(i));
+                        } else if response.secondary_clicked() {
+                            map.unbind(action, i);
+                            changed = true;
+                        }
+                    }
+                    let text = match capturing == Some((action, None)) {
+                        true => "Press a key...",
+                        false => "+",
+                    };
+                    if ui.button(text).clicked() {
+                        map.start_capture(action, None);
+                    }
+                });
+                ui.end_row();
+            }
+        });
+
+        ui.separator();
+        ui.label("Click a binding to change it, right click to remove it. Escape cancels.");
+        if ui
+            .add_enabled(!map.is_default(), Button::new("Reset to defaults"))
+            .clicked()
+        {
+            map.reset();
+            changed = true;
+        }
+    });
+
+    if changed && let Err(err) = map.save() {
+        warn!("Couldn't save the controls: {err}");
+    }
+    if !open {
+        map.cancel_capture();
+        world.get_resource_mut::<ControlsWindow>().open = false;
+    }
+}
+
+fn add_controls_panel<A: Action>(world: &mut World, _: &EventLoop<()>) -> Result<()> {
+    world.add_resource(ControlsWindow::default());
+    world
+        .get_resource_mut::<GuiPanels>()
+        .add(controls_open, controls_ui::<A>);
+    Ok(())
+}
+
+/// The controls window for the bindings of `A`, add it after
+/// [`actions_partial`](crate::ecs::actions::actions_partial).
+pub fn controls_partial<A: Action>() -> PartialManager {
+    PartialManager::new()
+        .add_startup_systems((add_controls_panel::<A> as StartupSystem,).order_up())
+        .add_winit_event_systems((controls_events as WinitEventSystem,).order_up())
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::{Event, Modifiers, Pos2, RawInput, Rect, Shape, epaint::ClippedShape, vec2};
+
+    use super::*;
+    use crate::ecs::actions::{Binding, InputButton};
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    enum TestAction {
+        Build,
+        Save,
+    }
+
+    impl Action for TestAction {
+        const ALL: &'static [Self] = &[TestAction::Build, TestAction::Save];
+    }
+
+    /// The controls window drawn by a headless egui.
+    struct ControlsGui {
+        ctx: Context,
+        world: World,
+    }
+
+    impl ControlsGui {
+        fn new() -> Self {
+            let mut world = World::new();
+            world.add_resource(InputMap::new().bind(TestAction::Build, KeyCode::KeyB).bind(
+                TestAction::Save,
+                Binding::new([InputButton::Ctrl, KeyCode::KeyS.into()]),
+            ));
+            world.add_resource(ControlsWindow { open: true });
+
+            let gui = Self {
+                ctx: Context::default(),
+                world,
+            };
+            // New windows are only sized on their first frame
+            gui.frame(vec![]);
+            gui
+        }
+
+        /// Run a frame with `events`, returns every piece of text drawn and where its middle is.
+        fn frame(&self, events: Vec<Event>) -> Vec<(String, Pos2)> {
+            let input = RawInput {
+                screen_rect: Some(Rect::from_min_size(Pos2::ZERO, vec2(1280.0, 720.0))),
+                events,
+                ..Default::default()
+            };
+            let output = self
+                .ctx
+                .run(input, |ctx| controls_ui::<TestAction>(ctx, &self.world));
+
+            fn texts(shape: &Shape, found: &mut Vec<(String, Pos2)>) {
+                match shape {
+                    Shape::Text(text) => found.push((
+                        text.galley.text().to_string(),
+                        text.pos + text.galley.rect.center().to_vec2(),
+                    )),
+                    Shape::Vec(shapes) => shapes.iter().for_each(|s| texts(s, found)),
+                    _ => {}
+                }
+            }
+            let mut found = vec![];
+            for ClippedShape { shape, .. } in &output.shapes {
+                texts(shape, &mut found);
+            }
+            found
+        }
+
+        fn texts(&self) -> Vec<String> {
+            self.frame(vec![])
+                .into_iter()
+                .map(|(text, _)| text)
+                .collect()
+        }
+
+        /// Click on the text `label` with `button`.
+        fn click(&self, label: &str, button: egui::PointerButton) {
+            let (_, pos) = self
+                .frame(vec![])
+                .into_iter()
+                .find(|(text, _)| text == label)
+                .unwrap_or_else(|| panic!("Nothing says {label:?}"));
+            let press = |pressed| Event::PointerButton {
+                pos,
+                button,
+                pressed,
+                modifiers: Modifiers::NONE,
+            };
+            self.frame(vec![Event::PointerMoved(pos), press(true)]);
+            self.frame(vec![press(false)]);
+        }
+
+        fn map(&self) -> std::sync::MappedRwLockReadGuard<'_, Box<InputMap<TestAction>>> {
+            self.world.get_resource::<InputMap<TestAction>>()
+        }
+    }
+
+    #[test]
+    fn every_action_and_binding_is_listed() {
+        let gui = ControlsGui::new();
+
+        let texts = gui.texts();
+
+        for text in ["Build", "KeyB", "Save", "Ctrl+KeyS"] {
+            assert!(texts.iter().any(|t| t == text), "{text} isn't in {texts:?}");
+        }
+    }
+
+    #[test]
+    fn clicking_a_binding_waits_for_a_key() {
+        let gui = ControlsGui::new();
+
+        gui.click("Ctrl+KeyS", egui::PointerButton::Primary);
+
+        assert_eq!(gui.map().capturing(), Some((TestAction::Save, Some(0))));
+        assert!(gui.texts().iter().any(|t| t == "Press a key..."));
+    }
+
+    #[test]
+    fn right_clicking_a_binding_removes_it_and_reset_brings_it_back() {
+        let gui = ControlsGui::new();
+        assert!(gui.map().is_default());
+
+        gui.click("KeyB", egui::PointerButton::Secondary);
+        assert_eq!(gui.map().bindings(TestAction::Build), []);
+        assert!(!gui.map().is_default());
+
+        gui.click("Reset to defaults", egui::PointerButton::Primary);
+        assert_eq!(
+            gui.map().bindings(TestAction::Build),
+            [KeyCode::KeyB.into()]
+        );
+    }
+}
@@ -0,0 +1,414 @@
+//! Windowed and fullscreen, which monitor, and where the window was left.
+//!
+//! [`EngineConfig::window_mode`] and [`EngineConfig::monitor`] are the source of truth, change them
+//! and the window follows before the next frame. Alt+Enter flips between windowed and whichever
+//! fullscreen was used last. The swapchain is remade after every switch.
+
+use anyhow::Result;
+use log::*;
+use winit::{
+    dpi::{LogicalSize, PhysicalPosition, PhysicalSize},
+    event::{ElementState, Event, WindowEvent},
+    event_loop::EventLoopWindowTarget,
+    keyboard::{KeyCode, ModifiersState, PhysicalKey},
+    monitor::{MonitorHandle, VideoMode},
+    window::{Fullscreen, Window},
+};
+
+use crate::config::{EngineConfig, WindowMode, WindowState};
+use crate::ecs::World;
+use crate::engine::Engine;
+
+/// What the window is actually set to, compared against the [`EngineConfig`] to see what changed.
+pub struct DisplayState {
+    window_mode: WindowMode,
+    monitor: Option<String>,
+    /// What Alt+Enter goes to from windowed.
+    fullscreen_mode: WindowMode,
+    /// Where the window was and how big while it was last windowed, fullscreen doesn't count.
+    windowed_position: Option<PhysicalPosition<i32>>,
+    windowed_size: LogicalSize<u32>,
+    modifiers: ModifiersState,
+}
+
+impl DisplayState {
+    pub fn new(config: &EngineConfig, window: &Window) -> Self {
+        Self::from_config(config, window.outer_position().ok())
+    }
+
+    /// The state of a window just made from `config`, which ended up at `window_position`.
+    fn from_config(config: &EngineConfig, window_position: Option<PhysicalPosition<i32>>) -> Self {
+        let windowed_position = match config.window_mode {
+            WindowMode::Windowed => window_position,
+            _ => config.position.map(|[x, y]| PhysicalPosition::new(x, y)),
+        };
+        Self {
+            window_mode: config.window_mode,
+            monitor: config.monitor.clone(),
+            fullscreen_mode: match config.window_mode {
+                WindowMode::Windowed => WindowMode::Borderless,
+                mode => mode,
+            },
+            windowed_position,
+            windowed_size: LogicalSize::new(config.width, config.height),
+            modifiers: ModifiersState::empty(),
+        }
+    }
+
+    pub fn window_mode(&self) -> WindowMode {
+        self.window_mode
+    }
+
+    /// The mode Alt+Enter switches to from `mode`.
+    fn toggled(&self, mode: WindowMode) -> WindowMode {
+        match mode {
+            WindowMode::Windowed => self.fullscreen_mode,
+            _ => WindowMode::Windowed,
+        }
+    }
+
+    /// Remember that the window now is what `config` says.
+    fn switched(&mut self, config: &EngineConfig) {
+        if config.window_mode.is_fullscreen() {
+            self.fullscreen_mode = config.window_mode;
+        }
+        self.window_mode = config.window_mode;
+        self.monitor = config.monitor.clone();
+    }
+
+    /// Switch the window to whatever `config` says, if that's different from what it is now.
+    fn apply(
+        &mut self,
+        config: &EngineConfig,
+        engine: &mut Engine,
+        elwt: &EventLoopWindowTarget<()>,
+    ) {
+        if config.window_mode == self.window_mode && config.monitor == self.monitor {
+            return;
+        }
+
+        let window = &engine.vulkan_app.window;
+        let monitor =
+            pick_monitor(elwt, config.monitor.as_deref()).or_else(|| window.current_monitor());
+        info!(
+            "Switching to {} on {}",
+            config.window_mode,
+            monitor_name(monitor.as_ref())
+        );
+
+        match config.window_mode {
+            WindowMode::Windowed => {
+                window.set_fullscreen(None);
+                if self.window_mode.is_fullscreen() {
+                    let _ = window.request_inner_size(self.windowed_size);
+                    if let Some(position) = self.windowed_position {
+                        window.set_outer_position(position);
+                    }
+                } else if let Some(monitor) = monitor {
+                    // Only the monitor changed, move over to the middle of it
+                    let size = window.outer_size();
+                    let position = PhysicalPosition::new(
+                        monitor.position().x
+                            + (monitor.size().width as i32 - size.width as i32) / 2,
+                        monitor.position().y
+                            + (monitor.size().height as i32 - size.height as i32) / 2,
+                    );
+                    window.set_outer_position(position);
+                }
+            }
+            mode => window.set_fullscreen(fullscreen(mode, monitor)),
+        }
+
+        self.switched(config);
+        engine.vulkan_app.resized = true;
+    }
+
+    /// What to save for next time.
+    pub fn window_state(&self, window: &Window) -> WindowState {
+        self.saved_state(window.current_monitor().and_then(|m| m.name()))
+    }
+
+    /// What to save with the window on the monitor called `current_monitor`.
+    fn saved_state(&self, current_monitor: Option<String>) -> WindowState {
+        WindowState {
+            width: self.windowed_size.width,
+            height: self.windowed_size.height,
+            position: self.windowed_position.map(|p| [p.x, p.y]),
+            window_mode: self.window_mode,
+            monitor: match self.window_mode {
+                WindowMode::Windowed => self.monitor.clone(),
+                _ => current_monitor.or_else(|| self.monitor.clone()),
+            },
+        }
+    }
+}
+
+/// The first monitor with `wanted` in its name or at that index, `None` if nothing was asked
+/// for or nothing matches.
+pub fn pick_monitor(
+    elwt: &EventLoopWindowTarget<()>,
+    wanted: Option<&str>,
+) -> Option<MonitorHandle> {
+    let wanted = wanted?;
+    let names: Vec<_> = elwt.available_monitors().map(|m| m.name()).collect();
+    let Some(index) = monitor_index(&names, wanted) else {
+        warn!("No monitor matches the configured monitor {wanted:?}");
+        return None;
+    };
+    elwt.available_monitors().nth(index)
+}
+
+/// Where the first of the monitors called `names` with `wanted` in its name or at that index is.
+fn monitor_index(names: &[Option<String>], wanted: &str) -> Option<usize> {
+    let wanted_lower = wanted.to_lowercase();
+    names.iter().enumerate().position(|(i, name)| {
+        wanted.parse() == Ok(i)
+            || name
+                .as_ref()
+                .is_some_and(|name| name.to_lowercase().contains(&wanted_lower))
+    })
+}
+
+/// What to pass to winit for `mode`. Without a monitor exclusive fullscreen has nothing to take
+/// over, so it ends up borderless.
+pub fn fullscreen(mode: WindowMode, monitor: Option<MonitorHandle>) -> Option<Fullscreen> {
+    match mode {
+        WindowMode::Windowed => None,
+        WindowMode::Borderless => Some(Fullscreen::Borderless(monitor)),
+        WindowMode::Exclusive => match monitor.as_ref().and_then(best_video_mode) {
+            Some(video_mode) => Some(Fullscreen::Exclusive(video_mode)),
+            None => {
+                warn!(
+                    "No video mode for exclusive fullscreen on {}, going borderless",
+                    monitor_name(monitor.as_ref())
+                );
+                Some(Fullscreen::Borderless(monitor))
+            }
+        },
+    }
+}
+
+/// The monitor's native resolution at the highest refresh rate it has.
+fn best_video_mode(monitor: &MonitorHandle) -> Option<VideoMode> {
+    let modes: Vec<_> = monitor.video_modes().collect();
+    let infos: Vec<_> = modes.iter().map(ModeInfo::of).collect();
+    let best = best_mode_index(monitor.size(), &infos)?;
+    modes.into_iter().nth(best)
+}
+
+/// The parts of a [`VideoMode`] that [`best_video_mode`] goes by.
+#[derive(Clone, Copy, Debug)]
+struct ModeInfo {
+    size: PhysicalSize<u32>,
+    refresh_rate_millihertz: u32,
+    bit_depth: u16,
+}
+
+impl ModeInfo {
+    fn of(mode: &VideoMode) -> Self {
+        Self {
+            size: mode.size(),
+            refresh_rate_millihertz: mode.refresh_rate_millihertz(),
+            bit_depth: mode.bit_depth(),
+        }
+    }
+}
+
+/// Which of `modes` is the best on a monitor `native` pixels big, the native size first and then
+/// the biggest, fastest and deepest.
+fn best_mode_index(native: PhysicalSize<u32>, modes: &[ModeInfo]) -> Option<usize> {
+    (0..modes.len()).max_by_key(|&i| {
+        let mode = modes[i];
+        (
+            mode.size == native,
+            mode.size.width * mode.size.height,
+            mode.refresh_rate_millihertz,
+            mode.bit_depth,
+        )
+    })
+}
+
+/// Whether a window with its corner at `position` would be on some monitor, a saved position
+/// can be left over from a monitor that isn't plugged in anymore.
+pub fn on_a_monitor(elwt: &EventLoopWindowTarget<()>, position: PhysicalPosition<i32>) -> bool {
+    elwt.available_monitors().any(|monitor| {
+        let (min, size) = (monitor.position(), monitor.size());
+        (min.x..min.x + size.width as i32).contains(&position.x)
+            && (min.y..min.y + size.height as i32).contains(&position.y)
+    })
+}
+
+fn monitor_name(monitor: Option<&MonitorHandle>) -> String {
+    monitor
+        .and_then(|m| m.name())
+        .unwrap_or_else(|| "the current monitor".to_string())
+}
+
+/// Alt+Enter, keeping track of the windowed position and size, and following the config.
+pub fn display_events(
+    world: &World,
+    event: Event<()>,
+    elwt: &EventLoopWindowTarget<()>,
+) -> Result<()> {
+    let (Some(mut engine), Some(mut display)) = (
+        world.try_get_resource_mut::<Engine>(),
+        world.try_get_resource_mut::<DisplayState>(),
+    ) else {
+        return Ok(());
+    };
+    let windowed = display.window_mode == WindowMode::Windowed;
+
+    match event {
+        Event::WindowEvent { event, .. } => match event {
+            WindowEvent::ModifiersChanged(modifiers) => display.modifiers = modifiers.state(),
+            WindowEvent::KeyboardInput { event, .. }
+                if event.state == ElementState::Pressed
+                    && !event.repeat
+                    && event.physical_key == PhysicalKey::Code(KeyCode::Enter)
+                    && display.modifiers.alt_key() =>
+            {
+                let mut config = world.get_resource_mut::<EngineConfig>();
+                config.window_mode = display.toggled(config.window_mode);
+            }
+            WindowEvent::Moved(position) if windowed => display.windowed_position = Some(position),
+            WindowEvent::Resized(size) if windowed && size.width > 0 && size.height > 0 => {
+                display.windowed_size = size.to_logical(engine.vulkan_app.window.scale_factor());
+            }
+            _ => {}
+        },
+        Event::AboutToWait => {
+            if let Some(config) = world.try_get_resource::<EngineConfig>() {
+                display.apply(&config, &mut engine, elwt);
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(window_mode: WindowMode) -> EngineConfig {
+        EngineConfig {
+            window_mode,
+            ..Default::default()
+        }
+    }
+
+    /// Alt+Enter the way `display_events` and `DisplayState::apply` do it.
+    fn alt_enter(display: &mut DisplayState, config: &mut EngineConfig) {
+        config.window_mode = display.toggled(config.window_mode);
+        display.switched(config);
+    }
+
+    #[test]
+    fn alt_enter_goes_between_windowed_and_the_last_fullscreen() {
+        let mut config = config(WindowMode::Windowed);
+        let mut display = DisplayState::from_config(&config, None);
+
+        alt_enter(&mut display, &mut config);
+        assert_eq!(config.window_mode, WindowMode::Borderless);
+        alt_enter(&mut display, &mut config);
+        assert_eq!(config.window_mode, WindowMode::Windowed);
+
+        // Switching to exclusive some other way makes it the one Alt+Enter goes back to
+        config.window_mode = WindowMode::Exclusive;
+        display.switched(&config);
+        alt_enter(&mut display, &mut config);
+        assert_eq!(config.window_mode, WindowMode::Windowed);
+        alt_enter(&mut display, &mut config);
+        assert_eq!(config.window_mode, WindowMode::Exclusive);
+    }
+
+    #[test]
+    fn starting_fullscreen_toggles_back_to_it() {
+        let mut config = config(WindowMode::Exclusive);
+        let mut display = DisplayState::from_config(&config, None);
+
+        alt_enter(&mut display, &mut config);
+        alt_enter(&mut display, &mut config);
+
+        assert_eq!(config.window_mode, WindowMode::Exclusive);
+    }
+
+    #[test]
+    fn monitors_are_picked_by_name_or_index() {
+        let names = [
+            Some("Dell (DP-2)".to_string()),
+            None,
+            Some("LG (HDMI-3)".to_string()),
+        ];
+
+        assert_eq!(monitor_index(&names, "hdmi"), Some(2));
+        assert_eq!(monitor_index(&names, "dp-2"), Some(0));
+        assert_eq!(monitor_index(&names, "1"), Some(1));
+        // The first match wins, whether it's by name or index
+        assert_eq!(monitor_index(&names, "2"), Some(0));
+        // Nothing matching falls back to whatever monitor the window is on
+        assert_eq!(monitor_index(&names, "5"), None);
+        assert_eq!(monitor_index(&names, "VGA"), None);
+        assert_eq!(monitor_index(&[], "0"), None);
+    }
+
+    #[test]
+    fn the_best_video_mode_is_native_then_fastest() {
+        let mode = |width, height, hz: u32, bit_depth| ModeInfo {
+            size: PhysicalSize::new(width, height),
+            refresh_rate_millihertz: hz * 1000,
+            bit_depth,
+        };
+        let native = PhysicalSize::new(2560, 1440);
+        let modes = [
+            mode(3840, 2160, 60, 32),
+            mode(2560, 1440, 60, 32),
+            mode(2560, 1440, 144, 24),
+            mode(2560, 1440, 144, 32),
+            mode(1920, 1080, 240, 32),
+        ];
+
+        assert_eq!(best_mode_index(native, &modes), Some(3));
+        // Without the native size the biggest wins
+        assert_eq!(best_mode_index(PhysicalSize::new(1, 1), &modes), Some(0));
+        assert_eq!(best_mode_index(native, &[]), None);
+    }
+
+    #[test]
+    fn window_state_round_trips() {
+        let config = EngineConfig {
+            width: 1600,
+            height: 900,
+            position: Some([100, 50]),
+            monitor: Some("DP-1".to_string()),
+            ..config(WindowMode::Windowed)
+        };
+        let display = DisplayState::from_config(&config, Some(PhysicalPosition::new(100, 50)));
+
+        let state = display.saved_state(Some("HDMI-1".to_string()));
+        let saved = toml::from_str::<WindowState>(&toml::to_string(&state).unwrap()).unwrap();
+        let mut loaded = EngineConfig::default();
+        saved.apply(&mut loaded);
+
+        assert_eq!(loaded, config);
+        let reloaded = DisplayState::from_config(&loaded, Some(PhysicalPosition::new(100, 50)));
+        assert_eq!(reloaded.saved_state(None), state);
+    }
+
+    #[test]
+    fn fullscreen_saves_the_monitor_its_on() {
+        let config = EngineConfig {
+            position: Some([100, 50]),
+            ..config(WindowMode::Borderless)
+        };
+        let display = DisplayState::from_config(&config, None);
+
+        let state = display.saved_state(Some("HDMI-1".to_string()));
+
+        assert_eq!(state.window_mode, WindowMode::Borderless);
+        assert_eq!(state.monitor.as_deref(), Some("HDMI-1"));
+        // Where to go back to when windowed
+        assert_eq!(state.position, Some([100, 50]));
+    }
+}
@@ -0,0 +1,114 @@
+//! Keeping frames evenly spaced when there's an FPS cap.
+//!
+//! The present mode decides how frames wait for the display, the [`FrameLimiter`] only makes sure
+//! they don't come faster than [`EngineConfig::fps_cap`](crate::config::EngineConfig::fps_cap).
+//! With vsync and a cap above the refresh rate it never has to wait at all.
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Sleeping is only good to about a millisecond, the last bit is spun away instead.
+const SPIN_TIME: Duration = Duration::from_millis(1);
+
+/// When the next frame is allowed to start.
+#[derive(Debug, Default)]
+pub struct FrameLimiter {
+    next_frame: Option<Instant>,
+}
+
+impl FrameLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Block until the next frame is due, `None` for no cap.
+    ///
+    /// Frames are scheduled from when the last one was due rather than when it ended, so the
+    /// average comes out right even when a sleep oversleeps. A frame that's already late starts
+    /// right away and the schedule starts over from it instead of rushing to catch up.
+    pub fn wait(&mut self, frame_time: Option<Duration>) {
+        let Some(frame_time) = frame_time else {
+            self.next_frame = None;
+            return;
+        };
+
+        let now = Instant::now();
+        let due = match self.next_frame {
+            Some(due) if due > now => due,
+            _ => now,
+        };
+
+        if let Some(sleep) = due.checked_duration_since(now + SPIN_TIME) {
+            thread::sleep(sleep);
+        }
+        while Instant::now() < due {
+            std::hint::spin_loop();
+        }
+
+        self.next_frame = Some(due + frame_time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME_TIME: Duration = Duration::from_millis(5);
+
+    #[test]
+    fn frames_are_spaced_by_the_frame_time() {
+        let mut limiter = FrameLimiter::new();
+        let frames = 20;
+
+        let start = Instant::now();
+        for _ in 0..frames {
+            limiter.wait(Some(FRAME_TIME));
+        }
+        let elapsed = start.elapsed();
+
+        // The first frame doesn't wait. Nothing to check above, a busy machine makes frames late
+        let expected = FRAME_TIME * (frames - 1);
+        assert!(elapsed >= expected, "{elapsed:?} is under {expected:?}");
+    }
+
+    #[test]
+    fn a_late_frame_doesnt_cause_a_burst() {
+        let mut limiter = FrameLimiter::new();
+        limiter.wait(Some(FRAME_TIME));
+
+        thread::sleep(FRAME_TIME * 6);
+        let late = Instant::now();
+        limiter.wait(Some(FRAME_TIME));
+        // Scheduled from the late frame, not from the one it missed
+        let next_frame = limiter.next_frame.unwrap();
+        assert!(
+            next_frame >= late + FRAME_TIME,
+            "The schedule didn't start over"
+        );
+
+        // Starts over from the late frame instead of running the missed ones back to back
+        let after_late = Instant::now();
+        for _ in 0..3 {
+            limiter.wait(Some(FRAME_TIME));
+        }
+        let elapsed = after_late.elapsed();
+        assert!(
+            elapsed >= FRAME_TIME * 3 - SPIN_TIME,
+            "{elapsed:?} is a burst"
+        );
+    }
+
+    #[test]
+    fn no_cap_doesnt_wait() {
+        let mut limiter = FrameLimiter::new();
+        limiter.wait(Some(Duration::from_secs(10)));
+
+        let start = Instant::now();
+        limiter.wait(None);
+        limiter.wait(None);
+
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -4,13 +4,14 @@ use std::hash::Hash;
 use anyhow::Result;
 use cgmath::{Vector3, vec2, vec3};
 use egui::epaint::Primitive;
-use egui::{CentralPanel, ClippedPrimitive, Context, ViewportId, ViewportInfo};
+use egui::{ClippedPrimitive, Context, FullOutput, RawInput, ViewportId, ViewportInfo};
 use egui_winit::{State, update_viewport_info};
 use log::*;
 use vulkanalia::vk::*;
 use winit::event::WindowEvent;
 use winit::window::Window;
 
+use crate::ecs::input::InputCapture;
 use crate::engine::vertex::Vertex;
 use crate::engine::vulkan::VulkanData;
 use crate::engine::vulkan::buffer_manager::buffer_pair::{
@@ -20,9 +21,10 @@ use crate::engine::vulkan::buffer_manager::{
     AllocateBufferType, BufferManager, BufferManagerCopyType, BufferManagerDataType,
     buffer_pair::BufferPair,
 };
-use crate::engine::vulkan::prelude::{create_index_buffer, create_vertex_buffer};
 
 pub struct GuiVulkanInfo {
+    /// The swapchain image the buffers were made for.
+    pub image: usize,
     pub buffer_count: u32,
     pub vertex_lengths: Vec<u32>,
     pub index_lengths: Vec<u32>,
@@ -39,7 +41,10 @@ impl GuiVulkanInfo {
             vertex_lengths.push(self.vertex_lengths[i as usize]);
             vertex_buffers.push(
                 buffer_manager
-                    .get_standard_buffer(StandardBufferMaps::GuiVertices(i as usize))
+                    .get_standard_buffer(StandardBufferMaps::GuiVertices {
+                        image: self.image,
+                        primitive: i as usize,
+                    })
                     .buffer,
             );
         }
@@ -55,7 +60,10 @@ impl GuiVulkanInfo {
             index_lengths.push(self.index_lengths[i as usize]);
             index_buffers.push(
                 buffer_manager
-                    .get_standard_buffer(StandardBufferMaps::GuiIndices(i as usize))
+                    .get_standard_buffer(StandardBufferMaps::GuiIndices {
+                        image: self.image,
+                        primitive: i as usize,
+                    })
                     .buffer,
             );
         }
@@ -64,6 +72,8 @@ impl GuiVulkanInfo {
 
 pub struct GuiApp {
     state: State,
+    /// Output of the last frame, uploaded the next time the gui buffers are made.
+    primitives: Option<Vec<(Vec<Vertex>, Vec<u16>)>>,
 }
 
 impl GuiApp {
@@ -71,28 +81,60 @@ impl GuiApp {
         let ctx = Context::default();
         let state = State::new(ctx, ViewportId::ROOT, &window, None, None);
 
-        Self { state }
+        Self {
+            state,
+            primitives: None,
+        }
     }
 
-    pub fn prepare(&mut self, window: &Window) -> Result<()> {
-        Ok(())
+    /// Hand the event to egui, and find out what it's keeping for itself.
+    pub fn window_events(&mut self, window: &Window, event: &WindowEvent) -> InputCapture {
+        let response = self.state.on_window_event(window, event);
+        let ctx = self.state.egui_ctx();
+        InputCapture {
+            consumed: response.consumed,
+            wants_keyboard: ctx.wants_keyboard_input(),
+            wants_pointer: ctx.wants_pointer_input(),
+        }
     }
 
-    pub fn window_events(&mut self, window: &Window, event: &WindowEvent) {
-        self.state.on_window_event(window, event);
+    pub fn context(&self) -> &Context {
+        self.state.egui_ctx()
     }
 
-    pub fn render(&mut self, window: &Window) -> Result<Vec<(Vec<Vertex>, Vec<u16>)>> {
+    /// The UI that is always there, anything passed to [`GuiApp::run_ui`] goes on top of it.
+    ///
+    /// A window rather than a panel, a panel covers the whole screen so the gui would take every
+    /// click.
+    fn base_ui(ctx: &Context) {
+        egui::Window::new("Hello").show(ctx, |ui| {
+            ui.label("Hello, World!");
+            let _ = ui.button("Hello!!");
+        });
+    }
+
+    /// The input for the next frame of egui, for running it with [`GuiApp::run_ui`] without
+    /// borrowing the gui.
+    pub fn begin_frame(&mut self, window: &Window) -> (Context, RawInput) {
         // update_viewport_info(&mut viewport, self.state.egui_ctx(), window, false);
         let raw_input = self.state.take_egui_input(window);
+        (self.state.egui_ctx().clone(), raw_input)
+    }
 
-        let full_output = self.state.egui_ctx().run(raw_input.clone(), |ctx| {
-            CentralPanel::default().show(ctx, |ui| {
-                ui.label("Hello, World!");
-                let _ = ui.button("Hello!!");
-            });
-        });
+    pub fn run_ui(
+        ctx: &Context,
+        raw_input: RawInput,
+        mut build: impl FnMut(&Context),
+    ) -> FullOutput {
+        ctx.run(raw_input, |ctx| {
+            Self::base_ui(ctx);
+            build(ctx);
+        })
+    }
 
+    /// Take the output of a frame started with [`GuiApp::begin_frame`]. Returns whether it looks
+    /// any different from the last one.
+    pub fn end_frame(&mut self, window: &Window, full_output: FullOutput) -> bool {
         self.state
             .handle_platform_output(window, full_output.platform_output);
         let primitives = self
@@ -102,7 +144,7 @@ impl GuiApp {
 
         let size = window.inner_size();
 
-        info!("Pre-primitives: {primitives:?}");
+        trace!("Pre-primitives: {primitives:?}");
         let primitives: Vec<(Vec<Vertex>, Vec<u16>)> = primitives
             .into_iter()
             .map(|p| {
@@ -120,7 +162,7 @@ impl GuiApp {
             })
             .collect();
 
-        info!(
+        trace!(
             "Primitives: {:?}",
             primitives
                 .iter()
@@ -128,15 +170,37 @@ impl GuiApp {
                 .collect::<Vec<Vec<Vector3<f32>>>>()
         );
 
-        Ok(primitives)
+        let changed = self.primitives.as_ref() != Some(&primitives);
+        self.primitives = Some(primitives);
+        changed
+    }
+
+    /// Throw away the last output so the next [`GuiApp::render`] runs a fresh frame,
+    /// e.g. after the window size changed.
+    pub fn clear_output(&mut self) {
+        self.primitives = None;
+    }
+
+    pub fn render(&mut self, window: &Window) -> Result<Vec<(Vec<Vertex>, Vec<u16>)>> {
+        if self.primitives.is_none() {
+            let (ctx, raw_input) = self.begin_frame(window);
+            let full_output = Self::run_ui(&ctx, raw_input, |_| {});
+            self.end_frame(window, full_output);
+        }
+
+        Ok(self.primitives.clone().unwrap())
     }
 
+    /// Upload the latest output into the buffers of swapchain image `image`.
+    ///
+    /// The buffers are host visible and written straight from here, so only the command buffer
+    /// of `image` has to be done with them, not the whole device.
     pub unsafe fn create_gui_buffers(
         &mut self,
         data: &mut VulkanData,
         window: &Window,
+        image: usize,
     ) -> Result<GuiVulkanInfo> {
-        self.prepare(window)?;
         let vertices = self.render(window)?;
         let mut buffer_count = 0;
         let mut vertex_lengths = vec![];
@@ -145,37 +209,65 @@ impl GuiApp {
             vertex_lengths.push(vertices.len() as u32);
             index_lengths.push(indices.len() as u32);
             unsafe {
-                Self::create_gui_vertex_buffer(data, vertices, i)?;
-                Self::create_gui_index_buffer(data, indices, i)?;
+                Self::create_gui_buffer(
+                    data,
+                    StandardBufferMaps::GuiVertices {
+                        image,
+                        primitive: i,
+                    },
+                    &vertices,
+                    BufferUsageFlags::VERTEX_BUFFER,
+                )?;
+                Self::create_gui_buffer(
+                    data,
+                    StandardBufferMaps::GuiIndices {
+                        image,
+                        primitive: i,
+                    },
+                    &indices,
+                    BufferUsageFlags::INDEX_BUFFER,
+                )?;
             }
             buffer_count = i;
         }
 
         Ok(GuiVulkanInfo {
+            image,
             buffer_count: buffer_count as u32,
             vertex_lengths,
             index_lengths,
         })
     }
 
-    pub unsafe fn create_gui_vertex_buffer(
+    /// Replace the buffer called `name` with a host visible one holding `values`.
+    unsafe fn create_gui_buffer<T>(
         data: &mut VulkanData,
-        vertices: Vec<Vertex>,
-        idx: usize,
+        name: StandardBufferMaps,
+        values: &[T],
+        usage: BufferUsageFlags,
     ) -> Result<()> {
-        unsafe { create_vertex_buffer(data, StandardBufferMaps::GuiVertices(idx), vertices) };
+        let size = size_of_val(values) as u64;
 
-        Ok(())
-    }
+        unsafe {
+            data.buffer_manager.allocate_buffer_with_size(
+                AllocateBufferType::Standard { name },
+                BufferPairData {
+                    instance: &data.buffer_manager.instance(),
+                    device: &data.buffer_manager.device(),
+                    physical_device: data.buffer_manager.physical_device,
+                    usage,
+                    properties: MemoryPropertyFlags::HOST_COHERENT
+                        | MemoryPropertyFlags::HOST_VISIBLE,
+                },
+                size,
+            )?;
 
-    pub unsafe fn create_gui_index_buffer(
-        data: &mut VulkanData,
-        indices: Vec<u16>,
-        idx: usize,
-    ) -> Result<()> {
-        unsafe { create_index_buffer(data, StandardBufferMaps::GuiIndices(idx), indices) };
-
-        Ok(())
+            data.buffer_manager.copy_data_to_buffer_with_size(
+                BufferManagerDataType::Data(values),
+                BufferManagerCopyType::StandardBuffer(name),
+                size,
+            )
+        }
     }
 }
 
@@ -0,0 +1,416 @@
+//! An egui window for poking around the [`World`] while the game runs. Toggle it with F12.
+//!
+//! Only types registered with [`World::register_reflect_type`] can be looked inside of,
+//! everything else just shows up as unregistered.
+
+use std::any::TypeId;
+
+use anyhow::Result;
+use egui::{CollapsingHeader, Context, DragValue, Grid, Id, ScrollArea, Ui, Window};
+use log::*;
+use winit::{
+    event::{ElementState, Event, WindowEvent},
+    event_loop::EventLoopWindowTarget,
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+use crate::ecs::{
+    World,
+    entity::Entity,
+    reflect::{Reflect, ReflectValue},
+    timings::SystemTimings,
+};
+
+#[derive(Default)]
+pub struct Inspector {
+    pub open: bool,
+    /// Only entities whose name contains this are listed.
+    entity_search: String,
+}
+
+/// Toggles the inspector on F12, it's drawn by [`draw_panels`](crate::engine::panels::draw_panels).
+pub fn inspector_events(
+    world: &World,
+    event: Event<()>,
+    _: &EventLoopWindowTarget<()>,
+) -> Result<()> {
+    if let Event::WindowEvent {
+        event: WindowEvent::KeyboardInput { event, .. },
+        ..
+    } = event
+        && event.physical_key == PhysicalKey::Code(KeyCode::F12)
+        && event.state == ElementState::Pressed
+        && !event.repeat
+    {
+        let mut inspector = world.get_resource_mut::<Inspector>();
+        inspector.open = !inspector.open;
+        debug!("Inspector open: {}", inspector.open);
+    }
+
+    Ok(())
+}
+
+pub fn inspector_open(world: &World) -> bool {
+    world.get_resource::<Inspector>().open
+}
+
+pub fn inspector_ui(ctx: &Context, world: &World) {
+    Window::new("Inspector")
+        .default_width(360.0)
+        .show(ctx, |ui| {
+            ScrollArea::vertical().show(ui, |ui| {
+                CollapsingHeader::new("Resources").show(ui, |ui| resources_ui(ui, world));
+                CollapsingHeader::new("Entities").show(ui, |ui| entities_ui(ui, world));
+                CollapsingHeader::new("Schedule").show(ui, |ui| schedule_ui(ui, world));
+            });
+        });
+}
+
+/// The registered name of a type, `None` if it can't be reflected.
+fn reflect_name(world: &World, id: TypeId) -> Option<&'static str> {
+    world
+        .type_registry()
+        .get(id)
+        .filter(|r| r.is_reflectable())
+        .map(|r| r.name)
+}
+
+fn resources_ui(ui: &mut Ui, world: &World) {
+    let mut resources = vec![];
+    let mut unregistered = 0;
+    for id in world.resource_type_ids() {
+        match reflect_name(world, id) {
+            Some(name) => resources.push((name, id)),
+            None => unregistered += 1,
+        }
+    }
+    resources.sort_by_key(|(name, _)| *name);
+
+    for (name, id) in resources {
+        CollapsingHeader::new(name)
+            .id_source(("resource", name))
+            .show(ui, |ui| {
+                let mut edits = vec![];
+                world.reflect_resource(id, |r| reflect_ui(ui, r, Id::new(name), "", &mut edits));
+                for (path, value) in edits {
+                    world.reflect_resource_mut(id, |r| apply_edit(r, &path, value));
+                }
+            });
+    }
+
+    if unregistered > 0 {
+        ui.weak(format!("{unregistered} unregistered resources"));
+    }
+}
+
+fn entities_ui(ui: &mut Ui, world: &World) {
+    let search = {
+        let mut inspector = world.get_resource_mut::<Inspector>();
+        ui.horizontal(|ui| {
+            ui.label("Search");
+            ui.text_edit_singleline(&mut inspector.entity_search);
+        });
+        inspector.entity_search.to_lowercase()
+    };
+
+    let entities: Vec<Entity> = world
+        .entities()
+        .filter(|entity| search.is_empty() || world.label(*entity).to_lowercase().contains(&search))
+        .collect();
+    ui.label(format!("{} entities", entities.len()));
+
+    for entity in entities {
+        CollapsingHeader::new(world.label(entity))
+            .id_source(("entity", entity))
+            .show(ui, |ui| {
+                if ui.button("Despawn").clicked() {
+                    world.queue_command(move |world| {
+                        world.despawn(entity);
+                        Ok(())
+                    });
+                }
+
+                let mut unregistered = 0;
+                for id in world.component_type_ids(entity) {
+                    let Some(name) = reflect_name(world, id) else {
+                        unregistered += 1;
+                        continue;
+                    };
+
+                    CollapsingHeader::new(name)
+                        .id_source(("component", entity, name))
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            let mut edits = vec![];
+                            world.reflect_component(entity, id, |r| {
+                                reflect_ui(ui, r, Id::new((entity, name)), "", &mut edits)
+                            });
+                            for (path, value) in edits {
+                                world.reflect_component_mut(entity, id, |r| {
+                                    apply_edit(r, &path, value)
+                                });
+                            }
+                        });
+                }
+
+                if unregistered > 0 {
+                    ui.weak(format!("{unregistered} unregistered components"));
+                }
+            });
+    }
+}
+
+fn schedule_ui(ui: &mut Ui, world: &World) {
+    let Some(timings) = world.try_get_resource::<SystemTimings>() else {
+        ui.weak("No timings");
+        return;
+    };
+
+    for (stage, systems) in timings.stages() {
+        CollapsingHeader::new(stage.to_string())
+            .default_open(true)
+            .show(ui, |ui| {
+                Grid::new(("schedule", stage.to_string()))
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("System");
+                        ui.strong("Last");
+                        ui.strong("Average");
+                        ui.strong("Calls");
+                        ui.end_row();
+
+                        for system in systems {
+                            ui.label(&system.name);
+                            ui.label(format!("{:.3} ms", system.last.as_secs_f64() * 1000.0));
+                            ui.label(format!("{:.3} ms", system.average.as_secs_f64() * 1000.0));
+                            ui.label(system.calls.to_string());
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+}
+
+/// Draw editable widgets for every field of `reflect`, which is at `path` in whatever is being
+/// inspected. Edits are only collected into `edits`, so nothing is borrowed mutably just to be
+/// looked at.
+fn reflect_ui(
+    ui: &mut Ui,
+    reflect: &dyn Reflect,
+    id: Id,
+    path: &str,
+    edits: &mut Vec<(String, ReflectValue)>,
+) {
+    let names = reflect.field_names();
+    if names.is_empty() {
+        if let Some(value) = value_ui(ui, reflect) {
+            edits.push((path.to_string(), value));
+        }
+        return;
+    }
+
+    for name in names {
+        let Some(field) = reflect.field(&name) else {
+            continue;
+        };
+        if reflect.is_read_only(&name) {
+            ui.horizontal(|ui| {
+                ui.label(&name);
+                ui.label(field.value().to_string());
+            });
+            continue;
+        }
+
+        let field_path = match path.is_empty() {
+            true => name.clone(),
+            false => format!("{path}.{name}"),
+        };
+        if field.field_names().is_empty() {
+            ui.horizontal(|ui| {
+                ui.label(&name);
+                if let Some(value) = value_ui(ui, field) {
+                    edits.push((field_path, value));
+                }
+            });
+        } else {
+            let id = id.with(&name);
+            CollapsingHeader::new(&name)
+                .id_source(id)
+                .show(ui, |ui| reflect_ui(ui, field, id, &field_path, edits));
+        }
+    }
+}
+
+/// Write an edit made in [`reflect_ui`] back.
+fn apply_edit(reflect: &mut dyn Reflect, path: &str, value: ReflectValue) {
+    if let Err(err) = reflect.set_path(path, value) {
+        warn!("Inspector couldn't set value: {err}");
+    }
+}
+
+/// Widget for a single plain value, returns the new value when it's changed.
+fn value_ui(ui: &mut Ui, reflect: &dyn Reflect) -> Option<ReflectValue> {
+    match reflect.value() {
+        ReflectValue::Bool(mut v) => ui
+            .checkbox(&mut v, "")
+            .changed()
+            .then_some(ReflectValue::Bool(v)),
+        ReflectValue::Int(mut v) => ui
+            .add(DragValue::new(&mut v))
+            .changed()
+            .then_some(ReflectValue::Int(v)),
+        ReflectValue::UInt(mut v) => ui
+            .add(DragValue::new(&mut v))
+            .changed()
+            .then_some(ReflectValue::UInt(v)),
+        ReflectValue::Float(mut v) => ui
+            .add(DragValue::new(&mut v).speed(0.01))
+            .changed()
+            .then_some(ReflectValue::Float(v)),
+        ReflectValue::String(mut v) => ui
+            .text_edit_singleline(&mut v)
+            .changed()
+            .then_some(ReflectValue::String(v)),
+        value => {
+            ui.label(value.to_string());
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::{CentralPanel, Event, Modifiers, Pos2, RawInput, Rect, Shape, vec2};
+
+    use super::*;
+    use crate::ecs::reflect::impl_reflect;
+
+    struct Belt {
+        speed: f32,
+        running: bool,
+        items: u32,
+    }
+
+    impl_reflect!(Belt { speed, running } read_only { items });
+
+    struct Line {
+        belt: Belt,
+        name: String,
+    }
+
+    impl_reflect!(Line { belt, name });
+
+    fn line() -> Line {
+        Line {
+            belt: Belt {
+                speed: 1.0,
+                running: false,
+                items: 3,
+            },
+            name: "North".to_string(),
+        }
+    }
+
+    /// Draw `reflect` with [`reflect_ui`] in a headless egui, returns the edits and every piece
+    /// of text drawn with its rectangle.
+    fn draw(
+        ctx: &Context,
+        reflect: &dyn Reflect,
+        events: Vec<Event>,
+    ) -> (Vec<(String, ReflectValue)>, Vec<(String, Rect)>) {
+        let input = RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, vec2(800.0, 600.0))),
+            events,
+            ..Default::default()
+        };
+        let mut edits = vec![];
+        let output = ctx.run(input, |ctx| {
+            CentralPanel::default().show(ctx, |ui| {
+                reflect_ui(ui, reflect, Id::new("line"), "", &mut edits)
+            });
+        });
+
+        let texts = output
+            .shapes
+            .iter()
+            .filter_map(|clipped| match &clipped.shape {
+                Shape::Text(text) => Some((
+                    text.galley.text().to_string(),
+                    text.galley.rect.translate(text.pos.to_vec2()),
+                )),
+                _ => None,
+            })
+            .collect();
+        (edits, texts)
+    }
+
+    fn click(pos: Pos2) -> Vec<Vec<Event>> {
+        let press = |pressed| Event::PointerButton {
+            pos,
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: Modifiers::NONE,
+        };
+        vec![
+            vec![Event::PointerMoved(pos), press(true)],
+            vec![press(false)],
+        ]
+    }
+
+    fn rect_of(texts: &[(String, Rect)], label: &str) -> Rect {
+        texts
+            .iter()
+            .find(|(text, _)| text == label)
+            .unwrap_or_else(|| panic!("Nothing says {label:?} in {texts:?}"))
+            .1
+    }
+
+    #[test]
+    fn nested_fields_are_edited_by_path() {
+        let ctx = Context::default();
+        let mut line = line();
+        let (_, texts) = draw(&ctx, &line, vec![]);
+
+        // Open up the belt
+        let mut edits = vec![];
+        for events in click(rect_of(&texts, "belt").center()) {
+            edits.extend(draw(&ctx, &line, events).0);
+        }
+        let (_, texts) = draw(&ctx, &line, vec![]);
+        // Read-only fields are only shown
+        assert!(texts.iter().any(|(text, _)| text == "3"));
+
+        // The checkbox goes right after its label
+        let running = rect_of(&texts, "running");
+        let checkbox = Pos2::new(
+            running.max.x + ctx.style().spacing.item_spacing.x + 4.0,
+            running.center().y,
+        );
+        for events in click(checkbox) {
+            edits.extend(draw(&ctx, &line, events).0);
+        }
+
+        assert_eq!(
+            edits,
+            [("belt.running".to_string(), ReflectValue::Bool(true))]
+        );
+        for (path, value) in edits {
+            apply_edit(&mut line, &path, value);
+        }
+        assert!(line.belt.running);
+    }
+
+    #[test]
+    fn bad_edits_are_only_warned_about() {
+        let mut line = line();
+
+        apply_edit(&mut line, "belt.speed", ReflectValue::Float(2.5));
+        apply_edit(&mut line, "belt.items", ReflectValue::UInt(10));
+        apply_edit(&mut line, "belt.gears", ReflectValue::UInt(10));
+        apply_edit(&mut line, "name", ReflectValue::Bool(true));
+
+        assert_eq!(line.belt.speed, 2.5);
+        assert_eq!(line.belt.items, 3);
+        assert_eq!(line.name, "North");
+    }
+}
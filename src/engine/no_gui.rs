@@ -0,0 +1,60 @@
+//! Stands in for [`gui`](super::gui) when the `gui` feature is off, so the renderer draws the
+//! scene without anything on top.
+
+use anyhow::Result;
+use vulkanalia::vk::Buffer;
+use winit::{event::WindowEvent, window::Window};
+
+use crate::ecs::input::InputCapture;
+use crate::engine::vulkan::{
+    VulkanData,
+    buffer_manager::{
+        BufferManager,
+        buffer_pair::{BufferPair, StandardBufferMaps, UniformBufferMaps},
+    },
+};
+
+pub struct GuiVulkanInfo {
+    pub buffer_count: u32,
+}
+
+impl GuiVulkanInfo {
+    pub fn add_to_vertex_buffers(
+        &mut self,
+        _buffer_manager: &mut BufferManager<BufferPair, StandardBufferMaps, UniformBufferMaps>,
+        _vertex_buffers: &mut Vec<Buffer>,
+        _vertex_lengths: &mut Vec<u32>,
+    ) {
+    }
+
+    pub fn add_to_index_buffers(
+        &mut self,
+        _buffer_manager: &mut BufferManager<BufferPair, StandardBufferMaps, UniformBufferMaps>,
+        _index_buffers: &mut Vec<Buffer>,
+        _index_lengths: &mut Vec<u32>,
+    ) {
+    }
+}
+
+pub struct GuiApp;
+
+impl GuiApp {
+    pub fn new(_window: &Window) -> Self {
+        Self
+    }
+
+    pub fn window_events(&mut self, _window: &Window, _event: &WindowEvent) -> InputCapture {
+        InputCapture::default()
+    }
+
+    pub fn clear_output(&mut self) {}
+
+    pub unsafe fn create_gui_buffers(
+        &mut self,
+        _data: &mut VulkanData,
+        _window: &Window,
+        _image: usize,
+    ) -> Result<GuiVulkanInfo> {
+        Ok(GuiVulkanInfo { buffer_count: 0 })
+    }
+}
@@ -0,0 +1,161 @@
+//! egui windows on top of the game.
+//!
+//! egui builds the whole UI again every time it runs, so every window has to be drawn in the same
+//! run or only the last one shows up. Plugins add their windows to [`GuiPanels`] from a startup
+//! system and [`draw_panels`] draws the open ones before every frame.
+//!
+//! # Example
+//! ```rs
+//! fn add_map_panel(world: &mut World, _: &EventLoop<()>) -> Result<()> {
+//!     world.get_resource_mut::<GuiPanels>().add(
+//!         |world| world.get_resource::<MapWindow>().open,
+//!         |ctx, world| { Window::new("Map").show(ctx, |ui| ..); },
+//!     );
+//!     Ok(())
+//! }
+//! ```
+
+use anyhow::Result;
+use egui::Context;
+use winit::{
+    event::{Event, WindowEvent},
+    event_loop::EventLoopWindowTarget,
+};
+
+use crate::{
+    ecs::World,
+    engine::{Engine, gui::GuiApp},
+};
+
+pub struct GuiPanel {
+    pub is_open: fn(&World) -> bool,
+    pub ui: fn(&Context, &World),
+}
+
+#[derive(Default)]
+pub struct GuiPanels {
+    panels: Vec<GuiPanel>,
+    /// Whether anything was drawn last frame, so closing the last window redraws the gui once
+    /// without it.
+    was_open: bool,
+}
+
+impl GuiPanels {
+    /// Draw `ui` every frame `is_open` says so.
+    pub fn add(&mut self, is_open: fn(&World) -> bool, ui: fn(&Context, &World)) {
+        self.panels.push(GuiPanel { is_open, ui });
+    }
+
+    /// The panels to draw this frame. `None` when none are open and none were last frame either,
+    /// so there's no need to run the gui at all.
+    fn to_draw(&mut self, world: &World) -> Option<Vec<fn(&Context, &World)>> {
+        let open: Vec<_> = self
+            .panels
+            .iter()
+            .filter(|panel| (panel.is_open)(world))
+            .map(|panel| panel.ui)
+            .collect();
+        if open.is_empty() && !self.was_open {
+            return None;
+        }
+        self.was_open = !open.is_empty();
+        Some(open)
+    }
+}
+
+/// Draws every open panel before the frame is rendered.
+///
+/// Has to run before [`engine_main`](crate::engine::engine_main) renders.
+pub fn draw_panels(world: &World, event: Event<()>, _: &EventLoopWindowTarget<()>) -> Result<()> {
+    let Event::WindowEvent {
+        event: WindowEvent::RedrawRequested,
+        ..
+    } = event
+    else {
+        return Ok(());
+    };
+
+    let Some(open) = world.get_resource_mut::<GuiPanels>().to_draw(world) else {
+        return Ok(());
+    };
+
+    let Some((ctx, raw_input)) = world
+        .try_get_resource_mut::<Engine>()
+        .map(|mut engine| engine.vulkan_app.begin_gui())
+    else {
+        return Ok(());
+    };
+
+    // The engine isn't borrowed while the panels draw, so they can use it too
+    let output = GuiApp::run_ui(&ctx, raw_input, |ctx| {
+        for ui in &open {
+            ui(ctx, world);
+        }
+    });
+    world
+        .get_resource_mut::<Engine>()
+        .vulkan_app
+        .end_gui(output);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct MapWindow {
+        open: bool,
+    }
+
+    fn map_open(world: &World) -> bool {
+        world.get_resource::<MapWindow>().open
+    }
+
+    fn map_ui(_: &Context, _: &World) {}
+
+    fn never_open(_: &World) -> bool {
+        false
+    }
+
+    fn panels_world() -> World {
+        let mut world = World::new();
+        world.add_resource(MapWindow::default());
+        let mut panels = GuiPanels::default();
+        panels.add(map_open, map_ui);
+        panels.add(never_open, map_ui);
+        world.add_resource(panels);
+        world
+    }
+
+    fn to_draw(world: &World) -> Option<usize> {
+        world
+            .get_resource_mut::<GuiPanels>()
+            .to_draw(world)
+            .map(|open| open.len())
+    }
+
+    #[test]
+    fn only_open_panels_are_drawn() {
+        let world = panels_world();
+        assert_eq!(to_draw(&world), None);
+
+        world.get_resource_mut::<MapWindow>().open = true;
+        assert_eq!(to_draw(&world), Some(1));
+        assert_eq!(to_draw(&world), Some(1));
+    }
+
+    #[test]
+    fn closing_the_last_panel_draws_once_more() {
+        let world = panels_world();
+        world.get_resource_mut::<MapWindow>().open = true;
+        to_draw(&world);
+
+        world.get_resource_mut::<MapWindow>().open = false;
+
+        // Once to clear what was there, then not at all
+        assert_eq!(to_draw(&world), Some(0));
+        assert_eq!(to_draw(&world), None);
+    }
+}
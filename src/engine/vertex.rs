@@ -11,7 +11,7 @@ pub type Vec3 = cgmath::Vector3<f32>;
 pub type Mat4 = cgmath::Matrix4<f32>;
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Vertex {
     pub pos: Vec3,
     pub color: Vec3,
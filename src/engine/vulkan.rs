@@ -1,9 +1,9 @@
 use anyhow::{Result, anyhow};
-use cgmath::{Deg, Matrix};
+use cgmath::{Matrix, SquareMatrix, vec4};
 use log::*;
 use std::{
     collections::HashSet,
-    ffi::{CStr, c_void},
+    ffi::{CStr, CString, c_void},
     fs::File,
     iter,
     ptr::copy_nonoverlapping,
@@ -17,8 +17,10 @@ use vulkanalia::{
     vk::*,
     window::{create_surface, get_required_instance_extensions},
 };
-use winit::{event::WindowEvent, window::Window};
+use winit::{dpi::PhysicalPosition, event::WindowEvent, window::Window};
 
+use crate::config::{EngineConfig, PresentMode};
+use crate::ecs::{camera::Camera2D, input::InputCapture};
 use crate::engine::{
     gui::{GuiApp, GuiVulkanInfo},
     vertex::{
@@ -46,7 +48,7 @@ pub mod shared_helpers;
 
 const PORTABILITY_MACOS_VERSION: Version = Version::new(1, 3, 216);
 
-const VALIDATION_ENABLED: bool = cfg!(debug_assertions);
+const VALIDATION_ENABLED: bool = cfg!(any(debug_assertions, feature = "vulkan-validation"));
 const VALIDATION_LAYER: ExtensionName = ExtensionName::from_bytes(b"VK_LAYER_KHRONOS_validation");
 
 const DEVICE_EXTENSIONS: &[ExtensionName] = &[
@@ -55,6 +57,8 @@ const DEVICE_EXTENSIONS: &[ExtensionName] = &[
 ];
 
 const MAX_FRAMES_IN_FLIGHT: usize = 2;
+/// How far above and below the ground things can be and still get drawn.
+const DEPTH_RANGE: f32 = 10.0;
 
 extern "system" fn debug_callback(
     severity: DebugUtilsMessageSeverityFlagsEXT,
@@ -89,8 +93,9 @@ pub struct VulkanApp {
     pub data: VulkanData,
     pub frame: usize,
     pub resized: bool,
+    /// Which swapchain images draw an old gui and need their gui buffers uploaded again.
+    pub gui_dirty: Vec<bool>,
     start: Instant,
-    pub camera_position: [f32; 2],
     pub window: Window,
     pub gui: GuiApp,
 }
@@ -124,14 +129,22 @@ pub struct VulkanData {
     pub texture_sampler: Sampler,
     pub buffer_manager: BufferManager<BufferPair, StandardBufferMaps, UniformBufferMaps>,
     pub image_manager: BufferManager<Texture, TextureName, TextureGroupName>,
+    /// From [`EngineConfig::present_mode`], used every time the swapchain is made.
+    pub present_mode: PresentMode,
+    /// From [`EngineConfig::gpu`].
+    pub gpu: Option<String>,
 }
 
 impl VulkanApp {
-    pub unsafe fn create(window: Window) -> Result<Self> {
+    pub unsafe fn create(window: Window, config: &EngineConfig) -> Result<Self> {
         unsafe {
             let loader = LibloadingLoader::new(LIBRARY)?;
             let entry = Entry::new(loader).map_err(|b| anyhow!("{}", b))?;
-            let mut data = VulkanData::default();
+            let mut data = VulkanData {
+                present_mode: config.present_mode,
+                gpu: config.gpu.clone(),
+                ..Default::default()
+            };
             let instance = Self::create_instance(&window, &entry, &mut data)?;
             data.surface = create_surface(&instance, &window, &window)?;
             Self::pick_physical_device(&instance, &mut data)?;
@@ -164,12 +177,7 @@ impl VulkanApp {
                             instance: &instance,
                             device: &device,
                             data: &mut data.clone(),
-                            image_data: ImageData::from_path(
-                                &instance,
-                                &device,
-                                &mut data.clone(),
-                                "resources/tuftie.png",
-                            )?,
+                            image_data: ImageData::load("resources/tuftie.png")?,
                             format: Format::R8G8B8A8_SRGB,
                             tiling: ImageTiling::OPTIMAL,
                             usage: ImageUsageFlags::SAMPLED | ImageUsageFlags::TRANSFER_DST,
@@ -193,14 +201,14 @@ impl VulkanApp {
                     StandardBufferMaps::ExtraIndices(0),
                     INDICES.to_vec(),
                 )?;
-                let gui_vulkan_info = gui.create_gui_buffers(&mut data, &window)?;
                 Self::create_uniform_buffers(&mut data)?;
                 Self::create_descriptor_pool(&mut data)?;
                 Self::create_descriptor_sets(&device, &mut data)?;
-                Self::create_command_buffers(&device, &mut data, &window, gui_vulkan_info)?;
+                Self::create_command_buffers(&device, &mut data, &window, &mut gui)?;
                 Self::create_sync_objects(&device, &mut data)?;
                 info!("Woo created everything, hard work ain't it?");
             }
+            let gui_dirty = vec![false; data.swapchain_images.len()];
 
             Ok(Self {
                 entry,
@@ -209,16 +217,18 @@ impl VulkanApp {
                 data,
                 frame: 0,
                 resized: false,
+                gui_dirty,
                 start: Instant::now(),
-                camera_position: [0.0, 0.0],
                 window,
                 gui,
             })
         }
     }
 
-    pub unsafe fn render(&mut self) -> Result<()> {
+    /// Draw a frame looking through `camera`.
+    pub unsafe fn render(&mut self, camera: &Camera2D) -> Result<()> {
         trace!("Rendering");
+
         let in_flight_fence = self.data.in_flight_fences[self.frame];
 
         (unsafe {
@@ -253,7 +263,13 @@ impl VulkanApp {
 
         self.data.images_in_flight[image_index] = in_flight_fence;
 
-        unsafe { self.update_uniform_buffer(image_index) }?;
+        // Nothing is drawing this image anymore, so its gui buffers are free to change
+        if self.gui_dirty[image_index] {
+            self.gui_dirty[image_index] = false;
+            unsafe { self.refresh_gui(image_index) }?;
+        }
+
+        unsafe { self.update_uniform_buffer(image_index, camera) }?;
 
         let wait_semaphores = &[self.data.image_available_semaphore[self.frame]];
         let wait_stages = &[PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
@@ -297,18 +313,66 @@ impl VulkanApp {
         Ok(())
     }
 
-    pub fn window_events(&mut self, event: &WindowEvent) {
-        self.gui.window_events(&self.window, event);
+    /// Remake the swapchain with a different present mode before the next frame.
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) {
+        if self.data.present_mode != present_mode {
+            info!("Switching present mode to {present_mode}");
+            self.data.present_mode = present_mode;
+            self.resized = true;
+        }
     }
 
-    unsafe fn update_uniform_buffer(&self, image_index: usize) -> Result<()> {
-        let _time = self.start.elapsed().as_secs_f32();
+    /// Hands the event to the gui, returns what it kept from the game.
+    pub fn window_events(&mut self, event: &WindowEvent) -> InputCapture {
+        self.gui.window_events(&self.window, event)
+    }
+
+    /// Start a gui frame to be run without holding onto the engine, see [`GuiApp::run_ui`].
+    #[cfg(feature = "gui")]
+    pub fn begin_gui(&mut self) -> (egui::Context, egui::RawInput) {
+        self.gui.begin_frame(&self.window)
+    }
+
+    /// Finish a gui frame started with [`VulkanApp::begin_gui`].
+    #[cfg(feature = "gui")]
+    pub fn end_gui(&mut self, output: egui::FullOutput) {
+        if self.gui.end_frame(&self.window, output) {
+            self.gui_dirty.fill(true);
+        }
+    }
+
+    /// Upload the latest gui output for swapchain image `image` and re-record its command buffer.
+    ///
+    /// Only `image` has to be done drawing, the other images keep their own gui buffers so the
+    /// rest of the device keeps going.
+    unsafe fn refresh_gui(&mut self, image: usize) -> Result<()> {
+        unsafe {
+            self.device
+                .free_command_buffers(self.data.command_pool, &[self.data.command_buffers[image]]);
+
+            let allocate_info = CommandBufferAllocateInfo::builder()
+                .command_pool(self.data.command_pool)
+                .level(CommandBufferLevel::PRIMARY)
+                .command_buffer_count(1);
+            self.data.command_buffers[image] =
+                self.device.allocate_command_buffers(&allocate_info)?[0];
+
+            let gui_vulkan_info =
+                self.gui
+                    .create_gui_buffers(&mut self.data, &self.window, image)?;
+            Self::record_command_buffer(&self.device, &mut self.data, image, gui_vulkan_info)?;
+        }
 
+        Ok(())
+    }
+
+    /// The view and projection matrices the shaders get, looking straight down through `camera`.
+    fn view_projection(camera: &Camera2D) -> (Mat4, Mat4) {
         #[rustfmt::skip]
         let view = Mat4::new(
-            1.0, 0.0, 0.0, -self.camera_position[0],
-            0.0, 1.0, 0.0, -self.camera_position[1],
-            0.0, 0.0, 1.0, -5.0,
+            1.0, 0.0, 0.0, -camera.position[0],
+            0.0, 1.0, 0.0, -camera.position[1],
+            0.0, 0.0, 1.0, 0.0,
             0.0, 0.0, 0.0, 1.0,
         ).transpose();
 
@@ -320,16 +384,50 @@ impl VulkanApp {
             0.0, 0.0, 0.5, 1.0,
         );
 
+        let [width, height] = camera.view_size();
         let proj = correction
-            * cgmath::perspective(
-                Deg(60.0),
-                self.data.swapchain_extent.width as f32 / self.data.swapchain_extent.height as f32,
-                0.1,
-                10.0,
+            * cgmath::ortho(
+                -width / 2.0,
+                width / 2.0,
+                -height / 2.0,
+                height / 2.0,
+                -DEPTH_RANGE,
+                DEPTH_RANGE,
             );
 
-        // info!("View: {view:?}");
-        // info!("Proj: {proj:?}");
+        (view, proj)
+    }
+
+    /// The point on the ground (`z = 0`) under `position` looking through `camera`, in pixels from
+    /// the top left of the window. `None` when looking along the ground.
+    pub fn screen_to_world(
+        &self,
+        camera: &Camera2D,
+        position: PhysicalPosition<f64>,
+    ) -> Option<[f32; 2]> {
+        let (view, proj) = Self::view_projection(camera);
+        let inverse = (proj * view).invert()?;
+
+        let extent = self.data.swapchain_extent;
+        let x = (2.0 * position.x / extent.width as f64 - 1.0) as f32;
+        let y = (2.0 * position.y / extent.height as f64 - 1.0) as f32;
+
+        // Vulkan depth goes from 0 to 1
+        let near = inverse * vec4(x, y, 0.0, 1.0);
+        let far = inverse * vec4(x, y, 1.0, 1.0);
+        let (near, far) = (near.truncate() / near.w, far.truncate() / far.w);
+
+        let t = -near.z / (far.z - near.z);
+        if !t.is_finite() {
+            return None;
+        }
+        let point = near + (far - near) * t;
+        Some([point.x, point.y])
+    }
+
+    unsafe fn update_uniform_buffer(&self, image_index: usize, camera: &Camera2D) -> Result<()> {
+        let _time = self.start.elapsed().as_secs_f32();
+        let (view, proj) = Self::view_projection(camera);
 
         let ubo = UniformBufferObject { view, proj };
 
@@ -552,8 +650,9 @@ impl VulkanApp {
         entry: &Entry,
         data: &mut VulkanData,
     ) -> Result<Instance> {
+        let application_name = CString::new(window.title())?;
         let application_info = ApplicationInfo::builder()
-            .application_name(b"Factory Game\0")
+            .application_name(application_name.as_bytes_with_nul())
             .application_version(make_version(1, 0, 0))
             .engine_name(b"Lemgine\0")
             .engine_version(make_version(1, 0, 0))
@@ -632,7 +731,44 @@ impl VulkanApp {
     }
 
     pub unsafe fn pick_physical_device(instance: &Instance, data: &mut VulkanData) -> Result<()> {
-        for physical_device in unsafe { instance.enumerate_physical_devices() }? {
+        let physical_devices = unsafe { instance.enumerate_physical_devices() }?;
+
+        if let Some(wanted) = data.gpu.clone() {
+            let wanted_lower = wanted.to_lowercase();
+            let found = physical_devices.iter().enumerate().find(|(i, d)| {
+                let properties = unsafe { instance.get_physical_device_properties(**d) };
+                wanted.parse() == Ok(*i)
+                    || properties
+                        .device_name
+                        .to_string()
+                        .to_lowercase()
+                        .contains(&wanted_lower)
+            });
+
+            match found {
+                Some((_, physical_device)) => {
+                    let properties =
+                        unsafe { instance.get_physical_device_properties(*physical_device) };
+                    match unsafe { Self::check_physical_device(instance, data, *physical_device) } {
+                        Ok(()) => {
+                            info!(
+                                "Selected configured physical device (`{}`).",
+                                properties.device_name
+                            );
+                            data.physical_device = *physical_device;
+                            return Ok(());
+                        }
+                        Err(error) => warn!(
+                            "Configured physical device (`{}`) can't be used: {}",
+                            properties.device_name, error
+                        ),
+                    }
+                }
+                None => warn!("No physical device matches the configured GPU {wanted:?}"),
+            }
+        }
+
+        for physical_device in physical_devices {
             let properties = unsafe { instance.get_physical_device_properties(physical_device) };
 
             if let Err(error) =
@@ -770,7 +906,8 @@ impl VulkanApp {
         let support = unsafe { SwapchainSupport::get(instance, data, data.physical_device) }?;
 
         let surface_format = SwapchainSupport::get_swapchain_surface_format(&support.formats);
-        let present_mode = SwapchainSupport::get_swapchain_present_mode(&support.present_modes);
+        let present_mode =
+            SwapchainSupport::get_swapchain_present_mode(&support.present_modes, data.present_mode);
         let extent = SwapchainSupport::get_swapchain_extent(window, support.capabilities);
 
         let mut image_count = support.capabilities.min_image_count + 1;
@@ -1077,7 +1214,7 @@ impl VulkanApp {
         device: &Device,
         data: &mut VulkanData,
         window: &Window,
-        mut gui_vulkan_info: GuiVulkanInfo,
+        gui: &mut GuiApp,
     ) -> Result<()> {
         let allocate_info = CommandBufferAllocateInfo::builder()
             .command_pool(data.command_pool)
@@ -1086,6 +1223,23 @@ impl VulkanApp {
 
         data.command_buffers = unsafe { device.allocate_command_buffers(&allocate_info) }?;
 
+        for image in 0..data.command_buffers.len() {
+            unsafe {
+                let gui_vulkan_info = gui.create_gui_buffers(data, window, image)?;
+                Self::record_command_buffer(device, data, image, gui_vulkan_info)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Record the commands drawing swapchain image `i`.
+    unsafe fn record_command_buffer(
+        device: &Device,
+        data: &mut VulkanData,
+        i: usize,
+        mut gui_vulkan_info: GuiVulkanInfo,
+    ) -> Result<()> {
         #[rustfmt::skip]
         let model = Mat4::new(
             1.0, 0.0, 0.0, -0.5,
@@ -1099,120 +1253,116 @@ impl VulkanApp {
             std::slice::from_raw_parts(&model as *const Mat4 as *const u8, size_of::<Mat4>())
         };
 
-        for (i, command_buffer) in data.command_buffers.clone().iter().enumerate() {
-            let inheritance = CommandBufferInheritanceInfo::builder();
+        let command_buffer = &data.command_buffers[i];
 
-            let info = CommandBufferBeginInfo::builder()
-                .flags(CommandBufferUsageFlags::empty()) // Optional.
-                .inheritance_info(&inheritance); // Optional.
+        let inheritance = CommandBufferInheritanceInfo::builder();
 
-            (unsafe { device.begin_command_buffer(*command_buffer, &info) })?;
+        let info = CommandBufferBeginInfo::builder()
+            .flags(CommandBufferUsageFlags::empty()) // Optional.
+            .inheritance_info(&inheritance); // Optional.
 
-            let render_area = Rect2D::builder()
-                .offset(Offset2D::default())
-                .extent(data.swapchain_extent);
+        (unsafe { device.begin_command_buffer(*command_buffer, &info) })?;
 
-            let color_clear_value = ClearValue {
-                color: ClearColorValue {
-                    float32: [1.0, 1.0, 1.0, 1.0],
-                },
-            };
+        let render_area = Rect2D::builder()
+            .offset(Offset2D::default())
+            .extent(data.swapchain_extent);
 
-            let depth_clear_value = ClearValue {
-                depth_stencil: ClearDepthStencilValue {
-                    depth: 1.0,
-                    stencil: 0,
-                },
-            };
+        let color_clear_value = ClearValue {
+            color: ClearColorValue {
+                float32: [1.0, 1.0, 1.0, 1.0],
+            },
+        };
 
-            let clear_values = &[color_clear_value, depth_clear_value];
-            let info = RenderPassBeginInfo::builder()
-                .render_pass(data.render_pass)
-                .framebuffer(data.framebuffers[i])
-                .render_area(render_area)
-                .clear_values(clear_values);
+        let depth_clear_value = ClearValue {
+            depth_stencil: ClearDepthStencilValue {
+                depth: 1.0,
+                stencil: 0,
+            },
+        };
 
-            unsafe {
-                let mut vertex_buffers = vec![
-                    data.buffer_manager
-                        .get_standard_buffer(StandardBufferMaps::Vertices)
-                        .buffer,
-                    // data.buffer_manager
-                    //     .get_standard_buffer(StandardBufferMaps::ExtraVertices(0))
-                    //     .buffer,
-                ];
-                let mut vertex_lengths = vec![
-                    // VERTICES.len() as u32, VERTICES2.len() as u32
-                ];
-                gui_vulkan_info.add_to_vertex_buffers(
-                    &mut data.buffer_manager,
-                    &mut vertex_buffers,
-                    &mut vertex_lengths,
-                );
+        let clear_values = &[color_clear_value, depth_clear_value];
+        let info = RenderPassBeginInfo::builder()
+            .render_pass(data.render_pass)
+            .framebuffer(data.framebuffers[i])
+            .render_area(render_area)
+            .clear_values(clear_values);
 
-                let mut index_buffers = vec![
-                    data.buffer_manager
-                        .get_standard_buffer(StandardBufferMaps::Indices)
-                        .buffer,
-                    // data.buffer_manager
-                    //     .get_standard_buffer(StandardBufferMaps::ExtraIndices(0))
-                    //     .buffer,
-                ];
-                let mut index_lengths = vec![
-                    INDICES.len() as u32,
-                    // INDICES.len() as u32
-                ];
-                gui_vulkan_info.add_to_index_buffers(
-                    &mut data.buffer_manager,
-                    &mut index_buffers,
-                    &mut index_lengths,
-                );
+        unsafe {
+            let mut vertex_buffers = vec![
+                data.buffer_manager
+                    .get_standard_buffer(StandardBufferMaps::Vertices)
+                    .buffer,
+                // data.buffer_manager
+                //     .get_standard_buffer(StandardBufferMaps::ExtraVertices(0))
+                //     .buffer,
+            ];
+            let mut vertex_lengths = vec![
+                // VERTICES.len() as u32, VERTICES2.len() as u32
+            ];
+            gui_vulkan_info.add_to_vertex_buffers(
+                &mut data.buffer_manager,
+                &mut vertex_buffers,
+                &mut vertex_lengths,
+            );
 
-                info!("Vertex Buffers: {vertex_buffers:?}");
+            let mut index_buffers = vec![
+                data.buffer_manager
+                    .get_standard_buffer(StandardBufferMaps::Indices)
+                    .buffer,
+                // data.buffer_manager
+                //     .get_standard_buffer(StandardBufferMaps::ExtraIndices(0))
+                //     .buffer,
+            ];
+            let mut index_lengths = vec![
+                INDICES.len() as u32,
+                // INDICES.len() as u32
+            ];
+            gui_vulkan_info.add_to_index_buffers(
+                &mut data.buffer_manager,
+                &mut index_buffers,
+                &mut index_lengths,
+            );
 
-                device.cmd_begin_render_pass(*command_buffer, &info, SubpassContents::INLINE);
-                device.cmd_bind_pipeline(
-                    *command_buffer,
-                    PipelineBindPoint::GRAPHICS,
-                    data.pipeline,
-                );
-                device.cmd_bind_descriptor_sets(
-                    *command_buffer,
-                    PipelineBindPoint::GRAPHICS,
-                    data.pipeline_layout,
-                    0,
-                    &[data.descriptor_sets[i]],
-                    &[],
-                );
-                device.cmd_push_constants(
-                    *command_buffer,
-                    data.pipeline_layout,
-                    ShaderStageFlags::VERTEX,
-                    0,
-                    model_bytes,
-                );
-                info!("Buffer count: {}", gui_vulkan_info.buffer_count);
+            info!("Vertex Buffers: {vertex_buffers:?}");
 
-                const INDEXING_COUNTS: [u32; 2] = [10, 0];
+            device.cmd_begin_render_pass(*command_buffer, &info, SubpassContents::INLINE);
+            device.cmd_bind_pipeline(*command_buffer, PipelineBindPoint::GRAPHICS, data.pipeline);
+            device.cmd_bind_descriptor_sets(
+                *command_buffer,
+                PipelineBindPoint::GRAPHICS,
+                data.pipeline_layout,
+                0,
+                &[data.descriptor_sets[i]],
+                &[],
+            );
+            device.cmd_push_constants(
+                *command_buffer,
+                data.pipeline_layout,
+                ShaderStageFlags::VERTEX,
+                0,
+                model_bytes,
+            );
+            info!("Buffer count: {}", gui_vulkan_info.buffer_count);
 
-                for (i, buffer) in vertex_buffers.into_iter().enumerate() {
-                    info!("Index ({i}) Length: {}", index_lengths[i]);
+            const INDEXING_COUNTS: [u32; 2] = [10, 0];
 
-                    device.cmd_bind_vertex_buffers(*command_buffer, 0, &[buffer], &[0]);
-                    device.cmd_bind_index_buffer(
-                        *command_buffer,
-                        index_buffers[i],
-                        0,
-                        IndexType::UINT16,
-                    );
-                    device.cmd_draw_indexed(*command_buffer, index_lengths[i], 1, 0, 0, 0);
-                    info!("Ran {} draw call(s)", i + 1);
-                }
+            for (i, buffer) in vertex_buffers.into_iter().enumerate() {
+                info!("Index ({i}) Length: {}", index_lengths[i]);
 
-                device.cmd_end_render_pass(*command_buffer);
-                device.end_command_buffer(*command_buffer)?;
-            };
-        }
+                device.cmd_bind_vertex_buffers(*command_buffer, 0, &[buffer], &[0]);
+                device.cmd_bind_index_buffer(
+                    *command_buffer,
+                    index_buffers[i],
+                    0,
+                    IndexType::UINT16,
+                );
+                device.cmd_draw_indexed(*command_buffer, index_lengths[i], 1, 0, 0, 0);
+                info!("Ran {} draw call(s)", i + 1);
+            }
+
+            device.cmd_end_render_pass(*command_buffer);
+            device.end_command_buffer(*command_buffer)?;
+        };
 
         Ok(())
     }
@@ -1254,14 +1404,15 @@ impl VulkanApp {
             Self::create_uniform_buffers(&mut self.data)?;
             Self::create_descriptor_pool(&mut self.data)?;
             Self::create_descriptor_sets(&self.device, &mut self.data)?;
-            let gui_vulkan_info = self.gui.create_gui_buffers(&mut self.data, &self.window)?;
+            self.gui.clear_output();
             Self::create_command_buffers(
                 &self.device,
                 &mut self.data,
                 &self.window,
-                gui_vulkan_info,
+                &mut self.gui,
             )?;
         }
+        self.gui_dirty = vec![false; self.data.swapchain_images.len()];
         self.data
             .images_in_flight
             .resize(self.data.swapchain_images.len(), Fence::null());
@@ -1541,12 +1692,25 @@ impl SwapchainSupport {
             })
             .unwrap_or_else(|| formats[0])
     }
-    fn get_swapchain_present_mode(present_modes: &[PresentModeKHR]) -> PresentModeKHR {
-        present_modes
+    /// The Vulkan mode for `wanted`, or the closest supported one. FIFO is always supported.
+    fn get_swapchain_present_mode(
+        present_modes: &[PresentModeKHR],
+        wanted: PresentMode,
+    ) -> PresentModeKHR {
+        let preferred: &[PresentModeKHR] = match wanted {
+            PresentMode::Vsync => &[],
+            PresentMode::LowLatency => &[PresentModeKHR::MAILBOX],
+            PresentMode::Uncapped => &[PresentModeKHR::IMMEDIATE, PresentModeKHR::MAILBOX],
+        };
+        let mode = preferred
             .iter()
             .cloned()
-            .find(|m| *m == PresentModeKHR::MAILBOX)
-            .unwrap_or(PresentModeKHR::FIFO)
+            .find(|m| present_modes.contains(m))
+            .unwrap_or(PresentModeKHR::FIFO);
+        if preferred.first().is_some_and(|m| *m != mode) {
+            warn!("Present mode {wanted} isn't supported, using {mode:?}");
+        }
+        mode
     }
     fn get_swapchain_extent(window: &Window, capabilities: SurfaceCapabilitiesKHR) -> Extent2D {
         if capabilities.current_extent.width != u32::MAX {
@@ -154,8 +154,16 @@ pub enum StandardBufferMaps {
     Indices,
     ExtraVertices(usize),
     ExtraIndices(usize),
-    GuiVertices(usize),
-    GuiIndices(usize),
+    /// Every swapchain image gets its own gui buffers, so one can be uploaded while the others
+    /// are still being drawn.
+    GuiVertices {
+        image: usize,
+        primitive: usize,
+    },
+    GuiIndices {
+        image: usize,
+        primitive: usize,
+    },
 }
 
 #[derive(Default, Debug, Clone, Copy, Eq, PartialEq, Hash)]
@@ -175,8 +183,10 @@ impl Display for StandardBufferMaps {
                 f.write_str("StandardBufferMaps::ExtraVertices")
             }
             StandardBufferMaps::ExtraIndices(_) => f.write_str("StandardBufferMaps::ExtraIndices"),
-            StandardBufferMaps::GuiVertices(_) => f.write_str("StandardBufferMaps::GuiVertices"),
-            StandardBufferMaps::GuiIndices(_) => f.write_str("StandardBufferMaps::GuiIndices"),
+            StandardBufferMaps::GuiVertices { .. } => {
+                f.write_str("StandardBufferMaps::GuiVertices")
+            }
+            StandardBufferMaps::GuiIndices { .. } => f.write_str("StandardBufferMaps::GuiIndices"),
         }
     }
 }
@@ -54,12 +54,9 @@ pub struct ImageData {
 }
 
 impl ImageData {
-    pub fn from_path(
-        instance: &Instance,
-        device: &Device,
-        data: &mut VulkanData,
-        path: &str,
-    ) -> Result<Self> {
+    /// Decode a png without touching vulkan, so it can be done on a
+    /// [`TaskPool`](crate::ecs::tasks::TaskPool) worker.
+    pub fn load(path: &str) -> Result<Self> {
         let image = File::open(path)?;
 
         let decoder = png::Decoder::new(image);
@@ -0,0 +1,19 @@
+//! The engine half of gristmill: the ECS, the renderer and logging.
+//!
+//! The game itself is the `gristmill` binary in `main.rs`, see `examples/` for the smallest apps
+//! that can be built on top of this.
+
+#![feature(never_type, trait_alias, mapped_lock_guards, lock_value_accessors)]
+
+extern crate pretty_env_logger;
+// Lets the derive macros refer to `::gristmill` from inside of this crate
+extern crate self as gristmill;
+
+pub mod cli;
+pub mod config;
+pub mod ecs;
+#[cfg(feature = "render")]
+pub mod engine;
+mod init;
+pub mod logging;
+pub mod prelude;
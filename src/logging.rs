@@ -6,7 +6,7 @@ use backtrace::Backtrace;
 use lazy_static::lazy_static;
 
 use env_logger::fmt::{Color, Style, StyledValue};
-use log::{Level, LevelFilter};
+use log::{Level, LevelFilter, warn};
 
 #[cfg(debug_assertions)]
 lazy_static! {
@@ -51,24 +51,9 @@ pub fn setup_logging() {
     let _ = BT;
 
     pretty_env_logger::formatted_builder()
-        .filter_level({
-            // There is multiple logging levels in highest priority to lowest
-            // error
-            // warn
-            // info
-            // debug
-            // trace
-            // off (no logs)
-            match env::var("RUST_LOG").unwrap_or("info".to_string()).as_str() {
-                "error" => LevelFilter::Error,
-                "warn" => LevelFilter::Warn,
-                "info" => LevelFilter::Info,
-                "debug" => LevelFilter::Debug,
-                "trace" => LevelFilter::Trace,
-                "off" => LevelFilter::Off,
-                _ => LevelFilter::Info,
-            }
-        })
+        // Everything gets through the logger, `log::max_level` does the filtering so the level
+        // can be changed later with `set_level`
+        .filter_level(LevelFilter::Trace)
         .format(|f, record| {
             use std::io::Write;
 
@@ -135,6 +120,36 @@ pub fn setup_logging() {
             }
         })
         .init();
+
+    let level = env::var("RUST_LOG").unwrap_or_default();
+    log::set_max_level(parse_level(&level).unwrap_or(LevelFilter::Info));
+}
+
+/// Change the log level once logging is set up, e.g. from the engine config.
+pub fn set_level(level: &str) {
+    match parse_level(level) {
+        Some(level) => log::set_max_level(level),
+        None => warn!("Unknown log level {level:?}, keeping {}", log::max_level()),
+    }
+}
+
+/// There is multiple logging levels in highest priority to lowest
+/// error
+/// warn
+/// info
+/// debug
+/// trace
+/// off (no logs)
+fn parse_level(level: &str) -> Option<LevelFilter> {
+    Some(match level.to_lowercase().as_str() {
+        "error" => LevelFilter::Error,
+        "warn" => LevelFilter::Warn,
+        "info" => LevelFilter::Info,
+        "debug" => LevelFilter::Debug,
+        "trace" => LevelFilter::Trace,
+        "off" => LevelFilter::Off,
+        _ => return None,
+    })
 }
 
 // https://github.com/seanmonstar/pretty-env-logger
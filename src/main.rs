@@ -1,52 +1,68 @@
-#![feature(never_type, trait_alias, mapped_lock_guards, lock_value_accessors)]
-
-extern crate pretty_env_logger;
-
-use std::time::Instant;
-
-use crate::ecs::order_up::OrderUp;
-use crate::ecs::{Manager, System, WinitEventSystem, World};
-use crate::engine::{engine_main, engine_partial};
-use crate::logging::setup_logging;
-use crate::systems::movement::movement_partial;
 use anyhow::Result;
+use gristmill::cli;
+use gristmill::config::EngineConfig;
+use gristmill::ecs::Manager;
+use gristmill::ecs::actions::actions_partial;
+use gristmill::ecs::camera::{Camera2D, camera_partial};
+use gristmill::ecs::camera_controller::camera_controller_partial;
+use gristmill::ecs::input_state::input_partial;
+use gristmill::ecs::picking::{TileGrid, picking_partial};
+use gristmill::ecs::prefab::Prefabs;
+use gristmill::ecs::spatial::{GridPosition, spatial_partial};
+use gristmill::ecs::tasks::tasks_partial;
+#[cfg(feature = "gui")]
+use gristmill::engine::controls::controls_partial;
+use gristmill::engine::engine_partial;
+use gristmill::logging::setup_logging;
+use log::info;
+use std::process::ExitCode;
+
+#[cfg(feature = "gui")]
+use crate::systems::actions::GameAction;
+use crate::systems::actions::default_bindings;
+use crate::systems::camera::{camera_actions, focus_partial};
 
-mod ecs;
-mod engine;
-mod init;
-mod logging;
 mod systems;
 
-fn main() -> Result<()> {
+fn main() -> Result<ExitCode> {
     setup_logging();
 
-    let manager = Manager::new()?
+    let config = EngineConfig::load()?;
+    let camera_controls = config.camera.clone();
+    let seed = config.seed;
+    let mut manager = Manager::new()?
+        .with_seed(seed)
+        .add_resource(config)
+        .register_reflect_type::<EngineConfig>("engine_config")
+        .add_resource(Prefabs::load_default()?)
+        .integrate(input_partial())?
+        // Before every plugin with shutdown systems, so the renderer is torn down last
         .integrate(engine_partial())?
-        .add_systems((update_delta_time as System,).order_up())
-        .add_resource(DeltaTime(0.0))
-        .add_resource(LastTime(Instant::now()))
-        .integrate(movement_partial())?;
+        .integrate(spatial_partial::<GridPosition>())?
+        .integrate(tasks_partial())?
+        .integrate(actions_partial(default_bindings().load_user_bindings()))?;
+    #[cfg(feature = "gui")]
+    {
+        manager = manager.integrate(controls_partial::<GameAction>())?;
+    }
+    manager = manager
+        .integrate(camera_controller_partial(camera_actions(), camera_controls))?
+        .integrate(focus_partial())?
+        .integrate(camera_partial(Camera2D::new()))?
+        .integrate(picking_partial(TileGrid::default()))?;
 
-    manager.run()?;
+    if let Some(path) = cli::value("record") {
+        manager = manager.record_input(path);
+    }
+    if let Some(path) = cli::value("replay") {
+        manager = manager.replay_input(path)?;
+    }
 
-    Ok(())
-}
-
-pub struct DeltaTime(pub f32);
-pub struct LastTime(Instant);
-
-pub fn update_delta_time(world: &World) -> Result<()> {
-    let now = Instant::now();
-
-    let dt = {
-        let mut t = world.get_resource_mut::<LastTime>();
-        let delta = now - t.0;
-        t.0 = now;
-        delta.as_secs_f32()
+    let exit = match cli::flag("headless") {
+        true => manager.run_headless()?,
+        false => manager.run()?,
     };
+    info!("Exiting with code {}", exit.code());
 
-    let mut delta_time = world.get_resource_mut::<DeltaTime>();
-    delta_time.0 = dt;
-
-    Ok(())
+    Ok(exit.into())
 }
@@ -0,0 +1,28 @@
+//! Everything a plugin usually needs, `use gristmill::prelude::*;`.
+
+pub use anyhow::Result;
+pub use log::*;
+
+pub use crate::config::EngineConfig;
+pub use crate::ecs::{
+    Event, EventLoopWindowTarget, EventSystem, InputSystem, Manager, System, WinitEventSystem,
+    World,
+    actions::{Action, ActionState, InputMap},
+    camera::Camera2D,
+    camera_controller::{CameraActions, CameraControls, FocusCamera},
+    events::{EcsEvent, EcsEventData, EventHandler, LemgineEventData},
+    exit::AppExit,
+    filter::{With, Without},
+    input::{InputCapture, InputEvent, KeyCode, MouseButton},
+    input_state::{ButtonInput, CursorPosition, MouseMotion, MouseScroll},
+    name::Name,
+    order_up::OrderUp,
+    partial_manager::PartialManager,
+    picking::{PickData, PickEvent, Picked, SpriteBounds, TileGrid},
+    rng::GameRng,
+    spatial::{GridPosition, SpatialIndex},
+    tasks::{CancelToken, TaskId, TaskPool},
+    time::Time,
+};
+#[cfg(feature = "render")]
+pub use crate::engine::Engine;
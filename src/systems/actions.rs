@@ -0,0 +1,56 @@
+use gristmill::ecs::{
+    actions::{Action, InputMap},
+    input::{KeyCode, MouseButton},
+};
+
+/// Everything the player can do, rebindable in the controls window (F10).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GameAction {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    ZoomIn,
+    ZoomOut,
+    /// Point the camera at whatever is under the cursor.
+    Focus,
+    Rotate,
+    PlaceBuilding,
+    OpenInventory,
+}
+
+impl Action for GameAction {
+    const ALL: &'static [Self] = &[
+        GameAction::MoveUp,
+        GameAction::MoveDown,
+        GameAction::MoveLeft,
+        GameAction::MoveRight,
+        GameAction::ZoomIn,
+        GameAction::ZoomOut,
+        GameAction::Focus,
+        GameAction::Rotate,
+        GameAction::PlaceBuilding,
+        GameAction::OpenInventory,
+    ];
+}
+
+pub fn default_bindings() -> InputMap<GameAction> {
+    InputMap::new()
+        .bind(GameAction::MoveUp, KeyCode::KeyW)
+        .bind(GameAction::MoveUp, KeyCode::ArrowUp)
+        .bind(GameAction::MoveDown, KeyCode::KeyS)
+        .bind(GameAction::MoveDown, KeyCode::ArrowDown)
+        .bind(GameAction::MoveLeft, KeyCode::KeyA)
+        .bind(GameAction::MoveLeft, KeyCode::ArrowLeft)
+        .bind(GameAction::MoveRight, KeyCode::KeyD)
+        .bind(GameAction::MoveRight, KeyCode::ArrowRight)
+        .bind(GameAction::ZoomIn, KeyCode::Equal)
+        .bind(GameAction::ZoomIn, KeyCode::NumpadAdd)
+        .bind(GameAction::ZoomOut, KeyCode::Minus)
+        .bind(GameAction::ZoomOut, KeyCode::NumpadSubtract)
+        .bind(GameAction::Focus, KeyCode::KeyF)
+        .bind(GameAction::Rotate, KeyCode::KeyR)
+        .bind(GameAction::PlaceBuilding, MouseButton::Left)
+        .bind(GameAction::OpenInventory, KeyCode::KeyI)
+        .bind(GameAction::OpenInventory, KeyCode::Tab)
+}
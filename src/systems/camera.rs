@@ -0,0 +1,43 @@
+use anyhow::Result;
+
+use gristmill::ecs::{
+    System, World,
+    actions::ActionState,
+    camera_controller::{CameraActions, FocusCamera},
+    exit::NoEventData,
+    order_up::OrderUp,
+    partial_manager::PartialManager,
+    picking::Picked,
+};
+
+use crate::systems::actions::GameAction;
+
+/// Which [`GameAction`]s move the camera.
+pub fn camera_actions() -> CameraActions<GameAction> {
+    CameraActions {
+        pan_up: GameAction::MoveUp,
+        pan_down: GameAction::MoveDown,
+        pan_left: GameAction::MoveLeft,
+        pan_right: GameAction::MoveRight,
+        zoom_in: GameAction::ZoomIn,
+        zoom_out: GameAction::ZoomOut,
+    }
+}
+
+pub fn focus_partial() -> PartialManager {
+    PartialManager::new().add_systems((focus_hovered as System,).order_up())
+}
+
+/// Jumps to whatever is under the cursor on [`GameAction::Focus`].
+pub fn focus_hovered(world: &World) -> Result<()> {
+    if !world
+        .get_resource::<ActionState<GameAction>>()
+        .just_pressed(GameAction::Focus)
+    {
+        return Ok(());
+    }
+    if let Some(entity) = world.get_resource::<Picked>().entity {
+        world.raise_event(FocusCamera(entity), NoEventData);
+    }
+    Ok(())
+}
@@ -1,2 +1,2 @@
-pub mod movement;
-pub mod prelude;
+pub mod actions;
+pub mod camera;
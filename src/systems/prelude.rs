@@ -1,14 +0,0 @@
-pub use anyhow::Result;
-pub use log::*;
-pub use winit::{event::Event, event_loop::EventLoopWindowTarget};
-
-pub use crate::{
-    DeltaTime,
-    ecs::{
-        EventSystem, System, WinitEventSystem, World,
-        events::{EcsEvent, EcsEventData, LemgineEventData},
-        order_up::OrderUp,
-        partial_manager::PartialManager,
-    },
-    engine::Engine,
-};